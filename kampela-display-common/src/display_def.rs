@@ -5,3 +5,6 @@ pub const SCREEN_SIZE: Size = Size{width: SCREEN_SIZE_X, height: SCREEN_SIZE_Y};
 pub const SCREEN_ZERO: Point = Point{x: 0, y: 0};
 pub const SCREEN_AREA: Rectangle = Rectangle{top_left: SCREEN_ZERO, size: SCREEN_SIZE};
 
+/// Network logos are square 1-bit images, stored row by row, MSB first
+pub const NETWORK_LOGO_SIDE: u32 = 16;
+pub const NETWORK_LOGO_LEN: usize = (NETWORK_LOGO_SIDE * NETWORK_LOGO_SIDE / 8) as usize;
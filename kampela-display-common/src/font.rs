@@ -0,0 +1,282 @@
+//! Compact proportional bitmap font, read glyph by glyph from storage
+//!
+//! Font image is built on host from BDF (see `kampela-host font`) and kept in external flash, so
+//! only glyphs being drawn are read. Layout:
+//!
+//! - header, [`HEADER_LEN`] bytes: magic, line height, ascent, glyph count (u16 LE)
+//! - index, [`INDEX_ENTRY_LEN`] bytes per glyph, sorted by codepoint, see [`Glyph`]
+//! - bitmaps, row by row, each row padded to whole bytes, most significant bit first, set bit is
+//!   ink
+
+pub const FONT_MAGIC: [u8; 4] = *b"KFN1";
+pub const HEADER_LEN: usize = 8;
+pub const INDEX_ENTRY_LEN: usize = 12;
+
+/// Longest glyph row, bytes; wider glyphs are rejected by generator, and not found in image by
+/// [`Font::glyph`]
+pub const MAX_ROW_LEN: usize = 4;
+
+/// Random access to font image
+pub trait FontStorage {
+    fn read(&self, offset: u32, data: &mut [u8]);
+}
+
+impl<T: FontStorage + ?Sized> FontStorage for &T {
+    fn read(&self, offset: u32, data: &mut [u8]) {
+        (**self).read(offset, data)
+    }
+}
+
+impl FontStorage for [u8] {
+    /// Bytes past the end read as erased flash
+    fn read(&self, offset: u32, data: &mut [u8]) {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = self.get(offset as usize + i).copied().unwrap_or(0xff);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FontHeader {
+    /// Distance between baselines of adjacent lines
+    pub line_height: u8,
+    /// Distance from line top to baseline
+    pub ascent: u8,
+    pub glyph_count: u16,
+}
+
+impl FontHeader {
+    pub fn encode(&self) -> [u8; HEADER_LEN] {
+        let count = self.glyph_count.to_le_bytes();
+        [FONT_MAGIC[0], FONT_MAGIC[1], FONT_MAGIC[2], FONT_MAGIC[3], self.line_height, self.ascent, count[0], count[1]]
+    }
+
+    /// `None` if there is no font image, e.g. flash is erased
+    pub fn decode(data: &[u8; HEADER_LEN]) -> Option<Self> {
+        if data[..4] != FONT_MAGIC { return None }
+        Some(Self {
+            line_height: data[4],
+            ascent: data[5],
+            glyph_count: u16::from_le_bytes([data[6], data[7]]),
+        })
+    }
+}
+
+/// Glyph metrics and bitmap position
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Glyph {
+    pub codepoint: u32,
+    /// Pen movement after glyph
+    pub advance: u8,
+    pub width: u8,
+    pub height: u8,
+    /// Bitmap left edge relative to pen
+    pub x_offset: i8,
+    /// Bitmap top edge relative to line top
+    pub y_offset: i8,
+    /// Bitmap offset from font image start
+    pub bitmap: u32,
+}
+
+impl Glyph {
+    pub fn row_len(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+
+    pub fn bitmap_len(&self) -> usize {
+        self.row_len() * self.height as usize
+    }
+
+    pub fn encode(&self) -> [u8; INDEX_ENTRY_LEN] {
+        let codepoint = self.codepoint.to_le_bytes();
+        let bitmap = self.bitmap.to_le_bytes();
+        [
+            codepoint[0], codepoint[1], codepoint[2],
+            self.advance, self.width, self.height, self.x_offset as u8, self.y_offset as u8,
+            bitmap[0], bitmap[1], bitmap[2], bitmap[3],
+        ]
+    }
+
+    pub fn decode(data: &[u8; INDEX_ENTRY_LEN]) -> Self {
+        Self {
+            codepoint: u32::from_le_bytes([data[0], data[1], data[2], 0]),
+            advance: data[3],
+            width: data[4],
+            height: data[5],
+            x_offset: data[6] as i8,
+            y_offset: data[7] as i8,
+            bitmap: u32::from_le_bytes([data[8], data[9], data[10], data[11]]),
+        }
+    }
+}
+
+/// Font image in storage
+pub struct Font<S: FontStorage> {
+    storage: S,
+    header: FontHeader,
+}
+
+impl<S: FontStorage> Font<S> {
+    /// `None` if storage holds no font image
+    pub fn new(storage: S) -> Option<Self> {
+        let mut data = [0u8; HEADER_LEN];
+        storage.read(0, &mut data);
+        let header = FontHeader::decode(&data)?;
+        Some(Self {
+            storage,
+            header,
+        })
+    }
+
+    pub fn line_height(&self) -> u32 {
+        self.header.line_height as u32
+    }
+
+    pub fn ascent(&self) -> u32 {
+        self.header.ascent as u32
+    }
+
+    fn glyph_at(&self, index: u16) -> Glyph {
+        let mut data = [0u8; INDEX_ENTRY_LEN];
+        self.storage.read((HEADER_LEN + index as usize * INDEX_ENTRY_LEN) as u32, &mut data);
+        Glyph::decode(&data)
+    }
+
+    /// Binary search through index; glyphs wider than [`MAX_ROW_LEN`] could not be drawn and are
+    /// treated as missing
+    pub fn glyph(&self, c: char) -> Option<Glyph> {
+        let codepoint = c as u32;
+        let (mut low, mut high) = (0u16, self.header.glyph_count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let glyph = self.glyph_at(middle);
+            match glyph.codepoint.cmp(&codepoint) {
+                core::cmp::Ordering::Equal => return Some(glyph).filter(|glyph| glyph.row_len() <= MAX_ROW_LEN),
+                core::cmp::Ordering::Less => low = middle + 1,
+                core::cmp::Ordering::Greater => high = middle,
+            }
+        }
+        None
+    }
+
+    /// Glyph bitmap row; `data` should hold [`Glyph::row_len`] bytes, row is cut to `data` length
+    /// otherwise
+    pub fn read_row(&self, glyph: &Glyph, row: u8, data: &mut [u8]) {
+        let offset = glyph.bitmap + (glyph.row_len() * row as usize) as u32;
+        let len = glyph.row_len().min(data.len());
+        self.storage.read(offset, &mut data[..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: FontHeader = FontHeader {
+        line_height: 12,
+        ascent: 9,
+        glyph_count: 3,
+    };
+
+    const BITMAPS_START: u32 = (HEADER_LEN + 3 * INDEX_ENTRY_LEN) as u32;
+
+    /// Sorted by codepoint; last one is too wide to be drawn
+    const GLYPHS: [Glyph; 3] = [
+        Glyph { codepoint: 'A' as u32, advance: 4, width: 3, height: 2, x_offset: 0, y_offset: 7, bitmap: BITMAPS_START },
+        Glyph { codepoint: 'é' as u32, advance: 10, width: 9, height: 1, x_offset: -1, y_offset: 3, bitmap: BITMAPS_START + 2 },
+        Glyph { codepoint: '€' as u32, advance: 41, width: 40, height: 1, x_offset: 0, y_offset: 0, bitmap: BITMAPS_START + 4 },
+    ];
+
+    const BITMAPS: [u8; 9] = [
+        0b1010_0000, 0b0100_0000,
+        0xff, 0x80,
+        0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+    ];
+
+    const IMAGE_LEN: usize = BITMAPS_START as usize + BITMAPS.len();
+
+    fn image() -> [u8; IMAGE_LEN] {
+        let mut image = [0u8; IMAGE_LEN];
+        image[..HEADER_LEN].copy_from_slice(&HEADER.encode());
+        for (i, glyph) in GLYPHS.iter().enumerate() {
+            image[HEADER_LEN + i * INDEX_ENTRY_LEN..][..INDEX_ENTRY_LEN].copy_from_slice(&glyph.encode());
+        }
+        image[BITMAPS_START as usize..].copy_from_slice(&BITMAPS);
+        image
+    }
+
+    #[test]
+    fn header_round_trip() {
+        assert_eq!(FontHeader::decode(&HEADER.encode()), Some(HEADER));
+        assert_eq!(FontHeader::decode(&[0xff; HEADER_LEN]), None);
+    }
+
+    #[test]
+    fn glyph_round_trip() {
+        let glyph = Glyph { codepoint: 0x10ffff, advance: 255, width: 32, height: 20, x_offset: -3, y_offset: -5, bitmap: 0x0102_0304 };
+        assert_eq!(Glyph::decode(&glyph.encode()), glyph);
+        for glyph in GLYPHS {
+            assert_eq!(Glyph::decode(&glyph.encode()), glyph);
+        }
+    }
+
+    #[test]
+    fn erased_storage_holds_no_font() {
+        assert!(Font::new(&[0xff; 64][..]).is_none());
+        assert!(Font::new(&[0u8; 0][..]).is_none());
+    }
+
+    #[test]
+    fn glyphs_are_found() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        assert_eq!(font.line_height(), 12);
+        assert_eq!(font.ascent(), 9);
+        assert_eq!(font.glyph('A'), Some(GLYPHS[0]));
+        assert_eq!(font.glyph('é'), Some(GLYPHS[1]));
+        for c in ['@', 'B', 'a', 'ê', '₿'] {
+            assert_eq!(font.glyph(c), None, "{c}");
+        }
+    }
+
+    #[test]
+    fn too_wide_glyph_is_missing() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        assert!(GLYPHS[2].row_len() > MAX_ROW_LEN);
+        assert_eq!(font.glyph('€'), None);
+    }
+
+    #[test]
+    fn empty_font_has_no_glyphs() {
+        let header = FontHeader { glyph_count: 0, ..HEADER };
+        let image = header.encode();
+        let font = Font::new(&image[..]).unwrap();
+        assert_eq!(font.glyph('A'), None);
+    }
+
+    #[test]
+    fn rows_are_read() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        let mut row = [0u8; MAX_ROW_LEN];
+        font.read_row(&GLYPHS[0], 0, &mut row);
+        assert_eq!(row[0], 0b1010_0000);
+        font.read_row(&GLYPHS[0], 1, &mut row);
+        assert_eq!(row[0], 0b0100_0000);
+        font.read_row(&GLYPHS[1], 0, &mut row);
+        assert_eq!(row[..2], [0xff, 0x80]);
+    }
+
+    #[test]
+    fn rows_are_cut_to_buffer() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        let mut row = [0u8; MAX_ROW_LEN];
+        font.read_row(&GLYPHS[2], 0, &mut row);
+        assert_eq!(row, [0xaa; MAX_ROW_LEN]);
+        let mut short = [0u8; 1];
+        font.read_row(&GLYPHS[1], 0, &mut short);
+        assert_eq!(short, [0xff]);
+    }
+}
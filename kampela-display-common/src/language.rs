@@ -0,0 +1,39 @@
+//! Language of UI strings, kept in settings
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Language {
+    /// All languages, in order of their code
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    /// Code for storage
+    pub fn code(&self) -> u8 {
+        match self {
+            Language::English => 0,
+            Language::Spanish => 1,
+        }
+    }
+
+    /// Unknown codes, e.g. of erased flash, give default language
+    pub fn from_code(code: u8) -> Self {
+        Self::ALL.get(code as usize).copied().unwrap_or_default()
+    }
+
+    /// Language name in that language
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+        }
+    }
+
+    /// Next language in order, for switching through all of them
+    pub fn next(&self) -> Self {
+        Self::ALL[(self.code() as usize + 1) % Self::ALL.len()]
+    }
+}
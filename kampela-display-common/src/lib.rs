@@ -1,2 +1,5 @@
 #![no_std]
 pub mod display_def;
+pub mod font;
+pub mod language;
+pub mod orientation;
@@ -0,0 +1,144 @@
+//! Screen orientation, for drawing and touch alike
+//!
+//! Panel coordinates are native landscape ones, [`SCREEN_SIZE`] wide and tall. UI coordinates
+//! are panel ones rotated clockwise by orientation angle.
+//!
+//! Only landscape orientations are offered, so UI size is [`SCREEN_SIZE`] in any of them:
+//! screen layouts are made for landscape size, and would be clipped in portrait.
+
+use embedded_graphics_core::{geometry::{Point, Size}, primitives::Rectangle};
+
+use crate::display_def::*;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Orientation {
+    /// Native landscape
+    #[default]
+    Deg0,
+    /// Landscape, flipped
+    Deg180,
+}
+
+const MAX_X: i32 = SCREEN_SIZE_X as i32 - 1;
+const MAX_Y: i32 = SCREEN_SIZE_Y as i32 - 1;
+
+impl Orientation {
+    /// All orientations, in order of their code
+    pub const ALL: [Orientation; 2] = [Orientation::Deg0, Orientation::Deg180];
+
+    /// Two-bit code for storage, quarter turns clockwise
+    pub fn code(&self) -> u8 {
+        match self {
+            Orientation::Deg0 => 0,
+            Orientation::Deg180 => 2,
+        }
+    }
+
+    /// `None` for portrait codes, which are not supported
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|orientation| orientation.code() == code)
+    }
+
+    /// Orientation switched to in settings
+    pub fn next(&self) -> Self {
+        match self {
+            Orientation::Deg0 => Orientation::Deg180,
+            Orientation::Deg180 => Orientation::Deg0,
+        }
+    }
+
+    /// Rotation angle, in degrees
+    pub fn degrees(&self) -> u16 {
+        match self {
+            Orientation::Deg0 => 0,
+            Orientation::Deg180 => 180,
+        }
+    }
+
+    /// Panel point for UI point; points off screen stay off screen
+    pub fn to_panel(&self, point: Point) -> Point {
+        match self {
+            Orientation::Deg0 => point,
+            Orientation::Deg180 => Point::new(MAX_X - point.x, MAX_Y - point.y),
+        }
+    }
+
+    /// UI point for panel point, e.g. touch
+    pub fn from_panel(&self, point: Point) -> Point {
+        match self {
+            Orientation::Deg0 => point,
+            Orientation::Deg180 => Point::new(MAX_X - point.x, MAX_Y - point.y),
+        }
+    }
+
+    /// Panel rectangle for UI rectangle
+    pub fn rectangle_to_panel(&self, rectangle: Rectangle) -> Rectangle {
+        match rectangle.bottom_right() {
+            Some(bottom_right) => Rectangle::with_corners(self.to_panel(rectangle.top_left), self.to_panel(bottom_right)),
+            None => Rectangle::new(self.to_panel(rectangle.top_left), Size::zero()),
+        }
+    }
+
+    /// UI rectangle for panel rectangle
+    pub fn rectangle_from_panel(&self, rectangle: Rectangle) -> Rectangle {
+        match rectangle.bottom_right() {
+            Some(bottom_right) => Rectangle::with_corners(self.from_panel(rectangle.top_left), self.from_panel(bottom_right)),
+            None => Rectangle::new(self.from_panel(rectangle.top_left), Size::zero()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORNERS: [Point; 4] = [
+        Point::new(0, 0),
+        Point::new(MAX_X, 0),
+        Point::new(0, MAX_Y),
+        Point::new(MAX_X, MAX_Y),
+    ];
+
+    #[test]
+    fn panel_round_trip() {
+        for orientation in Orientation::ALL {
+            for x in (-3..=MAX_X + 3).step_by(7) {
+                for y in (-3..=MAX_Y + 3).step_by(5) {
+                    let point = Point::new(x, y);
+                    assert_eq!(orientation.from_panel(orientation.to_panel(point)), point, "{orientation:?}");
+                    assert_eq!(orientation.to_panel(orientation.from_panel(point)), point, "{orientation:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn screen_maps_onto_panel() {
+        for orientation in Orientation::ALL {
+            for corner in CORNERS {
+                assert!(SCREEN_AREA.contains(corner));
+                assert!(SCREEN_AREA.contains(orientation.to_panel(corner)), "{orientation:?} {corner:?}");
+            }
+        }
+        assert_eq!(Orientation::Deg180.to_panel(Point::new(0, 0)), Point::new(MAX_X, MAX_Y));
+    }
+
+    #[test]
+    fn rectangle_round_trip() {
+        let rectangle = Rectangle::new(Point::new(10, 20), Size::new(30, 8));
+        for orientation in Orientation::ALL {
+            let panel = orientation.rectangle_to_panel(rectangle);
+            assert_eq!(panel.size, rectangle.size);
+            assert_eq!(orientation.rectangle_from_panel(panel), rectangle);
+        }
+    }
+
+    #[test]
+    fn codes_round_trip_and_portrait_is_rejected() {
+        for orientation in Orientation::ALL {
+            assert_eq!(Orientation::from_code(orientation.code()), Some(orientation));
+        }
+        assert_eq!(Orientation::from_code(1), None);
+        assert_eq!(Orientation::from_code(3), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Font image for device flash, built from BDF
+//!
+//! TTF and OTF fonts are rendered to BDF at chosen pixel size first, e.g. with `otf2bdf`.
+
+use std::collections::BTreeMap;
+
+use kampela_ui::font::{Font, FontHeader, Glyph, HEADER_LEN, INDEX_ENTRY_LEN, MAX_ROW_LEN};
+
+/// Symbols beyond Latin-1 that show up in chain names, token units and remarks
+const SYMBOLS: &[char] = &[
+    '–', '—', '‘', '’', '‚', '“', '”', '„', '•', '…', '‰', '€', '₿', '™',
+    '←', '↑', '→', '↓', '↔', '−', '∞', '≈', '≠', '≤', '≥', '✓', '✗', '�',
+];
+
+/// Characters to take from BDF: printable ASCII, Latin-1 and [`SYMBOLS`]
+pub fn charset() -> impl Iterator<Item = char> {
+    (' '..='~').chain('\u{A0}'..='ÿ').chain(SYMBOLS.iter().copied())
+}
+
+struct BdfGlyph {
+    advance: u8,
+    width: u8,
+    height: u8,
+    x_offset: i8,
+    /// Bottom edge relative to baseline, upwards
+    y_offset: i8,
+    bitmap: Vec<u8>,
+}
+
+struct Bdf {
+    ascent: u8,
+    descent: u8,
+    glyphs: BTreeMap<u32, BdfGlyph>,
+}
+
+fn numbers<const N: usize>(line: &str, keyword: &str) -> Result<[i32; N], String> {
+    let values = line
+        .split_whitespace()
+        .skip(1)
+        .map(|a| a.parse::<i32>().map_err(|e| format!("{}: {}", keyword, e)))
+        .collect::<Result<Vec<i32>, String>>()?;
+    values
+        .get(..N)
+        .and_then(|a| a.try_into().ok())
+        .ok_or_else(|| format!("{}: expected {} numbers", keyword, N))
+}
+
+fn narrow<T: TryFrom<i32>>(value: i32, what: &str) -> Result<T, String> {
+    T::try_from(value).map_err(|_| format!("{} {} is out of range", what, value))
+}
+
+fn parse_bdf(text: &str) -> Result<Bdf, String> {
+    let mut ascent = None;
+    let mut descent = None;
+    let mut glyphs = BTreeMap::new();
+
+    let mut encoding: Option<i32> = None;
+    let mut dwidth = 0;
+    let mut bbx = [0i32; 4];
+    let mut bitmap: Option<Vec<u8>> = None;
+
+    for line in text.lines() {
+        let keyword = line.split_whitespace().next().unwrap_or("");
+        match (keyword, bitmap.is_some()) {
+            ("ENDCHAR", true) => {
+                let bitmap = bitmap.take().expect("matched as Some");
+                let [width, height, x_offset, y_offset] = bbx;
+                let row_len = (width as usize).div_ceil(8);
+                if row_len > MAX_ROW_LEN {
+                    return Err(format!("glyph {:?} is {} pixels wide, at most {} supported", encoding, width, MAX_ROW_LEN * 8))
+                }
+                if bitmap.len() != row_len * height as usize {
+                    return Err(format!("glyph {:?} bitmap does not match its bounding box", encoding))
+                }
+                // unencoded glyphs have negative encoding
+                if let Some(codepoint) = encoding.take().filter(|a| *a >= 0) {
+                    glyphs.insert(codepoint as u32, BdfGlyph {
+                        advance: narrow(dwidth, "advance")?,
+                        width: narrow(width, "width")?,
+                        height: narrow(height, "height")?,
+                        x_offset: narrow(x_offset, "x offset")?,
+                        y_offset: narrow(y_offset, "y offset")?,
+                        bitmap,
+                    });
+                }
+            },
+            (_, true) => {
+                let row = hex::decode(line.trim()).map_err(|e| format!("bitmap row {}: {}", line, e))?;
+                let row_len = row.len().min((bbx[0] as usize).div_ceil(8));
+                bitmap.as_mut().expect("matched as set").extend_from_slice(&row[..row_len]);
+            },
+            ("FONT_ASCENT", false) => ascent = Some(numbers::<1>(line, keyword)?[0]),
+            ("FONT_DESCENT", false) => descent = Some(numbers::<1>(line, keyword)?[0]),
+            ("ENCODING", false) => encoding = Some(numbers::<1>(line, keyword)?[0]),
+            ("DWIDTH", false) => dwidth = numbers::<1>(line, keyword)?[0],
+            ("BBX", false) => bbx = numbers::<4>(line, keyword)?,
+            ("BITMAP", false) => bitmap = Some(Vec::new()),
+            _ => (),
+        }
+    }
+    Ok(Bdf {
+        ascent: narrow(ascent.ok_or("FONT_ASCENT is missing")?, "ascent")?,
+        descent: narrow(descent.ok_or("FONT_DESCENT is missing")?, "descent")?,
+        glyphs,
+    })
+}
+
+/// Font image with glyphs of `charset` found in BDF, and characters that were not found
+pub fn build(bdf: &str, charset: impl Iterator<Item = char>) -> Result<(Vec<u8>, Vec<char>), String> {
+    let bdf = parse_bdf(bdf)?;
+    let mut missing = Vec::new();
+    let mut selected = BTreeMap::new();
+    for c in charset {
+        match bdf.glyphs.get(&(c as u32)) {
+            Some(glyph) => { selected.insert(c as u32, glyph); },
+            None => missing.push(c),
+        }
+    }
+
+    let header = FontHeader {
+        line_height: bdf.ascent.checked_add(bdf.descent).ok_or("line height is out of range")?,
+        ascent: bdf.ascent,
+        glyph_count: narrow(selected.len() as i32, "glyph count")?,
+    };
+    let mut index = Vec::with_capacity(selected.len() * INDEX_ENTRY_LEN);
+    let mut bitmaps = Vec::new();
+    let bitmaps_start = HEADER_LEN + selected.len() * INDEX_ENTRY_LEN;
+    for (codepoint, glyph) in selected.iter() {
+        let top = bdf.ascent as i32 - glyph.y_offset as i32 - glyph.height as i32;
+        let entry = Glyph {
+            codepoint: *codepoint,
+            advance: glyph.advance,
+            width: glyph.width,
+            height: glyph.height,
+            x_offset: glyph.x_offset,
+            y_offset: narrow(top, "glyph top")?,
+            bitmap: (bitmaps_start + bitmaps.len()) as u32,
+        };
+        index.extend_from_slice(&entry.encode());
+        bitmaps.extend_from_slice(&glyph.bitmap);
+    }
+
+    let image = [&header.encode()[..], &index, &bitmaps].concat();
+    if image.len() > flash_map::FONT.len() {
+        return Err(format!("font image of {} bytes does not fit {} bytes reserved in flash", image.len(), flash_map::FONT.len()))
+    }
+
+    // device reads image through the same code
+    let font = Font::new(&image[..]).ok_or("font image header is not readable")?;
+    for codepoint in selected.keys() {
+        let c = char::from_u32(*codepoint).expect("taken from chars");
+        if font.glyph(c).map(|glyph| glyph.codepoint) != Some(*codepoint) {
+            return Err(format!("glyph {:?} could not be found in built image", c))
+        }
+    }
+    Ok((image, missing))
+}
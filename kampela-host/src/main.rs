@@ -0,0 +1,154 @@
+//! Host companion for Kampela: builds NFC payloads, checks signatures device returns, and builds
+//! font image for device flash
+//!
+//! Payload is assembled and parsed with firmware code, and LT packets are checked to restore it
+//! through firmware collector, so that generated files could serve as test vectors for both
+//! firmware and simulator.
+#![deny(unused_crate_dependencies)]
+use clap::{Parser, Subcommand};
+use std::{fs, path::{Path, PathBuf}, process::exit};
+use substrate_crypto_light::sr25519::Public;
+
+use kampela_system::parser::{GENESIS_HASH_LEN, PUBLIC_KEY_LEN};
+use kampela_ui::platform::verify_encoded_signature;
+
+mod font;
+mod payload;
+mod qr;
+
+#[derive(Parser, Debug)]
+#[command(about = "Build Kampela NFC payloads, verify signatures and build font images")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Assemble transaction payload and write its LT packet stream
+    Payload {
+        /// Genesis hash of network, hex
+        #[arg(long)]
+        genesis_hash: String,
+
+        /// Shortened metadata file
+        #[arg(long)]
+        metadata: PathBuf,
+
+        /// Unsigned transaction file: compact-prefixed call followed by extensions
+        #[arg(long)]
+        transaction: PathBuf,
+
+        /// Public key of signer, hex
+        #[arg(long)]
+        public_key: String,
+
+        /// Packet stream file: serialized packets one after another
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Also write assembled payload, e.g. for simulator
+        #[arg(long)]
+        payload_output: Option<PathBuf>,
+
+        /// Number of packets; by default twice as many as payload takes
+        #[arg(long)]
+        packets: Option<usize>,
+    },
+    /// Verify signature QR against payload it was made for
+    Verify {
+        /// Payload file, as written by `payload` command
+        #[arg(long)]
+        payload: PathBuf,
+
+        /// Signature QR image
+        #[arg(long)]
+        qr: PathBuf,
+    },
+    /// Build proportional font image from BDF, to be written to device flash at font base
+    Font {
+        /// BDF font; render TTF to BDF first
+        #[arg(long)]
+        bdf: PathBuf,
+
+        /// Font image file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+fn hex_bytes(hex_line: &str) -> Result<Vec<u8>, String> {
+    let hex_line = hex_line.trim();
+    hex::decode(hex_line.strip_prefix("0x").unwrap_or(hex_line)).map_err(|e| format!("invalid hex: {}", e))
+}
+
+fn hex_array<const N: usize>(hex_line: &str) -> Result<[u8; N], String> {
+    hex_bytes(hex_line)?
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("expected {} bytes, got {}", N, bytes.len()))
+}
+
+/// File content, either raw or hex text
+fn read_bytes(path: &Path) -> Result<Vec<u8>, String> {
+    let content = fs::read(path).map_err(|e| format!("{} could not be read: {}", path.display(), e))?;
+    match std::str::from_utf8(&content).ok().map(hex_bytes) {
+        Some(Ok(bytes)) => Ok(bytes),
+        _ => Ok(content),
+    }
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<(), String> {
+    fs::write(path, data).map_err(|e| format!("{} could not be written: {}", path.display(), e))
+}
+
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Payload{genesis_hash, metadata, transaction, public_key, output, payload_output, packets} => {
+            let genesis_hash = hex_array::<GENESIS_HASH_LEN>(&genesis_hash).map_err(|e| format!("genesis hash: {}", e))?;
+            let public_key = hex_array::<PUBLIC_KEY_LEN>(&public_key).map_err(|e| format!("public key: {}", e))?;
+            let payload = payload::assemble(&genesis_hash, &read_bytes(&metadata)?, &read_bytes(&transaction)?, &public_key)?;
+            println!("payload of {} bytes assembled", payload.len());
+            if let Some(path) = payload_output {
+                write(&path, &payload)?;
+            }
+
+            let count = packets.unwrap_or(2 * payload.len().div_ceil(lt_codes::packet::PACKET_SIZE));
+            let packets = payload::packets(&payload, count)?;
+            match payload::restore(&packets)? {
+                Some((used, restored)) if restored == payload => println!("{} packets written, payload restored from first {}", count, used),
+                Some(_) => return Err(String::from("restored payload differs from assembled one")),
+                None => println!("{} packets written, not enough to restore payload", count),
+            }
+            write(&output, &packets.concat())
+        },
+        Command::Verify{payload, qr} => {
+            let payload = fs::read(&payload).map_err(|e| format!("{} could not be read: {}", payload.display(), e))?;
+            let parts = payload::parse(&payload)?;
+            let public = Public(payload[parts.public_key].try_into().expect("static length"));
+            let signature = qr::read_signature(&qr)?;
+            if verify_encoded_signature(&public, &payload::signed_bytes(&payload)?, &signature) {
+                println!("signature is valid");
+                Ok(())
+            } else {
+                Err(String::from("signature is not valid"))
+            }
+        },
+        Command::Font{bdf, output} => {
+            let bdf = fs::read_to_string(&bdf).map_err(|e| format!("{} could not be read: {}", bdf.display(), e))?;
+            let (image, missing) = font::build(&bdf, font::charset())?;
+            if !missing.is_empty() {
+                println!("not in font: {}", missing.iter().collect::<String>());
+            }
+            println!("font image of {} bytes built", image.len());
+            write(&output, &image)
+        },
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(args.command) {
+        println!("{}", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,76 @@
+//! Transaction payload in the format firmware receives through NFC, and its LT packets
+
+use lt_codes::{encoder::Encoder, packet::PACKET_SIZE};
+use parity_scale_codec::Encode;
+
+use kampela_system::{
+    devices::psram::{psram_read_at_address, signing_payload, ExternalPsram},
+    hal::mock::MockPsram,
+    nfc::NfcCollector,
+    parser::{transaction_payload, TransactionPayload, GENESIS_HASH_LEN, PUBLIC_KEY_LEN},
+};
+
+/// Payload type byte for transaction
+const PAYLOAD_TRANSACTION: u8 = 3;
+
+/// Assemble transaction payload and check that firmware parser finds every part in it
+///
+/// Transaction is compact-prefixed call followed by extensions, as it is signed.
+pub fn assemble(
+    genesis_hash: &[u8; GENESIS_HASH_LEN],
+    metadata: &[u8],
+    transaction: &[u8],
+    public_key: &[u8; PUBLIC_KEY_LEN],
+) -> Result<Vec<u8>, String> {
+    let mut payload = vec![PAYLOAD_TRANSACTION];
+    payload.extend_from_slice(genesis_hash);
+    payload.extend_from_slice(&metadata.encode());
+    payload.extend_from_slice(&transaction.encode().encode());
+    payload.extend_from_slice(public_key);
+
+    let parts = parse(&payload)?;
+    if &payload[parts.genesis_hash.clone()] != genesis_hash
+        || &payload[parts.metadata.clone()] != metadata
+        || parts.extension.end - parts.call.start > transaction.len()
+        || !transaction.ends_with(&payload[parts.call.start..parts.extension.end])
+        || &payload[parts.public_key.clone()] != public_key
+    {
+        return Err(String::from("firmware parser finds parts of assembled payload elsewhere"))
+    }
+    Ok(payload)
+}
+
+pub fn parse(payload: &[u8]) -> Result<TransactionPayload, String> {
+    if payload.first() != Some(&PAYLOAD_TRANSACTION) {
+        return Err(String::from("not a transaction payload"))
+    }
+    transaction_payload::<&[u8], ()>(&payload, &mut ()).map_err(|e| format!("payload could not be parsed: {:?}", e))
+}
+
+/// Bytes device signs for payload: call immediately followed by extensions, hashed if long
+pub fn signed_bytes(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let parts = parse(payload)?;
+    Ok(signing_payload(&payload[parts.call.start..parts.extension.end]))
+}
+
+/// Serialized LT packets, in the order they should be transmitted
+pub fn packets(payload: &[u8], count: usize) -> Result<Vec<[u8; PACKET_SIZE]>, String> {
+    let mut encoder = Encoder::init(payload).map_err(|e| format!("payload could not be LT-encoded: {:?}", e))?;
+    Ok((0..count).map(|_| encoder.make_packet().serialize()).collect())
+}
+
+/// Feed packets to the same collector firmware uses; returns number of packets used to restore
+/// payload, if they are enough
+pub fn restore(packets: &[[u8; PACKET_SIZE]]) -> Result<Option<(usize, Vec<u8>)>, String> {
+    let mut psram = MockPsram::growing();
+    let mut collector = NfcCollector::new();
+    for (i, packet) in packets.iter().enumerate() {
+        let mut external_psram = ExternalPsram{psram: &mut psram};
+        collector.add_packet(&mut external_psram, *packet).map_err(|e| format!("packet {} rejected by collector: {:?}", i, e))?;
+        if let NfcCollector::Done(ref data) = collector {
+            let restored = psram_read_at_address(&mut psram, data.start_address, data.len).map_err(|e| format!("restored data could not be read: {:?}", e))?;
+            return Ok(Some((i + 1, restored)))
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,28 @@
+//! Signature QR, as device shows it after signing
+
+use image::{imageops::invert, GrayImage};
+use std::path::Path;
+
+use kampela_ui::platform::ENCODED_SIGNATURE_LEN;
+
+fn decode(image: GrayImage) -> Option<String> {
+    let mut prepared = rqrr::PreparedImage::prepare(image);
+    prepared
+        .detect_grids()
+        .iter()
+        .find_map(|grid| grid.decode().ok().map(|(_, content)| content))
+}
+
+/// Read signature from QR image; screenshots with inverted colors are accepted as well
+pub fn read_signature(path: &Path) -> Result<[u8; ENCODED_SIGNATURE_LEN], String> {
+    let image = image::open(path).map_err(|e| format!("image could not be read: {}", e))?.to_luma8();
+    let mut inverted = image.clone();
+    invert(&mut inverted);
+    let content = decode(image)
+        .or_else(|| decode(inverted))
+        .ok_or_else(|| String::from("no QR code found"))?;
+    content
+        .into_bytes()
+        .try_into()
+        .map_err(|content: Vec<u8>| format!("QR content has length {}, expected signature of length {}", content.len(), ENCODED_SIGNATURE_LEN))
+}
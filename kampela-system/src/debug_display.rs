@@ -27,8 +27,8 @@ use crate::devices::display_transmission::{
     epaper_reset,
     epaper_write_command,
     epaper_write_data,
-    BUFSIZE
 };
+use crate::devices::epd_sequence::BUFSIZE;
 use crate::draw::FrameBuffer;
 //**** Debug stuff ****//
 
@@ -5,10 +5,7 @@ use crate::peripherals::usart::*;
 use crate::peripherals::gpio_pins::{display_res_clear, display_res_set};
 use crate::{FreeError, if_in_free, in_free};
 use crate::parallel::Operation;
-use kampela_display_common::display_def::*;
-
-pub const BUFSIZE: usize = 5808;
-const X_ADDRESS_WIDTH: usize = (SCREEN_SIZE_Y / 8) as usize;
+use crate::hal::EpdBus;
 
 /// BUSY is on port B, pin [`SPI_BUSY_PIN`].
 pub fn display_is_busy() -> Result<bool, FreeError> {
@@ -89,8 +86,27 @@ pub fn epaper_write_data(peripherals: &mut Peripherals, data_set: &[u8]) {
     //    display_data_command_clear(peripherals);
 }
 
-/// Send command `C` to EPD
-pub struct EPDCommand<const C: u8>{
+impl EpdBus for Peripherals {
+    fn is_busy(&mut self) -> bool {
+        display_is_busy_cs(self)
+    }
+
+    fn reset(&mut self) {
+        epaper_reset(&mut self.GPIO_S);
+    }
+
+    fn write_command(&mut self, command_set: &[u8]) {
+        epaper_write_command(self, command_set);
+    }
+
+    fn write_data(&mut self, data_set: &[u8]) {
+        epaper_write_data(self, data_set);
+    }
+}
+
+/// Send command byte to EPD
+pub struct EPDCommand{
+    command: u8,
     state: EPDByteState,
     timer: usize,
 }
@@ -102,14 +118,15 @@ pub enum EPDByteState {
     Aftermath,
 }
 
-impl <const C: u8> Operation for EPDCommand<C> {
-    type Init = ();
+impl Operation for EPDCommand {
+    type Init = u8;
     type Input<'a> = ();
     type Output = bool;
     type StateEnum = EPDByteState;
 
-    fn new(_: ()) -> Self {
+    fn new(command: u8) -> Self {
         Self {
+            command,
             state: EPDByteState::Init,
             timer: 0,
         }
@@ -135,7 +152,7 @@ impl <const C: u8> Operation for EPDCommand<C> {
                         peripherals
                             .USART0_S
                             .txdata
-                            .write(|w_reg| w_reg.txdata().variant(C))
+                            .write(|w_reg| w_reg.txdata().variant(self.command))
                             );
                     self.change(EPDByteState::Aftermath);
                 }
@@ -167,276 +184,3 @@ impl <const C: u8> Operation for EPDCommand<C> {
         }
     }
 }
-
-
-
-/// Send data byte `B` to EPD
-pub struct EPDDataB<const B: u8>{
-    state: EPDByteState,
-    timer: usize,
-}
-
-impl <const B: u8> Operation for EPDDataB<B> {
-    type Init = ();
-    type Input<'a> = ();
-    type Output = bool;
-    type StateEnum = EPDByteState;
-
-    fn new(_: ()) -> Self {
-        Self {
-            state: EPDByteState::Init,
-            timer: 0,
-        }
-    }
-
-    fn wind(&mut self, state: EPDByteState, delay: usize) {
-        self.state = state;
-        self.timer = delay;
-    }
-
-    fn advance(&mut self, _: ()) -> bool {
-        match self.state {
-            EPDByteState::Init => {
-                in_free(|peripherals| {
-                    deselect_display(&mut peripherals.GPIO_S);
-                    select_display(&mut peripherals.GPIO_S); // not necessary if state is known and default at start
-                    display_select_data(&mut peripherals.GPIO_S);
-                });
-                if if_in_free(|peripherals|
-                    peripherals.USART0_S.status.read().txbl().bit_is_clear()
-                ) == Ok(false) {
-                    in_free(|peripherals|
-                        peripherals
-                            .USART0_S
-                            .txdata
-                            .write(|w_reg| w_reg.txdata().variant(B))
-                            );
-                    self.change(EPDByteState::Aftermath);
-                }
-                false
-            },
-            EPDByteState::Aftermath => {
-                if if_in_free(|peripherals|
-                    peripherals
-                        .USART0_S
-                        .status
-                        .read()
-                        .txc()
-                        .bit_is_set()
-                ) == Ok(false) {
-                    false
-                } else {
-                    in_free(|peripherals| {
-                        peripherals
-                            .USART0_S
-                            .rxdata
-                            .read()
-                            .rxdata()
-                            .bits();
-                        deselect_display(&mut peripherals.GPIO_S);
-                    });
-                    true
-                }
-            },
-        }
-    }
-}
-
-/// Send data byte `B` to EPD
-pub struct EPDData<const LEN: usize>{
-    state: EPDDataState,
-    position: usize,
-    timer: usize,
-}
-
-pub enum EPDDataState {
-    /// State where command is actually sent
-    Init,
-    Send,
-    WaitSend,
-    /// Receive something to keep protocol running and close connection
-    Aftermath,
-}
-
-impl <const LEN: usize> Operation for EPDData<LEN> {
-    type Init = ();
-    type Input<'a> = &'a [u8];
-    type Output = bool;
-    type StateEnum = EPDDataState;
-
-    fn new(_: ()) -> Self {
-        Self {
-            state: EPDDataState::Init,
-            position: 0,
-            timer: 0,
-        }
-    }
-
-    fn wind(&mut self, state: EPDDataState, delay: usize) {
-        self.state = state;
-        self.timer = delay;
-    }
-
-    fn advance(&mut self, data: Self::Input<'_>) -> bool {
-        match self.state {
-            EPDDataState::Init => {
-                in_free(|peripherals| {
-                    deselect_display(&mut peripherals.GPIO_S);
-                    select_display(&mut peripherals.GPIO_S); // not necessary if state is known and default at start
-                    display_select_data(&mut peripherals.GPIO_S);
-                });
-                if if_in_free(|peripherals|
-                    peripherals.USART0_S.status.read().txbl().bit_is_clear()
-                ) == Ok(false) {
-                    self.change(EPDDataState::Send);
-                }
-                false
-            },
-            EPDDataState::Send => {
-                in_free(|peripherals|
-                    peripherals
-                        .USART0_S
-                        .txdata
-                        .write(|w_reg| w_reg.txdata().variant(data[self.position]))
-                );
-                self.change(EPDDataState::WaitSend);
-                false
-            },
-            EPDDataState::WaitSend => {
-                if if_in_free(|peripherals|
-                    peripherals
-                        .USART0_S
-                        .status
-                        .read()
-                        .txc()
-                        .bit_is_set()
-                ) == Ok(true) {
-                    in_free(|peripherals| {
-                        peripherals
-                            .USART0_S
-                            .rxdata
-                            .read()
-                            .rxdata()
-                            .bits();
-                    });
-                    if self.position < LEN-1 {
-                        self.position += 1;
-                        self.change(EPDDataState::Send);
-                    } else {
-                        self.change(EPDDataState::Aftermath);
-                    }
-                }
-                false
-            },
-            EPDDataState::Aftermath => {
-                in_free(|peripherals| {
-                    deselect_display(&mut peripherals.GPIO_S);
-                });
-                true
-            },
-        }
-    }
-}
-
-pub struct EPDDataPart<const LEN: usize>{
-    state: EPDDataState,
-    position: usize,
-    x_start_position: usize,
-    x_end_position: usize,
-    y_end_position: usize,
-    timer: usize,
-}
-
-
-
-impl <const LEN: usize> Operation for EPDDataPart<LEN> {
-    type Init = (u8, u8, u16, u16);
-    type Input<'a> = &'a [u8];
-    type Output = bool;
-    type StateEnum = EPDDataState;
-
-    fn new(addresses: Self::Init) -> Self {
-        let x_start_position = addresses.0 as usize;
-        let y_start_position = (SCREEN_SIZE_X - 1) as usize - addresses.2 as usize; //Y coordinates inversed for some reason
-        Self {
-            state: EPDDataState::Init,
-            position: y_start_position * X_ADDRESS_WIDTH as usize + x_start_position,
-            x_start_position,
-            x_end_position: addresses.1 as usize,
-            y_end_position: (SCREEN_SIZE_X - 1) as usize - addresses.3 as usize,
-            timer: 0,
-        }
-    }
-
-    fn wind(&mut self, state: EPDDataState, delay: usize) {
-        self.state = state;
-        self.timer = delay;
-    }
-
-    fn advance(&mut self, data: Self::Input<'_>) -> bool {
-        match self.state {
-            EPDDataState::Init => {
-                in_free(|peripherals| {
-                    deselect_display(&mut peripherals.GPIO_S);
-                    select_display(&mut peripherals.GPIO_S); // not necessary if state is known and default at start
-                    display_select_data(&mut peripherals.GPIO_S);
-                });
-                if if_in_free(|peripherals|
-                    peripherals.USART0_S.status.read().txbl().bit_is_clear()
-                ) == Ok(false) {
-                    self.change(EPDDataState::Send);
-                }
-                false
-            },
-            EPDDataState::Send => {
-                in_free(|peripherals|
-                    peripherals
-                        .USART0_S
-                        .txdata
-                        .write(|w_reg| w_reg.txdata().variant(data[self.position]))
-                );
-                self.change(EPDDataState::WaitSend);
-                false
-            },
-            EPDDataState::WaitSend => {
-                if if_in_free(|peripherals|
-                    peripherals
-                        .USART0_S
-                        .status
-                        .read()
-                        .txc()
-                        .bit_is_set()
-                ) == Ok(true) {
-                    in_free(|peripherals| {
-                        peripherals
-                            .USART0_S
-                            .rxdata
-                            .read()
-                            .rxdata()
-                            .bits();
-                    });
-                    if self.position < X_ADDRESS_WIDTH * self.y_end_position + self.x_end_position {
-                        let y_position = self.position / X_ADDRESS_WIDTH;
-                        let x_position = self.position - y_position * X_ADDRESS_WIDTH;
-                        
-                        if x_position >= self.x_end_position {
-                            self.position = (y_position + 1) * X_ADDRESS_WIDTH + self.x_start_position;
-                        } else {
-                            self.position += 1;
-                        }
-                        self.change(EPDDataState::Send);
-                    } else {
-                        self.change(EPDDataState::Aftermath);
-                    }
-                }
-                false
-            },
-            EPDDataState::Aftermath => {
-                in_free(|peripherals| {
-                    deselect_display(&mut peripherals.GPIO_S);
-                });
-                true
-            },
-        }
-    }
-}
\ No newline at end of file
@@ -0,0 +1,479 @@
+//! EPD command sequences as data
+//!
+//! Every draw or update sequence is a list of stages, each stage a `const` slice of [`EpdOp`].
+//! [`EpdRunner`] runs them on any [`EpdBus`]: on peripherals in firmware, see
+//! [`display`](crate::devices::display) and [`panic_screen`](crate::panic_screen), and on
+//! [`MockEpd`](crate::hal::mock::MockEpd) in tests.
+//!
+//! New LUT or panel needs only new tables here.
+
+use kampela_display_common::display_def::*;
+
+use crate::hal::EpdBus;
+
+pub const BUFSIZE: usize = 5808;
+const X_ADDRESS_WIDTH: usize = (SCREEN_SIZE_Y / 8) as usize;
+
+/// Display RAM window to refresh: X start and end (1 byte each), Y start and end (2 bytes each)
+///
+/// X and Y of display RAM are Y and X of screen due to orientation.
+pub type RamArea = (u8, u8, u16, u16);
+
+/// Sequence: stages run one after another
+pub type Sequence = &'static [&'static [EpdOp]];
+
+/// Whole display RAM, for sequences that do not use area
+pub const FULL_AREA: RamArea = (0, (SCREEN_SIZE_Y / 8 - 1) as u8, (SCREEN_SIZE_X - 1) as u16, 0);
+
+/// Bound of [`RamArea`] to send as data
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AreaBound {
+    XStart,
+    XEnd,
+    YStart,
+    YEnd,
+}
+
+impl AreaBound {
+    /// Little endian bytes of bound and their number
+    pub fn bytes(&self, area: RamArea) -> ([u8; 2], usize) {
+        match self {
+            AreaBound::XStart => ([area.0, 0], 1),
+            AreaBound::XEnd => ([area.1, 0], 1),
+            AreaBound::YStart => (area.2.to_le_bytes(), 2),
+            AreaBound::YEnd => (area.3.to_le_bytes(), 2),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EpdOp {
+    Command(u8),
+    Data(u8),
+    /// Whole frame buffer
+    Buffer,
+    /// Frame buffer within refreshed area only
+    BufferPart,
+    /// Bound of refreshed area
+    Area(AreaBound),
+    /// Wait until BUSY line is released
+    WaitBusy,
+    /// Skip this many advances
+    Delay(usize),
+}
+
+use EpdOp::{Area, Buffer, BufferPart, Command, Data, WaitBusy};
+
+/// Set RAM X&Y address counters to the start of full frame
+const FRAME_START: &[EpdOp] = &[
+    Command(0x4E), Data(0x00),
+    Command(0x4F), Data(0x07),
+];
+
+pub const UPDATE_FULL: &[EpdOp] = &[
+    // bypass RED RAM
+    Command(0x21), Data(0x40), Data(0x00),
+    // set read temperature from internal TS
+    Command(0x18), Data(0x80),
+    Command(0x22), Data(0xF7),
+    WaitBusy,
+    Command(0x20),
+];
+
+/// Update with LUT for 100 degrees, which is the fastest one whatever real temperature is;
+/// unreadable in the cold, see [`FAST_REFRESH_MIN_TEMPERATURE`](crate::thresholds::FAST_REFRESH_MIN_TEMPERATURE)
+pub const UPDATE_FAST: &[EpdOp] = &[
+    // set read from internal temperature sensor
+    Command(0x18), Data(0x80),
+    // set temperature register at 100deg
+    Command(0x1A), Data(0x64), Data(0x00),
+    // load LUT with new temperature value
+    Command(0x22), Data(0x91),
+    WaitBusy,
+    Command(0x20),
+    // bypass RED RAM
+    Command(0x21), Data(0x40), Data(0x00),
+    WaitBusy,
+    // set to display with new LUT
+    Command(0x22), Data(0xC7),
+    WaitBusy,
+    Command(0x20),
+];
+
+pub const UPDATE_ULTRA_FAST: &[EpdOp] = &[
+    // inverse RED RAM (for some reason red ram still used in mode 2)
+    Command(0x21), Data(0x80), Data(0x00),
+    Command(0x22), Data(0xFF),
+    WaitBusy,
+    Command(0x20),
+];
+
+/// Fast draw without full refresh; display should be awake
+pub const FAST_DRAW: Sequence = &[
+    FRAME_START,
+    &[WaitBusy, Command(0x24), Buffer],
+    UPDATE_FAST,
+];
+
+/// Slow draw with full refresh; display should be awake
+pub const FULL_DRAW: Sequence = &[
+    FRAME_START,
+    &[Command(0x24), Buffer],
+    UPDATE_FULL,
+];
+
+/// Draw of refreshed area only; display should be awake
+pub const PART_DRAW: Sequence = &[
+    &[
+        // border waveform
+        Command(0x3C), Data(0x80),
+        // set RAM X address start/end position (which is Y due to orientation)
+        Command(0x44), Area(AreaBound::XStart), Area(AreaBound::XEnd),
+        // set RAM Y address start/end position (which is X due to orientation)
+        Command(0x45), Area(AreaBound::YStart), Area(AreaBound::YEnd),
+        // set RAM X&Y address write starting position
+        Command(0x4E), Area(AreaBound::XStart),
+        Command(0x4F), Area(AreaBound::YStart),
+        Command(0x24), BufferPart,
+        // for some reason red ram still used in mode 2
+        Command(0x26), BufferPart,
+    ],
+    UPDATE_ULTRA_FAST,
+];
+
+/// Position within sequence
+#[derive(Clone, Copy, Debug)]
+pub struct SequenceCursor {
+    sequence: Sequence,
+    stage: usize,
+    op: usize,
+}
+
+impl SequenceCursor {
+    pub fn new(sequence: Sequence) -> Self {
+        let mut out = Self {
+            sequence,
+            stage: 0,
+            op: 0,
+        };
+        out.skip_empty();
+        out
+    }
+
+    fn skip_empty(&mut self) {
+        while self.stage < self.sequence.len() && self.op >= self.sequence[self.stage].len() {
+            self.stage += 1;
+            self.op = 0;
+        }
+    }
+
+    /// Op to run now; `None` when sequence is over
+    pub fn op(&self) -> Option<EpdOp> {
+        self.sequence.get(self.stage).map(|stage| stage[self.op])
+    }
+
+    /// Move to the next op
+    pub fn step(&mut self) {
+        self.op += 1;
+        self.skip_empty();
+    }
+}
+
+/// Frame buffer indices within refreshed area, in the order display expects them
+///
+/// Display RAM Y is inverted relative to frame buffer rows.
+#[derive(Clone, Debug)]
+pub struct PartWindow {
+    next: Option<usize>,
+    x_start_position: usize,
+    x_end_position: usize,
+    y_end_position: usize,
+}
+
+impl PartWindow {
+    pub fn new(area: RamArea) -> Self {
+        let x_start_position = area.0 as usize;
+        let y_start_position = (SCREEN_SIZE_X - 1) as usize - area.2 as usize;
+        Self {
+            next: Some(y_start_position * X_ADDRESS_WIDTH + x_start_position),
+            x_start_position,
+            x_end_position: area.1 as usize,
+            y_end_position: (SCREEN_SIZE_X - 1) as usize - area.3 as usize,
+        }
+    }
+}
+
+impl Iterator for PartWindow {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let position = self.next?;
+        self.next = if position < X_ADDRESS_WIDTH * self.y_end_position + self.x_end_position {
+            let y_position = position / X_ADDRESS_WIDTH;
+            let x_position = position - y_position * X_ADDRESS_WIDTH;
+            if x_position >= self.x_end_position {
+                Some((y_position + 1) * X_ADDRESS_WIDTH + self.x_start_position)
+            } else {
+                Some(position + 1)
+            }
+        } else {
+            None
+        };
+        Some(position)
+    }
+}
+
+/// Data bytes sent per advance, so that frame transfer does not block for long
+pub const DATA_CHUNK: usize = 64;
+
+/// Sequence runner for [`EpdBus`]
+///
+/// Every advance runs one op, or sends next [`DATA_CHUNK`] bytes of op transferring data. BUSY
+/// is polled once per advance, so runner never blocks.
+pub struct EpdRunner {
+    cursor: SequenceCursor,
+    area: RamArea,
+    /// Bytes of current op already sent
+    sent: usize,
+    /// Indices left to send for current [`EpdOp::BufferPart`]
+    window: Option<PartWindow>,
+    timer: usize,
+}
+
+impl EpdRunner {
+    /// `area` is used only by sequences refreshing part of display, e.g. [`PART_DRAW`]
+    pub fn new(sequence: Sequence, area: RamArea) -> Self {
+        Self {
+            cursor: SequenceCursor::new(sequence),
+            area,
+            sent: 0,
+            window: None,
+            timer: 0,
+        }
+    }
+
+    /// Returns `true` when sequence is over
+    pub fn advance<B: EpdBus>(&mut self, bus: &mut B, frame: &[u8]) -> bool {
+        if self.timer != 0 {
+            self.timer -= 1;
+            return false
+        }
+        let op = match self.cursor.op() {
+            Some(op) => op,
+            None => return true,
+        };
+        let op_done = match op {
+            EpdOp::Command(command) => {
+                bus.write_command(&[command]);
+                true
+            },
+            EpdOp::Data(data) => {
+                bus.write_data(&[data]);
+                true
+            },
+            EpdOp::Buffer => self.send(bus, &frame[..BUFSIZE]),
+            EpdOp::BufferPart => {
+                let area = self.area;
+                let window = self.window.get_or_insert_with(|| PartWindow::new(area));
+                let mut chunk = [0; DATA_CHUNK];
+                let len = chunk
+                    .iter_mut()
+                    .zip(window.by_ref())
+                    .fold(0, |len, (byte, i)| {
+                        *byte = frame[i];
+                        len + 1
+                    });
+                bus.write_data(&chunk[..len]);
+                window.next.is_none()
+            },
+            EpdOp::Area(bound) => {
+                let (bytes, len) = bound.bytes(self.area);
+                bus.write_data(&bytes[..len]);
+                true
+            },
+            EpdOp::WaitBusy => !bus.is_busy(),
+            EpdOp::Delay(delay) => {
+                self.timer = delay;
+                true
+            },
+        };
+        if !op_done { return false }
+        self.sent = 0;
+        self.window = None;
+        self.cursor.step();
+        self.cursor.op().is_none()
+    }
+
+    /// Send next chunk of data; returns `true` when all of it is sent
+    fn send<B: EpdBus>(&mut self, bus: &mut B, data: &[u8]) -> bool {
+        let end = data.len().min(self.sent + DATA_CHUNK);
+        bus.write_data(&data[self.sent..end]);
+        self.sent = end;
+        end == data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::{vec, vec::Vec};
+
+    use crate::hal::mock::{EpdTransfer, MockEpd};
+
+    fn c(command: u8) -> EpdTransfer {
+        EpdTransfer::Command(vec![command])
+    }
+
+    fn d(data: &[u8]) -> EpdTransfer {
+        EpdTransfer::Data(data.to_vec())
+    }
+
+    /// Long data as runner sends it, chunk by chunk
+    fn chunked(data: &[u8]) -> Vec<EpdTransfer> {
+        data.chunks(DATA_CHUNK).map(d).collect()
+    }
+
+    fn frame() -> Vec<u8> {
+        (0..BUFSIZE).map(|i| (i % 253) as u8).collect()
+    }
+
+    /// Run sequence to the end; returns what display got and number of advances it took
+    fn run(sequence: Sequence, area: RamArea, busy_polls: usize) -> (Vec<EpdTransfer>, usize) {
+        let mut epd = MockEpd::new(busy_polls);
+        let mut runner = EpdRunner::new(sequence, area);
+        let frame = frame();
+        let mut advances = 1;
+        while !runner.advance(&mut epd, &frame) {
+            advances += 1;
+            assert!(advances < 10_000, "sequence does not end");
+        }
+        (epd.log, advances)
+    }
+
+    // Expected streams below are those of hand-written draw operations that tables replaced, with
+    // long data split into chunks
+
+    fn full_draw_stream() -> Vec<EpdTransfer> {
+        [
+            vec![
+                c(0x4E), d(&[0x00]),
+                c(0x4F), d(&[0x07]),
+                c(0x24),
+            ],
+            chunked(&frame()),
+            vec![
+                c(0x21), d(&[0x40]), d(&[0x00]),
+                c(0x18), d(&[0x80]),
+                c(0x22), d(&[0xF7]),
+                c(0x20),
+            ],
+        ].concat()
+    }
+
+    fn fast_draw_stream() -> Vec<EpdTransfer> {
+        [
+            vec![
+                c(0x4E), d(&[0x00]),
+                c(0x4F), d(&[0x07]),
+                c(0x24),
+            ],
+            chunked(&frame()),
+            vec![
+                c(0x18), d(&[0x80]),
+                c(0x1A), d(&[0x64]), d(&[0x00]),
+                c(0x22), d(&[0x91]),
+                c(0x20),
+                c(0x21), d(&[0x40]), d(&[0x00]),
+                c(0x22), d(&[0xC7]),
+                c(0x20),
+            ],
+        ].concat()
+    }
+
+    /// Frame bytes of area, row by row, with display RAM Y inverted
+    fn part(area: RamArea) -> Vec<u8> {
+        let frame = frame();
+        let width = (SCREEN_SIZE_Y / 8) as usize;
+        let rows = (SCREEN_SIZE_X - 1) as usize - area.2 as usize..=(SCREEN_SIZE_X - 1) as usize - area.3 as usize;
+        rows.flat_map(|y| (area.0 as usize..=area.1 as usize).map(move |x| y * width + x))
+            .map(|i| frame[i])
+            .collect()
+    }
+
+    fn part_draw_stream(area: RamArea) -> Vec<EpdTransfer> {
+        [
+            vec![
+                c(0x3C), d(&[0x80]),
+                c(0x44), d(&[area.0]), d(&[area.1]),
+                c(0x45), d(&area.2.to_le_bytes()), d(&area.3.to_le_bytes()),
+                c(0x4E), d(&[area.0]),
+                c(0x4F), d(&area.2.to_le_bytes()),
+                c(0x24),
+            ],
+            chunked(&part(area)),
+            vec![
+                c(0x26),
+            ],
+            chunked(&part(area)),
+            vec![
+                c(0x21), d(&[0x80]), d(&[0x00]),
+                c(0x22), d(&[0xFF]),
+                c(0x20),
+            ],
+        ].concat()
+    }
+
+    #[test]
+    fn full_draw_matches_baseline() {
+        assert_eq!(run(FULL_DRAW, FULL_AREA, 0).0, full_draw_stream());
+    }
+
+    #[test]
+    fn fast_draw_matches_baseline() {
+        assert_eq!(run(FAST_DRAW, FULL_AREA, 0).0, fast_draw_stream());
+    }
+
+    #[test]
+    fn part_draw_matches_baseline() {
+        for area in [(2, 5, 200, 150), (0, 0, 0, 0), (10, 21, 100, 0), FULL_AREA] {
+            assert_eq!(run(PART_DRAW, area, 0).0, part_draw_stream(area), "area {:?}", area);
+        }
+    }
+
+    #[test]
+    fn busy_display_delays_but_does_not_change_stream() {
+        for (sequence, expected) in [(FULL_DRAW, full_draw_stream()), (FAST_DRAW, fast_draw_stream()), (PART_DRAW, part_draw_stream(FULL_AREA))] {
+            let (idle_log, idle_advances) = run(sequence, FULL_AREA, 0);
+            let (busy_log, busy_advances) = run(sequence, FULL_AREA, 5);
+            assert_eq!(idle_log, expected);
+            assert_eq!(busy_log, expected);
+            assert!(busy_advances > idle_advances);
+        }
+    }
+
+    #[test]
+    fn data_goes_in_chunks() {
+        for sequence in [FULL_DRAW, FAST_DRAW, PART_DRAW] {
+            for transfer in run(sequence, FULL_AREA, 0).0 {
+                if let EpdTransfer::Data(data) = transfer {
+                    assert!(!data.is_empty() && data.len() <= DATA_CHUNK);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mock_gets_whole_image() {
+        let mut epd = MockEpd::new(0);
+        let mut runner = EpdRunner::new(FULL_DRAW, FULL_AREA);
+        let frame = frame();
+        while !runner.advance(&mut epd, &frame) {}
+        assert_eq!(epd.image(), Some(frame));
+    }
+
+    #[test]
+    fn full_part_window_covers_frame_in_order() {
+        assert!(PartWindow::new(FULL_AREA).eq(0..BUFSIZE));
+    }
+}
@@ -1,97 +1,93 @@
-use core::cmp;
 use efm32pg23_fix::Peripherals;
 use crate::peripherals::usart::*;
 use crate::devices::se_aes_gcm::ENCODED_LEN;
+use crate::flash_map;
+use crate::hal::{FlashBus, FLASH_PAGE_SIZE};
 use crate::in_free;
 use cortex_m::asm::delay;
 
 use super::se_aes_gcm::Protected;
 
-const PAGE_SIZE: usize = 256;
+pub use crate::hal::FlashErr;
 
-#[derive(Clone, Copy, Debug)]
-pub enum FlashErr {
-    WriteNotMatch
+impl FlashBus for Peripherals {
+    fn wake(&mut self) {
+        flash_wakeup(self);
+    }
+
+    fn sleep(&mut self) {
+        flash_sleep(self);
+    }
+
+    fn erase_page(&mut self, addr: u32) {
+        flash_unlock(self);
+        flash_erase_page(self, addr);
+        flash_wait_ready(self);
+    }
+
+    fn write_page(&mut self, addr: u32, data: &[u8]) {
+        flash_unlock(self);
+        flash_write_page(self, addr, data);
+        flash_wait_ready(self);
+    }
+
+    fn read_page(&mut self, addr: u32, data: &mut [u8; FLASH_PAGE_SIZE]) {
+        flash_wait_ready(self);
+        flash_read(self, addr, data);
+    }
 }
 
-pub fn store_data<const N: usize>(addr: u32, payload: &[u8; N]) -> Result<(), FlashErr> {
-    let mut data = [0u8; N];
-    let mut read_data_chunk = [0u8; PAGE_SIZE];
-    let initial_addr = addr / PAGE_SIZE as u32 * PAGE_SIZE as u32;
-    for (i, chunk) in payload.chunks(PAGE_SIZE).enumerate() {
-        let addr = initial_addr + i as u32 * PAGE_SIZE as u32;
-        in_free(|peripherals| {
-            flash_wakeup(peripherals);
-    
-            flash_unlock(peripherals);
-            flash_erase_page(peripherals, addr);
-            flash_wait_ready(peripherals);
-    
-            flash_unlock(peripherals);
-    
-            flash_write_page(peripherals, addr, chunk);
-            flash_wait_ready(peripherals);
-    
-            flash_read(peripherals, addr, &mut read_data_chunk);
-            flash_sleep(peripherals);
-        });
-        let chunk_start = i * PAGE_SIZE;
-        let chunk_len = cmp::min(N - chunk_start, PAGE_SIZE);
-        data[chunk_start..chunk_start + chunk_len].clone_from_slice(&read_data_chunk[0..chunk_len]);
+/// Flash outside of critical section, each page operation takes peripherals separately
+pub struct FreeFlash;
+
+impl FlashBus for FreeFlash {
+    fn wake(&mut self) {
+        in_free(|peripherals| peripherals.wake());
     }
 
-    if &data != payload {
-        Err(FlashErr::WriteNotMatch)
-    } else {
-        Ok(())
+    fn sleep(&mut self) {
+        in_free(|peripherals| peripherals.sleep());
+    }
+
+    fn erase_page(&mut self, addr: u32) {
+        in_free(|peripherals| peripherals.erase_page(addr));
+    }
+
+    fn write_page(&mut self, addr: u32, data: &[u8]) {
+        in_free(|peripherals| peripherals.write_page(addr, data));
+    }
+
+    fn read_page(&mut self, addr: u32, data: &mut [u8; FLASH_PAGE_SIZE]) {
+        in_free(|peripherals| peripherals.read_page(addr, data));
     }
 }
 
+pub fn store_data<const N: usize>(addr: u32, payload: &[u8; N]) -> Result<(), FlashErr> {
+    let mut result = None;
+    in_free(|peripherals| result = Some(peripherals.store_data(addr, payload)));
+    result.unwrap()
+}
+
 pub fn read_data(addr: u32, data: &mut [u8]) -> Result<(), FlashErr> {
-    let mut read_data_chunk = [0u8; PAGE_SIZE];
-    let initial_addr = addr / PAGE_SIZE as u32 * PAGE_SIZE as u32;
-    for i in 0..data.len().div_ceil(PAGE_SIZE) {
-        let addr = initial_addr + i as u32 * PAGE_SIZE as u32;
-        in_free(|peripherals| {
-            flash_wakeup(peripherals);
-            flash_wait_ready(peripherals);
-
-            flash_read(peripherals, addr, &mut read_data_chunk);
-            flash_sleep(peripherals);
-        });
-        let chunk_start = i * PAGE_SIZE;
-        let chunk_len = cmp::min(data.len() - chunk_start, PAGE_SIZE);
-        data[chunk_start..chunk_start + chunk_len].clone_from_slice(&read_data_chunk[0..chunk_len]);
-    }
-    Ok(())
+    let mut result = None;
+    in_free(|peripherals| result = Some(peripherals.read_data(addr, data)));
+    result.unwrap()
 }
 
 pub fn erase_data(addr: u32, pages: u32) {
-    let initial_addr = addr / PAGE_SIZE as u32 * PAGE_SIZE as u32;
-    for i in 0..pages {
-        let addr = initial_addr + i as u32 * PAGE_SIZE as u32;
-        in_free(|peripherals| {
-            flash_wakeup(peripherals);
-    
-            flash_unlock(peripherals);
-            flash_erase_page(peripherals, addr);
-
-            flash_wait_ready(peripherals);
-            flash_sleep(peripherals);
-        });
-    }
+    in_free(|peripherals| peripherals.erase_data(addr, pages));
 }
 
 pub fn store_encoded_entopy(protected: &Protected) {
     // stroring encoded entropy
-    if let Err(_) = store_data(0, &protected.0) {
+    if let Err(_) = store_data(flash_map::SEED.base(), &protected.0) {
         panic!("Failed to save seedphrase");
     }
 }
 
 pub fn read_encoded_entropy() -> Option<Protected> {
     let mut data = [0u8; ENCODED_LEN];
-    if let Err(_) = read_data(0, &mut data) {
+    if let Err(_) = read_data(flash_map::SEED.base(), &mut data) {
         panic!("Failed to read seedphrase");
     }
     match data[0] {
@@ -101,7 +97,7 @@ pub fn read_encoded_entropy() -> Option<Protected> {
         },
         255 => None,
         _ => {
-            erase_data(0, 1);
+            erase_data(flash_map::SEED.base(), flash_map::SEED.count);
             panic!("Seed storage corrupted! Wiping seed...");
         },
     }
@@ -357,7 +353,7 @@ pub fn flash_write_page(peripherals: &mut Peripherals, addr: u32, data: &[u8]) {
     select_flash(&mut peripherals.GPIO_S);
     flash_cmd(peripherals, FlashCommand::WritePage);
     flash_write_addr!(peripherals, addr);
-    let xfer_len = if PAGE_SIZE < data.len() { PAGE_SIZE } else { data.len() };
+    let xfer_len = if FLASH_PAGE_SIZE < data.len() { FLASH_PAGE_SIZE } else { data.len() };
     flash_write_some(peripherals, &data[0..xfer_len]);
     deselect_flash(&mut peripherals.GPIO_S);
 }
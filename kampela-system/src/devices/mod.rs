@@ -1,11 +1,20 @@
 //! HAL for Kampela devices
+//!
+//! Without `efm32` feature only platform-independent PSRAM access and EPD sequences are built.
 
+#[cfg(feature = "efm32")]
 pub mod power;
 pub mod psram;
+#[cfg(feature = "efm32")]
 pub mod display;
+#[cfg(feature = "efm32")]
 pub mod display_transmission;
+pub mod epd_sequence;
+#[cfg(feature = "efm32")]
 pub mod se_rng;
+#[cfg(feature = "efm32")]
 pub mod se_aes_gcm;
+#[cfg(feature = "efm32")]
 pub mod touch;
+#[cfg(feature = "efm32")]
 pub mod flash;
-
@@ -1,7 +1,11 @@
 //! Power measurement unit
 
-use crate::{if_in_free, parallel::Operation, peripherals::adc};
+use crate::{hal::VoltageSource, if_in_free, parallel::Operation, peripherals::adc};
 
+/// Supply voltage for raw ADC value
+pub fn adc_to_voltage(value: i32) -> i32 {
+    value * 211 / 10000
+}
 
 pub struct ADC {
     state: ADCState,
@@ -15,7 +19,7 @@ pub enum ADCState {
 
 impl ADC {
     pub fn read(&self) -> i32 {
-        self.last_value * 211 / 10000
+        adc_to_voltage(self.last_value)
     }
 }
 
@@ -53,3 +57,12 @@ impl Operation for ADC {
     }
 }
 
+impl VoltageSource for ADC {
+    fn advance(&mut self) {
+        Operation::advance(self, ())
+    }
+
+    fn read(&self) -> i32 {
+        ADC::read(self)
+    }
+}
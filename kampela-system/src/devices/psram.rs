@@ -1,53 +1,56 @@
 //! external RAM
 
 use alloc::{format, vec::Vec, string::String};
+use blake2_rfc::blake2b::{blake2b, Blake2b};
+#[cfg(feature = "efm32")]
 use primitive_types::H256;
+#[cfg(feature = "efm32")]
 use efm32pg23_fix::Peripherals;
+#[cfg(feature = "efm32")]
 use crate::peripherals::eusart::*;
-use substrate_parser::{cards::{Call, ExtendedData}, decode_as_call_unmarked, decode_extensions_unmarked};
+#[cfg(feature = "efm32")]
+use crate::parser::{decode_call, decode_extensions, CheckedMetadataMetal, DecodeError};
+#[cfg(feature = "efm32")]
+use substrate_parser::cards::{Call, ExtendedData};
+#[cfg(feature = "efm32")]
 use crate::in_free;
+use crate::hal::PsramBus;
 
-pub fn psram_decode_call(call_psram_access: &PsramAccess, metadata_psram_access: &PsramAccess) -> (Call, ShortSpecs, String) {
+#[cfg(feature = "efm32")]
+pub fn psram_decode_call(call_psram_access: &PsramAccess, metadata_psram_access: &PsramAccess) -> Result<(Call, ShortSpecs, String), DecodeError> {
     let call_data = read_from_psram(call_psram_access);
 
     let (
         checked_metadata_metal,
         specs,
         spec_name,
-    ) = read_checked_metadata_metal(metadata_psram_access);
+    ) = read_checked_metadata_metal(metadata_psram_access)?;
 
     let mut decoded_call_option = None;
     in_free(|peripherals| {
-        let mut external_psram = ExternalPsram{peripherals};
-        let mut decoding_postition = 0;
-        let decoded_call = decode_as_call_unmarked(
-            &call_data.as_ref(),
-            &mut decoding_postition,
-            &mut external_psram,
-            &checked_metadata_metal,
-        ).unwrap();
-
-        decoded_call_option = Some(decoded_call);
+        let mut external_psram = ExternalPsram{psram: peripherals};
+        decoded_call_option = Some(decode_call(&call_data, &checked_metadata_metal, &mut external_psram));
     });
-    (
-        decoded_call_option.unwrap(),
+    Ok((
+        decoded_call_option.unwrap()?,
         specs,
         spec_name,
-    )
+    ))
 }
 
+#[cfg(feature = "efm32")]
 pub fn psram_decode_extension(
     extension_psram_access: &PsramAccess,
     metadata_psram_access: &PsramAccess,
     genesis_hash_bytes_psram_access: &PsramAccess,
-) -> (Vec<ExtendedData>, ShortSpecs, String) {
+) -> Result<(Vec<ExtendedData>, ShortSpecs, String), DecodeError> {
     let extension_data = read_from_psram(extension_psram_access);
     
     let (
         checked_metadata_metal,
         specs,
         spec_name
-    ) = read_checked_metadata_metal(metadata_psram_access);
+    ) = read_checked_metadata_metal(metadata_psram_access)?;
 
     let genesis_hash = H256(
         read_from_psram(genesis_hash_bytes_psram_access)
@@ -57,47 +60,109 @@ pub fn psram_decode_extension(
 
     let mut decoded_extension_option = None;
     in_free(|peripherals| {
-        let mut external_psram = ExternalPsram{peripherals};
-        let mut decoding_postition = 0;
-        let decoded_extension = decode_extensions_unmarked(
-            &extension_data.as_ref(),
-            &mut decoding_postition,
-            &mut external_psram,
-            &checked_metadata_metal,
-            Some(genesis_hash),
-        ).unwrap();
-
-        decoded_extension_option = Some(decoded_extension);
+        let mut external_psram = ExternalPsram{psram: peripherals};
+        decoded_extension_option = Some(decode_extensions(&extension_data, &checked_metadata_metal, genesis_hash, &mut external_psram));
     });
-    (
-        decoded_extension_option.unwrap(),
+    Ok((
+        decoded_extension_option.unwrap()?,
         specs,
         spec_name,
-    )
+    ))
 }
 
-fn read_checked_metadata_metal(metadata_psram_access: &PsramAccess) -> (CheckedMetadataMetal, ShortSpecs, String) {
+#[cfg(feature = "efm32")]
+fn read_checked_metadata_metal(metadata_psram_access: &PsramAccess) -> Result<(CheckedMetadataMetal<PsramAccess>, ShortSpecs, String), DecodeError> {
     let mut checked_metadata_metal_option = None;
     in_free(|peripherals| {
-        let mut external_psram = ExternalPsram{peripherals};
+        let mut external_psram = ExternalPsram{psram: peripherals};
         checked_metadata_metal_option = Some(
             CheckedMetadataMetal::from(
                 metadata_psram_access,
                 &mut external_psram
-            ).unwrap()
+            )
         );
     });
 
-    let checked_metadata_metal = checked_metadata_metal_option.unwrap();
+    let checked_metadata_metal = checked_metadata_metal_option.unwrap().map_err(DecodeError::Metadata)?;
     let specs = checked_metadata_metal.to_specs();
     let spec_name = checked_metadata_metal.spec_name_version.spec_name.to_owned();
-    (
+    Ok((
         checked_metadata_metal,
         specs,
         spec_name
-    )
+    ))
+}
+
+/// Chunk of PSRAM data fed into hasher at once
+const HASH_CHUNK_LEN: usize = 256;
+
+/// Blake2b-256 hash of PSRAM data, read in chunks so that large payloads never get copied into
+/// RAM as a whole
+#[cfg(feature = "efm32")]
+pub fn psram_blake2b_256(psram_access: &PsramAccess) -> [u8; 32] {
+    blake2b_256_chunked(psram_access, read_from_psram)
+}
+
+/// Same as [`psram_blake2b_256`], on given PSRAM bus
+pub fn psram_blake2b_256_on<P: PsramBus>(psram: &mut P, psram_access: &PsramAccess) -> [u8; 32] {
+    blake2b_256_chunked(psram_access, |chunk_access| read_from_psram_on(psram, chunk_access))
+}
+
+fn blake2b_256_chunked<F>(psram_access: &PsramAccess, mut read: F) -> [u8; 32]
+where
+    F: FnMut(&PsramAccess) -> Vec<u8>,
+{
+    let mut hasher = Blake2b::new(32);
+    let mut position = 0;
+    while position < psram_access.total_len {
+        let len = HASH_CHUNK_LEN.min(psram_access.total_len - position);
+        let chunk = read(&PsramAccess {
+            start_address: psram_access.start_address.try_shift(position).expect("access is within PSRAM"),
+            total_len: len,
+        });
+        hasher.update(&chunk);
+        position += len;
+    }
+    hasher.finalize().as_bytes().try_into().expect("static length")
+}
+
+/// Longest payload that is signed as is; longer payloads are signed by hash
+pub const MAX_UNHASHED_PAYLOAD_LEN: usize = 256;
+
+/// Bytes actually signed for payload in memory, same as [`psram_signing_payload`] gives
+pub fn signing_payload(payload: &[u8]) -> Vec<u8> {
+    if payload.len() > MAX_UNHASHED_PAYLOAD_LEN {
+        blake2b(32, &[], payload).as_bytes().to_vec()
+    } else {
+        payload.to_vec()
+    }
+}
+
+/// Bytes actually signed for payload in PSRAM, as defined by Substrate
+#[cfg(feature = "efm32")]
+pub fn psram_signing_payload(psram_access: &PsramAccess) -> Vec<u8> {
+    if psram_access.total_len > MAX_UNHASHED_PAYLOAD_LEN {
+        psram_blake2b_256(psram_access).to_vec()
+    } else {
+        read_from_psram(psram_access)
+    }
+}
+
+/// Same as [`psram_signing_payload`], on given PSRAM bus
+pub fn psram_signing_payload_on<P: PsramBus>(psram: &mut P, psram_access: &PsramAccess) -> Vec<u8> {
+    if psram_access.total_len > MAX_UNHASHED_PAYLOAD_LEN {
+        psram_blake2b_256_on(psram, psram_access).to_vec()
+    } else {
+        read_from_psram_on(psram, psram_access)
+    }
 }
 
+fn read_from_psram_on<P: PsramBus>(psram: &mut P, psram_access: &PsramAccess) -> Vec<u8> {
+    psram_read_at_address(psram, psram_access.start_address, psram_access.total_len)
+        .expect("access is within PSRAM")
+}
+
+#[cfg(feature = "efm32")]
 pub fn read_from_psram(psram_access: &PsramAccess) -> Vec<u8> {
     let mut bytes_option = None;
     in_free(|peripherals| {
@@ -113,6 +178,7 @@ pub fn read_from_psram(psram_access: &PsramAccess) -> Vec<u8> {
     bytes_option.unwrap()
 }
 
+#[cfg(feature = "efm32")]
 pub fn psram_reset(peripherals: &mut Peripherals) {
     deselect_psram(&mut peripherals.GPIO_S);
     select_psram(&mut peripherals.GPIO_S);
@@ -123,6 +189,7 @@ pub fn psram_reset(peripherals: &mut Peripherals) {
     deselect_psram(&mut peripherals.GPIO_S);
 }
 
+#[cfg(feature = "efm32")]
 pub fn psram_write_read_byte(peripherals: &mut Peripherals, byte: u8) -> u8 {
     while peripherals.EUSART2_S.status.read().txfl().bit_is_clear() {}
     peripherals.EUSART2_S.txdata.write({|w_reg|
@@ -141,6 +208,7 @@ pub fn psram_write_read_byte(peripherals: &mut Peripherals, byte: u8) -> u8 {
 /// Could have switched into autotx mode instead.
 pub const PSRAM_DUMMY: u8 = 0xff;
 
+#[cfg(feature = "efm32")]
 pub fn psram_read_id(peripherals: &mut Peripherals) -> [u8; ID_LEN] {
     select_psram(&mut peripherals.GPIO_S);
     psram_write_read_byte(peripherals, PSRAM_READ_ID);
@@ -148,12 +216,14 @@ pub fn psram_read_id(peripherals: &mut Peripherals) -> [u8; ID_LEN] {
     psram_read_vec(peripherals, ID_LEN).try_into().expect("static length, always fits")
 }
 
+#[cfg(feature = "efm32")]
 pub fn psram_write_slice(peripherals: &mut Peripherals, slice: &[u8]) {
     for byte in slice.iter() {
         psram_write_read_byte(peripherals, *byte);
     }
 }
 
+#[cfg(feature = "efm32")]
 pub fn psram_read_vec(peripherals: &mut Peripherals, len: usize) -> Vec<u8> {
     let mut out: Vec<u8> = Vec::with_capacity(len);
     for _i in 0..len {
@@ -162,6 +232,31 @@ pub fn psram_read_vec(peripherals: &mut Peripherals, len: usize) -> Vec<u8> {
     out
 }
 
+/// Transactions are sent through EUSART, with chip select held for transaction duration
+#[cfg(feature = "efm32")]
+impl PsramBus for Peripherals {
+    fn reset(&mut self) {
+        psram_reset(self);
+    }
+
+    fn read_in_page(&mut self, address: AddressPsram, len: usize) -> Vec<u8> {
+        select_psram(&mut self.GPIO_S);
+        psram_write_read_byte(self, PSRAM_READ);
+        psram_write_slice(self, &address.inner());
+        let out = psram_read_vec(self, len);
+        deselect_psram(&mut self.GPIO_S);
+        out
+    }
+
+    fn write_in_page(&mut self, address: AddressPsram, data: &[u8]) {
+        select_psram(&mut self.GPIO_S);
+        psram_write_read_byte(self, PSRAM_WRITE);
+        psram_write_slice(self, &address.inner());
+        psram_write_slice(self, data);
+        deselect_psram(&mut self.GPIO_S);
+    }
+}
+
 /// PSRAM commands from manual
 pub const PSRAM_RESET_ENABLE: u8 = 0x66;
 pub const PSRAM_RESET: u8 = 0x99;
@@ -211,21 +306,13 @@ pub enum MemoryError {
 ///
 /// Slice length should be checked elsewhere to be sufficiently low to read only
 /// data from the address going forward.
-pub fn psram_read_at_address_native(peripherals: &mut Peripherals, address: AddressPsram, len: usize) -> Vec<u8> {
-    psram_reset(peripherals);
-    psram_read_at_address_helper(peripherals, address, len)
+pub fn psram_read_at_address_native<P: PsramBus>(psram: &mut P, address: AddressPsram, len: usize) -> Vec<u8> {
+    psram.reset();
+    psram.read_in_page(address, len)
 }
 
-fn psram_read_at_address_helper(peripherals: &mut Peripherals, address: AddressPsram, len: usize) -> Vec<u8> {
-    select_psram(&mut peripherals.GPIO_S);
-    psram_write_read_byte(peripherals, PSRAM_READ);
-    psram_write_slice(peripherals, &address.inner());
-    let out = psram_read_vec(peripherals, len);
-    deselect_psram(&mut peripherals.GPIO_S);
-    out
-}
-pub fn psram_read_at_address(peripherals: &mut Peripherals, address: AddressPsram, len: usize) -> Result<Vec<u8>, MemoryError> {
-    psram_reset(peripherals);
+pub fn psram_read_at_address<P: PsramBus>(psram: &mut P, address: AddressPsram, len: usize) -> Result<Vec<u8>, MemoryError> {
+    psram.reset();
     
     let start = address.as_u32();
 
@@ -234,20 +321,20 @@ pub fn psram_read_at_address(peripherals: &mut Peripherals, address: AddressPsra
 
     let space_left_on_page = PSRAM_PAGE_SIZE - start%PSRAM_PAGE_SIZE;
     if len as u32 <= space_left_on_page {
-        out.extend_from_slice(&psram_read_at_address_helper(peripherals, address, len));
+        out.extend_from_slice(&psram.read_in_page(address, len));
     }
     else {
-        out.extend_from_slice(&psram_read_at_address_helper(peripherals, address, space_left_on_page as usize));
+        out.extend_from_slice(&psram.read_in_page(address, space_left_on_page as usize));
         let full_pages = (len as u32 - space_left_on_page)/PSRAM_PAGE_SIZE;
         for i in 0..full_pages {
             let full_page_start = (start/PSRAM_PAGE_SIZE + 1 + i) * PSRAM_PAGE_SIZE;
             let address = AddressPsram::new(full_page_start).expect("checked that length does not overflow");
-            out.extend_from_slice(&psram_read_at_address_helper(peripherals, address, PSRAM_PAGE_SIZE as usize));
+            out.extend_from_slice(&psram.read_in_page(address, PSRAM_PAGE_SIZE as usize));
         }
         let last_page_start = (start/PSRAM_PAGE_SIZE + 1 + full_pages) * PSRAM_PAGE_SIZE;
         let address = AddressPsram::new(last_page_start).expect("checked that length does not overflow");
         let tail_len = len - (space_left_on_page + full_pages*PSRAM_PAGE_SIZE) as usize;
-        out.extend_from_slice(&psram_read_at_address_helper(peripherals, address, tail_len));
+        out.extend_from_slice(&psram.read_in_page(address, tail_len));
     }
     Ok(out)
 }
@@ -256,26 +343,16 @@ pub fn psram_read_at_address(peripherals: &mut Peripherals, address: AddressPsra
 ///
 /// Slice length should be checked elsewhere to be sufficiently low to fit on
 /// page without wrapping.
-pub fn psram_write_at_address_native(peripherals: &mut Peripherals, address: AddressPsram, slice: &[u8]) {
-    psram_reset(peripherals);
-    psram_write_at_address_helper(peripherals, address, slice);
+pub fn psram_write_at_address_native<P: PsramBus>(psram: &mut P, address: AddressPsram, slice: &[u8]) {
+    psram.reset();
+    psram.write_in_page(address, slice);
 }
 
-/// Helper function, without reset at start.
-///
-/// Use only as a part of function with reset.
-fn psram_write_at_address_helper(peripherals: &mut Peripherals, address: AddressPsram, slice: &[u8]) {
-    select_psram(&mut peripherals.GPIO_S);
-    psram_write_read_byte(peripherals, PSRAM_WRITE);
-    psram_write_slice(peripherals, &address.inner());
-    psram_write_slice(peripherals, slice);
-    deselect_psram(&mut peripherals.GPIO_S);
-}
 /// Write at address seamlessly, i.e. without wrapping.
 ///
 /// Each new byte is written to the next address.
-pub fn psram_write_at_address(peripherals: &mut Peripherals, address: AddressPsram, slice: &[u8]) -> Result<(), MemoryError> {
-    psram_reset(peripherals);
+pub fn psram_write_at_address<P: PsramBus>(psram: &mut P, address: AddressPsram, slice: &[u8]) -> Result<(), MemoryError> {
+    psram.reset();
     
     let start = address.as_u32();
     let slice_len = slice.len() as u32;
@@ -284,57 +361,57 @@ pub fn psram_write_at_address(peripherals: &mut Peripherals, address: AddressPsr
 
     let space_left_on_page = PSRAM_PAGE_SIZE - start%PSRAM_PAGE_SIZE;
     if slice_len <= space_left_on_page {
-        psram_write_at_address_helper(peripherals, address, slice);
+        psram.write_in_page(address, slice);
     }
     else {
-        psram_write_at_address_helper(peripherals, address, &slice[..space_left_on_page as usize]);
+        psram.write_in_page(address, &slice[..space_left_on_page as usize]);
         let full_pages = (slice_len - space_left_on_page)/PSRAM_PAGE_SIZE;
         for i in 0..full_pages {
             let full_page_start = (start/PSRAM_PAGE_SIZE + 1 + i) * PSRAM_PAGE_SIZE;
             let address = AddressPsram::new(full_page_start).expect("checked that length does not overflow");
             let slice_start = (space_left_on_page + i*PSRAM_PAGE_SIZE) as usize;
             let slice_end = slice_start + PSRAM_PAGE_SIZE as usize;
-            psram_write_at_address_helper(peripherals, address, &slice[slice_start..slice_end]);
+            psram.write_in_page(address, &slice[slice_start..slice_end]);
         }
         let last_page_start = (start/PSRAM_PAGE_SIZE + 1 + full_pages) * PSRAM_PAGE_SIZE;
         let address = AddressPsram::new(last_page_start).expect("checked that length does not overflow");
         let slice_start = (space_left_on_page + full_pages*PSRAM_PAGE_SIZE) as usize;
-        psram_write_at_address_helper(peripherals, address, &slice[slice_start..]);
+        psram.write_in_page(address, &slice[slice_start..]);
     }
     Ok(())
 }
 /// PSRAM is *paged*, with data in pages wrapped at page end.
 pub const PSRAM_PAGE_SIZE: u32 = 1024;
 
-/// PSRAM total size, 2^23 byte.
+/// Bound for PSRAM reads and writes, 2^26 byte.
 ///
-/// Limits maximum address available to `AddressPsram([0x8f, ff, ff])`.
+/// Addresses are narrower than that: `AddressPsram` could not go past `[0x8f, 0xff, 0xff]`.
 pub const PSRAM_TOTAL_SIZE: u32 = 67_108_864;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PsramAccess {
     pub start_address: AddressPsram,
     pub total_len: usize,
 }
-use core::{any::TypeId, fmt::{Debug, Display, Formatter, Result as FmtResult}};
+use core::fmt::{Debug, Display, Formatter, Result as FmtResult};
+#[cfg(feature = "efm32")]
 use alloc::borrow::ToOwned;
 
 use external_memory_tools::{AddressableBuffer, BufferError, ExternalMemory};
-use parity_scale_codec::{Decode, DecodeAll, Encode};
-use substrate_parser::{AsMetadata, ResolveType, ShortSpecs, compacts::find_compact, error::{RegistryError, RegistryInternalError}, traits::{SignedExtensionMetadata, SpecNameVersion}};
-use scale_info::{form::PortableForm, interner::UntrackedSymbol, Type};
+#[cfg(feature = "efm32")]
+use substrate_parser::ShortSpecs;
 
-pub struct ExternalPsram<'a> {
-    pub peripherals: &'a mut Peripherals,
+pub struct ExternalPsram<'a, P: PsramBus> {
+    pub psram: &'a mut P,
 }
 
-impl <'a> Debug for ExternalPsram<'a> {
+impl <'a, P: PsramBus> Debug for ExternalPsram<'a, P> {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         write!(f, "ExternalPsram")
     }
 }
 
-impl <'a> ExternalMemory for ExternalPsram<'a> {
+impl <'a, P: PsramBus> ExternalMemory for ExternalPsram<'a, P> {
     type ExternalMemoryError = MemoryError;
 }
 impl MemoryError {
@@ -353,18 +430,18 @@ impl Display for MemoryError {
         write!(f, "{}", self.error_text())
     }
 }
-impl <'a> AddressableBuffer<ExternalPsram<'a>> for PsramAccess {
+impl <'a, P: PsramBus> AddressableBuffer<ExternalPsram<'a, P>> for PsramAccess {
     type ReadBuffer = Vec<u8>;
     fn total_len(&self) -> usize {
         self.total_len
     }
-    fn read_slice(&self, ext_memory: &mut ExternalPsram<'a>, position: usize, len: usize) -> Result<Self::ReadBuffer, BufferError<ExternalPsram<'a>>> {
+    fn read_slice(&self, ext_memory: &mut ExternalPsram<'a, P>, position: usize, len: usize) -> Result<Self::ReadBuffer, BufferError<ExternalPsram<'a, P>>> {
         if self.total_len() < position {return Err(BufferError::OutOfRange { position, total_length: self.total_len() })}
         if self.total_len() < (position + len) {return Err(BufferError::DataTooShort { position: self.total_len(), minimal_length: position + len - self.total_len() })}
         let address = self.start_address.try_shift(position).map_err(BufferError::External)?;
-        psram_read_at_address(ext_memory.peripherals, address, len).map_err(BufferError::External)
+        psram_read_at_address(ext_memory.psram, address, len).map_err(BufferError::External)
     }
-    fn limit_length(&self, new_len: usize) -> Result<Self, BufferError<ExternalPsram<'a>>> {
+    fn limit_length(&self, new_len: usize) -> Result<Self, BufferError<ExternalPsram<'a, P>>> {
         if new_len > self.total_len {Err(BufferError::DataTooShort { position: 0, minimal_length: new_len })}
         else {Ok(PsramAccess {
             start_address: self.start_address,
@@ -373,182 +450,116 @@ impl <'a> AddressableBuffer<ExternalPsram<'a>> for PsramAccess {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct MetalRegistry {
-    pub start_address: AddressPsram,
-    pub registry: Vec<EntryPsram>,
-}
+use lt_codes::decoder_metal::ExternalAddress;
 
-#[derive(Clone, Debug)]
-pub struct EntryPsram {
-    pub id: u32,
-    pub position: usize,
-    pub entry_len: usize,
-}
-
-impl <'a> ResolveType<ExternalPsram<'a>> for MetalRegistry {
-    fn resolve_ty(&self, id: u32, ext_memory: &mut ExternalPsram<'a>) -> Result<Type<PortableForm>, RegistryError<ExternalPsram<'a>>> {
-        for entry_psram in self.registry.iter() {
-            if entry_psram.id == id {
-                let address = self.start_address.try_shift(entry_psram.position).map_err(RegistryError::External)?;
-                let encoded_type_data = psram_read_at_address(ext_memory.peripherals, address, entry_psram.entry_len).map_err(RegistryError::External)?;
-                let ty = Type::<PortableForm>::decode_all(&mut &encoded_type_data[..]).map_err(|_| RegistryError::External(MemoryError::TypeInfoDamaged{id}))?;
-                return Ok(ty)
-            }
-        }
-        Err(RegistryError::Internal(RegistryInternalError::TypeNotResolved { id }))
+impl ExternalAddress for AddressPsram {
+    fn zero() -> Self {
+        AddressPsram::zero()
+    }
+    fn shift(&mut self, position: usize) {
+        *self = self.try_shift(position).unwrap(); //TODO
     }
 }
 
-#[derive(Debug)]
-pub struct CheckedMetadataMetal {
-    pub types: MetalRegistry,
-    pub call_ty: UntrackedSymbol<TypeId>,
-    pub signed_extensions: Vec<SignedExtensionMetadata>,
-    pub spec_name_version: SpecNameVersion,
-    pub base58prefix: u16,
-    pub decimals: u8,
-    pub unit: String,
-}
-
-#[derive(Debug, Decode, Encode)]
-pub struct CheckedMeadataMetalTail {
-    pub call_ty: UntrackedSymbol<TypeId>,
-    pub signed_extensions: Vec<SignedExtensionMetadata>,
-    pub spec_name_version: SpecNameVersion,
-    pub base58prefix: u16,
-    pub decimals: u8,
-    pub unit: String,
-}
+impl <'a, P: PsramBus> lt_codes::decoder_metal::ExternalMemory<AddressPsram> for ExternalPsram<'a, P> {
 
-impl <'a> AsMetadata<ExternalPsram<'a>> for CheckedMetadataMetal {
-    type TypeRegistry = MetalRegistry;
-    type MetaStructureError = NoEntries;
-    fn types(&self) -> Self::TypeRegistry {
-        self.types.to_owned()
-    }
-    fn spec_name_version(&self) -> Result<SpecNameVersion, Self::MetaStructureError> {
-        Ok(self.spec_name_version.to_owned())
-    }
-    fn call_ty(&self) -> Result<UntrackedSymbol<TypeId>, Self::MetaStructureError> {
-        Ok(self.call_ty.to_owned())
+    fn write_external(&mut self, address: &AddressPsram, data: &[u8]) {
+         psram_write_at_address(self.psram, *address, data).unwrap() //TODO
     }
-    fn signed_extensions(&self) -> Result<Vec<SignedExtensionMetadata>, Self::MetaStructureError> {
-        Ok(self.signed_extensions.to_owned())
+    fn read_external(&mut self, address: &AddressPsram, len: usize) -> Vec<u8> {
+         psram_read_at_address(self.psram, *address, len).unwrap() //TODO
     }
 }
 
-/// Empty error enum, for cases with fault-free memory access.
-#[derive(Debug, Eq, PartialEq)]
-pub enum NoEntries {}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Display for NoEntries {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "")
-    }
-}
+    use rand::{rngs::StdRng, SeedableRng};
+    use sp_core::{crypto::Pair as _, sr25519};
+    use substrate_crypto_light::sr25519::{Pair, Public, Signature};
+
+    use crate::hal::mock::MockPsram;
 
-fn force_decode_at<T: Decode>(psram_data: &PsramAccess, ext_memory: &mut ExternalPsram<'_>, start_position: usize, err_at: ReceivedMetadataError) -> Result<(T, usize), ReceivedMetadataError> {
-    let mut data = Vec::with_capacity(psram_data.total_len - start_position);
-    let mut out: Option<(T, usize)> = None;
-    for i in 0..psram_data.total_len - start_position {
-        let address = psram_data.start_address.try_shift(start_position+i).map_err(|_| err_at.to_owned())?;
-        let byte = psram_read_at_address(ext_memory.peripherals, address, 1usize).map_err(|_| err_at.to_owned())?[0];
-        data.push(byte);
-        if let Ok(a) = T::decode(&mut &data[..]) {
-            out = Some((a, i+1));
-            break;
+    /// Message signed for payload, as Substrate runtime checks it
+    fn reference_signing_payload(payload: &[u8]) -> Vec<u8> {
+        if payload.len() > 256 {
+            sp_core::hashing::blake2_256(payload).to_vec()
+        } else {
+            payload.to_vec()
         }
     }
-    match out {
-        Some(a) => Ok(a),
-        None => Err(err_at),
-    }
-}
-impl <'a> CheckedMetadataMetal {
-    /// Assume here that the metadata is received as SCALE-encoded
-    /// `ShortMetadata` with known length.
-    ///
-    /// Provided `PsramAccess` corresponds to whole encoded metadata.
-    pub fn from(psram_data: &PsramAccess, ext_memory: &mut ExternalPsram<'a>) -> Result<Self, ReceivedMetadataError> {
-        
-        let mut position = 0usize;
-
-        // Metadata starts with types registry, a vec of Type descriptors.
-        // Search for compact, the number of `PortableType` entries to follow.
-        let found_compact = find_compact::<u32, PsramAccess, ExternalPsram<'a>>(psram_data, ext_memory, position).map_err(|_| ReceivedMetadataError::Format)?;
-
-        let types_set_len = found_compact.compact;
-        let mut registry: Vec<EntryPsram> = Vec::with_capacity(types_set_len as usize);
-        position = found_compact.start_next_unit;
-        
-        for _entry_number in 0..types_set_len {
-            // Each `PortableType` starts with compact of the id.
-            let entry_number_compact = find_compact::<u32, PsramAccess, ExternalPsram<'a>>(psram_data, ext_memory, position).map_err(|_| ReceivedMetadataError::Format)?;
-            position = entry_number_compact.start_next_unit;
-            
-            // And is followed by encoded `Type<PortableForm>` entry.
-            let (_ty, entry_len) = force_decode_at::<Type<PortableForm>>(psram_data, ext_memory, position, ReceivedMetadataError::Format)?;
-
-            registry.push(EntryPsram{id: entry_number_compact.compact, position, entry_len});
-
-            position += entry_len;
-        }
-
-        let types = MetalRegistry {
-            start_address: psram_data.start_address, 
-            registry,
-        };
-
-        // The rest corresponds to `CheckedMeadataMetalTail`
 
-        let tail_data = psram_data.read_slice(ext_memory, position, psram_data.total_len - position).map_err(|_| ReceivedMetadataError::Format)?;
-        let tail = CheckedMeadataMetalTail::decode_all(&mut &tail_data[..]).map_err(|_| ReceivedMetadataError::Format)?;
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
 
-        Ok(CheckedMetadataMetal{
-            types,
-            call_ty: tail.call_ty,
-            signed_extensions: tail.signed_extensions,
-            spec_name_version: tail.spec_name_version,
-            base58prefix: tail.base58prefix,
-            decimals: tail.decimals,
-            unit: tail.unit,
-        })
+    /// Payload placed in PSRAM off page boundary, so that reads cross pages
+    fn psram_with(payload: &[u8]) -> (MockPsram, PsramAccess) {
+        let mut psram = MockPsram::new(payload.len() / PSRAM_PAGE_SIZE as usize + 2);
+        let psram_access = PsramAccess {
+            start_address: AddressPsram::new(PSRAM_PAGE_SIZE - 100).unwrap(),
+            total_len: payload.len(),
+        };
+        psram_write_at_address(&mut psram, psram_access.start_address, payload).unwrap();
+        (psram, psram_access)
     }
 
-    pub fn to_specs(&self) -> ShortSpecs {
-        ShortSpecs {
-            base58prefix: self.base58prefix,
-            decimals: self.decimals,
-            unit: self.unit.to_owned(),
+    #[test]
+    fn payload_up_to_limit_is_signed_as_is() {
+        for len in [0, 1, MAX_UNHASHED_PAYLOAD_LEN] {
+            let payload = payload(len);
+            assert_eq!(signing_payload(&payload), payload);
+            assert_eq!(signing_payload(&payload), reference_signing_payload(&payload));
         }
     }
-}
 
-#[derive(Clone, Debug)]
-pub enum ReceivedMetadataError {
-    Format,
-//    Memory(MemoryError),
-//    UnableToDecode,
-}
-
-use lt_codes::decoder_metal::ExternalAddress;
-
-impl ExternalAddress for AddressPsram {
-    fn zero() -> Self {
-        AddressPsram::zero()
-    }
-    fn shift(&mut self, position: usize) {
-        *self = self.try_shift(position).unwrap(); //TODO
+    #[test]
+    fn payload_over_limit_is_signed_by_hash() {
+        for len in [MAX_UNHASHED_PAYLOAD_LEN + 1, 5000] {
+            let payload = payload(len);
+            assert_eq!(signing_payload(&payload), sp_core::hashing::blake2_256(&payload));
+        }
     }
-}
 
-impl <'a> lt_codes::decoder_metal::ExternalMemory<AddressPsram> for ExternalPsram<'a> {
+    #[test]
+    fn psram_payload_matches_payload_in_memory() {
+        for len in [1, MAX_UNHASHED_PAYLOAD_LEN, MAX_UNHASHED_PAYLOAD_LEN + 1, 3 * PSRAM_PAGE_SIZE as usize + 7] {
+            let payload = payload(len);
+            let (mut psram, psram_access) = psram_with(&payload);
+            assert_eq!(psram_signing_payload_on(&mut psram, &psram_access), reference_signing_payload(&payload), "length {len}");
+        }
+    }
 
-    fn write_external(&mut self, address: &AddressPsram, data: &[u8]) {
-         psram_write_at_address(self.peripherals, *address, data).unwrap() //TODO
+    #[test]
+    fn psram_hash_is_blake2b_256() {
+        let payload = payload(2 * HASH_CHUNK_LEN + 1);
+        let (mut psram, psram_access) = psram_with(&payload);
+        assert_eq!(psram_blake2b_256_on(&mut psram, &psram_access), sp_core::hashing::blake2_256(&payload));
     }
-    fn read_external(&mut self, address: &AddressPsram, len: usize) -> Vec<u8> {
-         psram_read_at_address(self.peripherals, *address, len).unwrap() //TODO
+
+    #[test]
+    fn signatures_match_reference_at_boundary() {
+        let pair = Pair::from_entropy_and_pwd(&[7; 32], "").ok().expect("valid entropy length");
+        let public = sr25519::Public::from_raw(pair.public().0);
+        let reference_pair = sr25519::Pair::from_seed(&[8; 32]);
+
+        for len in [MAX_UNHASHED_PAYLOAD_LEN, MAX_UNHASHED_PAYLOAD_LEN + 1] {
+            let payload = payload(len);
+            let (mut psram, psram_access) = psram_with(&payload);
+            let message = psram_signing_payload_on(&mut psram, &psram_access);
+
+            let signature = pair.sign_external_rng(&message, &mut StdRng::seed_from_u64(0));
+            assert!(
+                sr25519::Pair::verify(&sr25519::Signature::from_raw(signature.0), reference_signing_payload(&payload), &public),
+                "length {len}",
+            );
+
+            let reference_signature = reference_pair.sign(&reference_signing_payload(&payload));
+            assert!(
+                Public(reference_pair.public().0).verify(&message, &Signature(reference_signature.0)),
+                "length {len}",
+            );
+        }
     }
 }
@@ -11,6 +11,8 @@ use crate::peripherals::se_command::{
     se_command_aes_gsm_decrypt, DataTransfer, RxError, SeCommand, SE_COMMAND_AES_GCM_ENCRYPT,
     SE_COMMAND_CREATE_KEY, SE_DATATRANSFER_REALIGN, SE_DATATRANSFER_STOP,
 };
+use crate::devices::se_rng::random_with_length_cs;
+use crate::hal::SeMailbox;
 use crate::in_free;
 
 pub const KEY_META_LEN: usize = 8;
@@ -94,6 +96,41 @@ pub fn decode_entropy(protected: &Protected) -> Vec<u8> {
     entropy.unwrap()
 }
 
+/// Key is kept wrapped in [`KEY_BUFFER`]; AES GCM here uses zero AAD and IV, as entropy
+/// encoding does
+impl SeMailbox for Peripherals {
+    type Error = RxError;
+
+    fn fill_random(&mut self, dest: &mut [u8]) -> Result<(), RxError> {
+        let random = random_with_length_cs(self, dest.len())?;
+        dest.copy_from_slice(&random);
+        Ok(())
+    }
+
+    fn create_key(&mut self) -> Result<(), RxError> {
+        create_key(self)
+    }
+
+    fn aes_gcm_encrypt(&mut self, secret: &[u8], encoded: &mut [u8], tag: &mut [u8]) -> Result<(), RxError> {
+        let out = aes_gcm_encrypt(self, [0; AAD_LEN], [0; IV_LEN], secret.to_vec())?;
+        encoded.copy_from_slice(&out.data[..out.len]);
+        tag.copy_from_slice(&out.tag);
+        Ok(())
+    }
+
+    fn aes_gcm_decrypt(&mut self, encoded: &[u8], tag: &[u8], decoded: &mut [u8]) -> Result<(), RxError> {
+        let mut out_encoded = Out {
+            data: [0; SECRET_MAX_LEN],
+            len: encoded.len(),
+            tag: tag.try_into().map_err(|_| RxError::InvalidParameter)?,
+        };
+        out_encoded.data.get_mut(..encoded.len()).ok_or(RxError::InvalidParameter)?.copy_from_slice(encoded);
+        let out = aes_gcm_decrypt(self, &out_encoded, [0; AAD_LEN], [0; IV_LEN])?;
+        decoded.copy_from_slice(&out.data[..out.len]);
+        Ok(())
+    }
+}
+
 pub fn create_key(peripherals: &mut Peripherals) -> Result<(), RxError> {
     let command_word = SE_COMMAND_CREATE_KEY;
 
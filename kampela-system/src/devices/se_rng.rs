@@ -3,6 +3,7 @@
 use alloc::{vec, vec::Vec};
 use core::{mem::size_of, ptr::addr_of};
 
+use efm32pg23_fix::Peripherals;
 use rand_core::{CryptoRng, Error, RngCore};
 
 use crate::peripherals::se_command::{
@@ -41,6 +42,13 @@ impl CryptoRng for SeRng {}
 pub const PAGE_SIZE: usize = 4;
 
 pub fn random_with_length(len: usize) -> Result<Vec<u8>, RxError> {
+    let mut out = None;
+    in_free(|peripherals| out = Some(random_with_length_cs(peripherals, len)));
+    out.expect("peripherals are available")
+}
+
+/// Random bytes from SE, for use within critical section
+pub fn random_with_length_cs(peripherals: &mut Peripherals, len: usize) -> Result<Vec<u8>, RxError> {
     // bytes in whole 4-byte pages
     let even = len & !0x3;
 
@@ -76,10 +84,7 @@ pub fn random_with_length(len: usize) -> Result<Vec<u8>, RxError> {
             parameters: parameters.as_slice(),
         };
         
-        in_free(|peripherals| {
-            se_command.execute(peripherals).expect("se_rng communication error");
-        }
-        );
+        se_command.execute(peripherals)?;
     }
 
     if odd > 0 {
@@ -100,10 +105,7 @@ pub fn random_with_length(len: usize) -> Result<Vec<u8>, RxError> {
             parameters: parameters.as_slice(),
         };
 
-        in_free(|peripherals| {
-            se_command.execute(peripherals).expect("se_rng communication error");
-        }
-        );
+        se_command.execute(peripherals)?;
     }
 
     out.truncate(len);
@@ -7,13 +7,14 @@ use cortex_m::asm::delay;
 use crate::peripherals::i2c::{acknowledge_i2c_tx, acknowledge_i2c_tx_free, check_i2c_errors, check_i2c_errors_free, I2CError, mstop_i2c_wait_and_clear, mstop_i2c_wait_and_clear_free, ReadI2C};
 use crate::peripherals::gpio_pins::{touch_res_set, touch_res_clear, is_touch_int};
 use crate::parallel::{DELAY, Operation};
+use crate::hal::{TouchBus, TOUCH_DATA_LEN};
 use crate::{FreeError, in_free, if_in_free};
 
 pub const FT6X36_REG_CHIPID: u8 = 0xA3;
 pub const LEN_CHIPID: usize = 1;
 
 pub const FT6X36_REG_NUM_TOUCHES: u8 = 0x02;
-pub const LEN_NUM_TOUCHES: usize = 5;
+pub const LEN_NUM_TOUCHES: usize = TOUCH_DATA_LEN;
 
 /*
 /// Blocking write function
@@ -259,6 +260,36 @@ pub fn touch_detected() -> Result<bool, FreeError> {
     })
 }
 
+/// Touch panel driven by async [`Read`] operations, one started per [`TouchBus::read_touch`] series
+pub struct Efm32Touch {
+    read: Option<Read<LEN_NUM_TOUCHES, FT6X36_REG_NUM_TOUCHES>>,
+}
+
+impl Efm32Touch {
+    pub fn new() -> Self {
+        Self {
+            read: None,
+        }
+    }
+}
+
+impl TouchBus for Efm32Touch {
+    type Error = I2CError;
+
+    fn touch_detected(&mut self) -> bool {
+        touch_detected().unwrap_or(false)
+    }
+
+    fn read_touch(&mut self) -> Result<Option<[u8; TOUCH_DATA_LEN]>, I2CError> {
+        let read = self.read.get_or_insert_with(|| Read::new(()));
+        let result = read.advance(());
+        if !matches!(result, Ok(None)) {
+            self.read = None;
+        }
+        result
+    }
+}
+
 pub fn init_touch(peripherals: &mut Peripherals) {
     touch_res_set(&mut peripherals.GPIO_S); // datasheet: pulse width >=1ms
     delay(10000);
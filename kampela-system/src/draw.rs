@@ -10,19 +10,22 @@ use embedded_graphics::{
     Pixel,
 };
 
-use kampela_display_common::display_def::*;
+use kampela_display_common::{display_def::*, orientation::Orientation};
 use qrcodegen_no_heap::{QrCode, QrCodeEcc, Version};
 
-use crate::devices::display::{FastDraw, FullDraw, PartDraw, Request};
+use crate::devices::display::Request;
+use crate::devices::epd_sequence::{RamArea, FAST_DRAW, FULL_AREA, FULL_DRAW, PART_DRAW};
 use crate::devices::display_transmission::{epaper_deep_sleep, display_is_busy};
 use crate::debug_display::epaper_draw_stuff_differently;
+use crate::peripherals::temperature::read_temperature;
+use crate::thresholds::{FAST_REFRESH_MIN_TEMPERATURE, FAST_REFRESH_POWER, FULL_REFRESH_POWER, PART_REFRESH_POWER};
 
 const SCREEN_SIZE_VALUE: usize = (SCREEN_SIZE_X*SCREEN_SIZE_Y) as usize;
 
 use crate::{in_free, parallel::Operation}; 
 
-// x and y of framebuffer and display RAM address are inversed
-fn refreshable_area_address(refreshable_area: Rectangle) -> (u8, u8, u16, u16) {
+// x and y of framebuffer and display RAM address are inversed; area is in panel coordinates
+fn refreshable_area_address(refreshable_area: Rectangle) -> RamArea {
     let x_start_address: u8 = if refreshable_area.top_left.y < 0 {
         0
     } else if refreshable_area.top_left.y > (SCREEN_SIZE_Y - 1) as i32 {
@@ -63,30 +66,43 @@ fn refreshable_area_address(refreshable_area: Rectangle) -> (u8, u8, u16, u16) {
 #[derive(Debug)]
 pub enum DisplayError {}
 
-/// These are voltage thresholds to allow screen updates;
-/// for wired debug, set both well below 5000
-///
-//TODO tune these values for prod; something like 12k and 8k
-const FAST_REFRESH_POWER: i32 = 5000;
-const FULL_REFRESH_POWER: i32 = 5000;
-const PART_REFRESH_POWER: i32 = 5000;
-
 /// Virtual display data storage
 type PixelData = BitArr!(for SCREEN_SIZE_VALUE, in u8, Msb0);
 
+// this was an experiment to find Y offset value in memory
+//const SHIFT_COEFFICIENT: usize = (SCREEN_SIZE_Y * 7) as usize;
 
-/// A virtual display that could be written to EPD simultaneously
-pub struct FrameBuffer {
-    data: PixelData,
+/// Pixel index in display RAM plane for point in panel coordinates
+pub(crate) fn pixel_index(point: Point) -> Option<usize> {
+    if (point.x<0)|(point.x>=SCREEN_SIZE_X as i32) {return None}
+    if (point.y<0)|(point.y>=SCREEN_SIZE_Y as i32) {return None}
+    //transposing pizels correctly here
+    let n = (point.y + point.x*SCREEN_SIZE_Y as i32) /*(pixel.0.y*176 + (175 - pixel.0.x))*/ as usize;
+    //let n = if n<SHIFT_COEFFICIENT { n + SCREEN_SIZE_VALUE - SHIFT_COEFFICIENT } else { n - SHIFT_COEFFICIENT };
+    Some(n)
+}
+
+/// Too cold for updates with fixed waveform, see [`FAST_REFRESH_MIN_TEMPERATURE`]
+fn too_cold_for_fast() -> bool {
+    read_temperature() < FAST_REFRESH_MIN_TEMPERATURE
+}
+
+/// Refresh progress, orientation and area changed since last refresh request
+struct Refresh {
+    /// Drawing and refresh areas are in UI coordinates, rotated with this orientation
+    orientation: Orientation,
+    /// Corners of area with pixels changed since last refresh request, inclusive, in panel
+    /// coordinates
+    dirty: Option<(Point, Point)>,
     display_state: DisplayState,
     timer: usize,
 }
 
-impl FrameBuffer {
-    /// Create new virtual display and fill it with ON pixels
-    pub fn new_white() -> Self {
+impl Refresh {
+    fn new() -> Self {
         Self {
-            data: bitarr!(u8, Msb0; 1; SCREEN_SIZE_X as usize*SCREEN_SIZE_Y as usize),
+            orientation: Orientation::default(),
+            dirty: None,
             display_state: DisplayState::Idle,
             timer: 0,
         }
@@ -101,6 +117,139 @@ impl FrameBuffer {
         }
     }
 
+    fn mark_dirty(&mut self, point: Point) {
+        self.dirty = Some(match self.dirty {
+            Some((top_left, bottom_right)) => (
+                Point::new(top_left.x.min(point.x), top_left.y.min(point.y)),
+                Point::new(bottom_right.x.max(point.x), bottom_right.y.max(point.y)),
+            ),
+            None => (point, point),
+        });
+    }
+
+    /// Panel point of UI point
+    fn to_panel(&self, point: Point) -> Point {
+        self.orientation.to_panel(point)
+    }
+
+    fn dirty_area(&self) -> Option<Rectangle> {
+        self.dirty.map(|(top_left, bottom_right)| self.orientation.rectangle_from_panel(Rectangle::with_corners(
+            Point::new(top_left.x, top_left.y / 8 * 8),
+            Point::new(bottom_right.x, bottom_right.y / 8 * 8 + 7),
+        )))
+    }
+
+    /// Temperature is read once here, not on every advance; updates with fixed waveform are
+    /// replaced by full refresh when too cold
+    ///
+    /// Every request covers dirty area, so it is forgotten: full and fast refresh whole screen, and
+    /// partial area is extended by [`Refresh::request_part`].
+    fn request(&mut self, state: DisplayState) {
+        self.dirty = None;
+        self.display_state = match state {
+            DisplayState::FastRequested |
+            DisplayState::PartRequested(_) if too_cold_for_fast() => DisplayState::FullRequested,
+            state => state,
+        };
+    }
+
+    /// Area is extended to cover pixels drawn outside of it since last refresh
+    fn request_part(&mut self, area: Rectangle) {
+        let area = self.orientation.rectangle_to_panel(area);
+        let area = match (self.dirty, area.bottom_right()) {
+            (Some((top_left, bottom_right)), Some(area_bottom_right)) => Rectangle::with_corners(
+                Point::new(top_left.x.min(area.top_left.x), top_left.y.min(area.top_left.y)),
+                Point::new(bottom_right.x.max(area_bottom_right.x), bottom_right.y.max(area_bottom_right.y)),
+            ),
+            (Some((top_left, bottom_right)), None) => Rectangle::with_corners(top_left, bottom_right),
+            (None, _) => area,
+        };
+        self.request(DisplayState::PartRequested(area));
+    }
+
+    fn wind(&mut self, state: DisplayState, delay: usize) {
+        self.display_state = state;
+        self.timer = delay;
+    }
+
+    /// Move through display update progress; `data` is display RAM content
+    ///
+    /// Fast and partial updates fall back to full refresh in the cold, as decided in
+    /// [`Refresh::request`].
+    fn advance(&mut self, voltage: i32, data: &[u8]) -> Option<bool> {
+        if self.count() { return None };
+
+        match self.display_state {
+            DisplayState::Idle => Some(true),
+            DisplayState::FastRequested => {
+                if voltage > FAST_REFRESH_POWER {        
+                    self.display_state = DisplayState::FastOperating(Request::new((FAST_DRAW, FULL_AREA)));
+                };
+                None
+            },
+            DisplayState::FullRequested => {
+                if voltage > FULL_REFRESH_POWER {
+                    self.display_state = DisplayState::FullOperating(Request::new((FULL_DRAW, FULL_AREA)));
+                };
+                None
+            },
+            DisplayState::PartRequested(r) => {
+                if voltage > PART_REFRESH_POWER {
+                    let area = refreshable_area_address(r);
+                    self.display_state = DisplayState::PartOperating(Request::new((PART_DRAW, area)));
+                };
+                None
+            },
+            DisplayState::FastOperating(ref mut a) |
+            DisplayState::FullOperating(ref mut a) |
+            DisplayState::PartOperating(ref mut a) => {
+                if a.advance(data) {
+                    self.wind(DisplayState::UpdatingNow, 0)
+                }
+                Some(false)
+            },
+            DisplayState::UpdatingNow => {
+                if display_is_busy() == Ok(true) { return Some(false) };
+                in_free(|peripherals| epaper_deep_sleep(peripherals));
+                self.display_state = DisplayState::Idle;
+                Some(false)
+            },
+        }
+    }
+}
+
+/// A virtual display that could be written to EPD simultaneously
+pub struct FrameBuffer {
+    data: PixelData,
+    refresh: Refresh,
+}
+
+impl FrameBuffer {
+    /// Create new virtual display and fill it with ON pixels
+    pub fn new_white() -> Self {
+        Self {
+            data: bitarr!(u8, Msb0; 1; SCREEN_SIZE_X as usize*SCREEN_SIZE_Y as usize),
+            refresh: Refresh::new(),
+        }
+    }
+
+    /// Rotate UI coordinates; content already drawn is not rotated, redraw it before refresh
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.refresh.orientation = orientation;
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.refresh.orientation
+    }
+
+    /// Area of pixels changed since last refresh request, if any
+    ///
+    /// Display RAM addresses panel rows by 8 pixels, so area is extended to that granularity
+    /// across panel rows, see [`refreshable_area_address`].
+    pub fn dirty_area(&self) -> Option<Rectangle> {
+        self.refresh.dirty_area()
+    }
+
     /// Send display data to real EPD; invokes full screen refresh
     ///
     /// this is for cs environment; do not use otherwise
@@ -110,17 +259,17 @@ impl FrameBuffer {
 
     /// Start full display update sequence
     pub fn request_full(&mut self) {
-        self.display_state = DisplayState::FullRequested;
+        self.refresh.request(DisplayState::FullRequested);
     }
 
     /// Start partial fast display update sequence
     pub fn request_fast(&mut self) {
-        self.display_state = DisplayState::FastRequested;
+        self.refresh.request(DisplayState::FastRequested);
     }
 
     /// Start partial fast display update sequence
     pub fn request_part(&mut self, area: Rectangle) {
-        self.display_state = DisplayState::PartRequested(area);
+        self.refresh.request_part(area);
     }
 }
 
@@ -133,13 +282,13 @@ pub enum DisplayState {
     Idle,
     /// Fast update was requested; waiting for power
     FastRequested,
-    FastOperating(Request<FastDraw>),
+    FastOperating(Request),
     /// Slow update was requested; waiting for power
     FullRequested,
-    FullOperating(Request<FullDraw>),
+    FullOperating(Request),
     /// Part update was requested; waiting for power
     PartRequested(Rectangle),
-    PartOperating(Request<PartDraw>, (u8, u8, u16, u16)),
+    PartOperating(Request),
     /// Display not available due to update cycle
     UpdatingNow,
 }
@@ -155,75 +304,21 @@ impl Operation for FrameBuffer {
     }
 
     fn wind(&mut self, state: DisplayState, delay: usize) {
-        self.display_state = state;
-        self.timer = delay;
+        self.refresh.wind(state, delay);
     }
 
     /// Move through display update progress
     fn advance(&mut self, voltage: i32) -> Option<bool> {
-        if self.count() { return None };
-
-        match self.display_state {
-            DisplayState::Idle => Some(true),
-            DisplayState::FastRequested => {
-                if voltage > FAST_REFRESH_POWER {        
-                    self.display_state = DisplayState::FastOperating(Request::<FastDraw>::new(()));
-                };
-                None
-            },
-            DisplayState::FastOperating(ref mut a) => {
-                if a.advance(&self.data.data) {
-                    self.wind(DisplayState::UpdatingNow, 0)
-                }
-                Some(false)
-            },
-            DisplayState::FullRequested => {
-                if voltage > FULL_REFRESH_POWER {
-                    self.display_state = DisplayState::FullOperating(Request::<FullDraw>::new(()));
-                };
-                None
-            },
-            DisplayState::FullOperating(ref mut a) => {
-                if a.advance(&self.data.data) {
-                    self.wind(DisplayState::UpdatingNow, 0)
-                }
-                Some(false)
-            },
-            DisplayState::PartRequested(r) => {
-                if voltage > PART_REFRESH_POWER {
-                    let d: (u8, u8, u16, u16) = refreshable_area_address(r);
-                    self.display_state = DisplayState::PartOperating(Request::<PartDraw>::new(()), d);
-                };
-                None
-            },
-            DisplayState::PartOperating(ref mut a, bounds) => {
-                if a.advance((&self.data.data, bounds)) {
-                    self.wind(DisplayState::UpdatingNow, 0)
-                }
-                Some(false)
-            },
-            DisplayState::UpdatingNow => {
-                if display_is_busy() == Ok(true) { return Some(false) };
-                in_free(|peripherals| epaper_deep_sleep(peripherals));
-                self.display_state = DisplayState::Idle;
-                Some(false)
-            },
-        }
+        self.refresh.advance(voltage, &self.data.data)
     }
 }
 
 impl Dimensions for FrameBuffer {
     fn bounding_box(&self) -> Rectangle {
-            Rectangle {
-                top_left: SCREEN_ZERO,
-                size: SCREEN_SIZE,
-            }
+        SCREEN_AREA
     }
 }
 
-// this was an experiment to find Y offset value in memory
-//const SHIFT_COEFFICIENT: usize = (SCREEN_SIZE_Y * 7) as usize;
-
 impl DrawTarget for FrameBuffer {
     type Color = BinaryColor;
     type Error = DisplayError;
@@ -232,19 +327,17 @@ impl DrawTarget for FrameBuffer {
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
         for pixel in pixels {
-            if (pixel.0.x<0)|(pixel.0.x>=SCREEN_SIZE_X as i32) {continue}
-            if (pixel.0.y<0)|(pixel.0.y>=SCREEN_SIZE_Y as i32) {continue}
-            //transposing pizels correctly here
-            let n = (pixel.0.y + pixel.0.x*SCREEN_SIZE_Y as i32) /*(pixel.0.y*176 + (175 - pixel.0.x))*/ as usize;
-            //let n = if n<SHIFT_COEFFICIENT { n + SCREEN_SIZE_VALUE - SHIFT_COEFFICIENT } else { n - SHIFT_COEFFICIENT };
-            let mut pixel_update = self.data.get_mut(n).expect("checked the bounds");
-            match pixel.1 {
-                BinaryColor::Off => {
-                    *pixel_update = true; //white
-                },
-                BinaryColor::On => {
-                    *pixel_update = false; //black
-                }
+            let point = self.refresh.to_panel(pixel.0);
+            let n = match pixel_index(point) {
+                Some(n) => n,
+                None => continue,
+            };
+            let value = match pixel.1 {
+                BinaryColor::Off => true, //white
+                BinaryColor::On => false, //black
+            };
+            if self.data.replace(n, value) != value {
+                self.refresh.mark_dirty(point);
             }
         }
         Ok(())
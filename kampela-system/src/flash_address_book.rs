@@ -0,0 +1,198 @@
+//! Address book of known accounts
+//!
+//! Contacts are added through signed NFC payload or saved by user from reviewed transaction, and
+//! kept in flash, one contact per page.
+
+use alloc::{format, string::String, vec::Vec};
+
+use parity_scale_codec::{Decode, DecodeAll, Encode};
+use substrate_parser::cards::{ExtendedCard, ParserCard};
+
+use crate::flash_map;
+use crate::hal::{FlashBus, FlashErr};
+use crate::flash_networks::GENESIS_HASH_LEN;
+
+pub const ADDRESS_BOOK_BASE: u32 = flash_map::ADDRESS_BOOK.base();
+const CONTACT_SLOT_SIZE: usize = 256;
+pub const MAX_CONTACTS: usize = flash_map::ADDRESS_BOOK.len() / CONTACT_SLOT_SIZE;
+
+const SLOT_SET: u8 = 1;
+
+pub const ACCOUNT_ID_LEN: usize = 32;
+
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct Contact {
+    pub account_id: [u8; ACCOUNT_ID_LEN],
+    pub label: String,
+    /// Contact is shown only for transactions in this network; `None` for any network
+    pub genesis_hash: Option<[u8; GENESIS_HASH_LEN]>,
+}
+
+impl Contact {
+    /// Read contact from verified NFC update content
+    pub fn from_update(content: &[u8]) -> Option<Self> {
+        Self::decode_all(&mut &content[..]).ok()
+    }
+
+    fn fits(&self, account_id: &[u8; ACCOUNT_ID_LEN], genesis_hash: &[u8; GENESIS_HASH_LEN]) -> bool {
+        &self.account_id == account_id
+            && self.genesis_hash.as_ref().map_or(true, |hash| hash == genesis_hash)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum AddressBookError {
+    Flash(FlashErr),
+    AddressBookFull,
+    TooLarge,
+}
+
+fn slot_address(slot: usize) -> u32 {
+    ADDRESS_BOOK_BASE + (slot * CONTACT_SLOT_SIZE) as u32
+}
+
+fn read_slot<F: FlashBus>(flash: &mut F, slot: usize) -> Option<Contact> {
+    let mut data = [0u8; CONTACT_SLOT_SIZE];
+    if let Err(_) = flash.read_data(slot_address(slot), &mut data) {
+        panic!("Failed to read address book slot {}", slot);
+    }
+    match data[0] {
+        // corrupted records are treated as free slots and get overwritten
+        SLOT_SET => Contact::decode(&mut &data[1..]).ok(),
+        _ => None,
+    }
+}
+
+/// Find contact for account in transaction for network with given genesis hash
+pub fn find_contact<F: FlashBus>(flash: &mut F, account_id: &[u8; ACCOUNT_ID_LEN], genesis_hash: &[u8; GENESIS_HASH_LEN]) -> Option<Contact> {
+    (0..MAX_CONTACTS)
+        .filter_map(|slot| read_slot(flash, slot))
+        .find(|contact| contact.fits(account_id, genesis_hash))
+}
+
+/// Put contact in flash, replacing older record for the same account and network if there is one
+pub fn store_contact<F: FlashBus>(flash: &mut F, contact: &Contact) -> Result<(), AddressBookError> {
+    let encoded = contact.encode();
+    if encoded.len() + 1 > CONTACT_SLOT_SIZE {
+        return Err(AddressBookError::TooLarge)
+    }
+
+    let mut free_slot = None;
+    let mut same_slot = None;
+    for slot in 0..MAX_CONTACTS {
+        match read_slot(flash, slot) {
+            Some(stored) => {
+                if stored.account_id == contact.account_id && stored.genesis_hash == contact.genesis_hash {
+                    same_slot = Some(slot);
+                    break
+                }
+            },
+            None => {
+                if free_slot.is_none() {
+                    free_slot = Some(slot);
+                }
+            },
+        }
+    }
+    let slot = same_slot.or(free_slot).ok_or(AddressBookError::AddressBookFull)?;
+
+    let mut data = [0xffu8; CONTACT_SLOT_SIZE];
+    data[0] = SLOT_SET;
+    data[1..1 + encoded.len()].copy_from_slice(&encoded);
+    flash.store_data(slot_address(slot), &data).map_err(AddressBookError::Flash)
+}
+
+/// Show card, with labels after account ids known to address book.
+///
+/// Accounts not in address book are collected, each with account id and its card as shown.
+pub fn show_card<F: FlashBus>(flash: &mut F, card: &ExtendedCard, genesis_hash: &[u8; GENESIS_HASH_LEN], unsaved_accounts: &mut Vec<([u8; ACCOUNT_ID_LEN], String)>) -> String {
+    let shown = card.show();
+    if let ParserCard::Id{ref id, ..} = card.parser_card {
+        match find_contact(flash, &id.0, genesis_hash) {
+            Some(contact) => return format!("{shown} -> {}", contact.label),
+            None => {
+                if !unsaved_accounts.iter().any(|(account_id, _)| account_id == &id.0) {
+                    unsaved_accounts.push((id.0, String::from(shown.trim())));
+                }
+            },
+        }
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hal::mock::MockFlash;
+
+    const POLKADOT: [u8; GENESIS_HASH_LEN] = [0x91; GENESIS_HASH_LEN];
+    const KUSAMA: [u8; GENESIS_HASH_LEN] = [0xb0; GENESIS_HASH_LEN];
+
+    fn flash() -> MockFlash {
+        MockFlash::new(flash_map::ADDRESS_BOOK.end() as usize)
+    }
+
+    fn contact(account: u8, label: &str, genesis_hash: Option<[u8; GENESIS_HASH_LEN]>) -> Contact {
+        Contact {
+            account_id: [account; ACCOUNT_ID_LEN],
+            label: String::from(label),
+            genesis_hash,
+        }
+    }
+
+    #[test]
+    fn contact_is_found() {
+        let mut flash = flash();
+        store_contact(&mut flash, &contact(1, "Alice", None)).unwrap();
+        store_contact(&mut flash, &contact(2, "Bob", None)).unwrap();
+        assert_eq!(find_contact(&mut flash, &[2; ACCOUNT_ID_LEN], &POLKADOT).unwrap().label, "Bob");
+        assert!(find_contact(&mut flash, &[3; ACCOUNT_ID_LEN], &POLKADOT).is_none());
+    }
+
+    #[test]
+    fn contact_for_network_is_found_only_in_it() {
+        let mut flash = flash();
+        store_contact(&mut flash, &contact(1, "Alice", Some(KUSAMA))).unwrap();
+        assert_eq!(find_contact(&mut flash, &[1; ACCOUNT_ID_LEN], &KUSAMA).unwrap().label, "Alice");
+        assert!(find_contact(&mut flash, &[1; ACCOUNT_ID_LEN], &POLKADOT).is_none());
+    }
+
+    #[test]
+    fn contact_is_replaced() {
+        let mut flash = flash();
+        store_contact(&mut flash, &contact(1, "Alice", None)).unwrap();
+        store_contact(&mut flash, &contact(1, "Alice Cold", None)).unwrap();
+        assert_eq!(find_contact(&mut flash, &[1; ACCOUNT_ID_LEN], &POLKADOT).unwrap().label, "Alice Cold");
+        assert_eq!((0..MAX_CONTACTS).filter_map(|slot| read_slot(&mut flash, slot)).count(), 1);
+    }
+
+    #[test]
+    fn address_book_fills_up() {
+        let mut flash = flash();
+        for account in 0..MAX_CONTACTS as u8 {
+            store_contact(&mut flash, &contact(account, "Contact", None)).unwrap();
+        }
+        assert!(matches!(
+            store_contact(&mut flash, &contact(MAX_CONTACTS as u8, "Extra", None)),
+            Err(AddressBookError::AddressBookFull)
+        ));
+        // existing contacts could still be updated
+        store_contact(&mut flash, &contact(0, "Renamed", None)).unwrap();
+    }
+
+    #[test]
+    fn large_contact_is_rejected() {
+        let label = "a".repeat(CONTACT_SLOT_SIZE);
+        assert!(matches!(store_contact(&mut flash(), &contact(1, &label, None)), Err(AddressBookError::TooLarge)));
+    }
+
+    #[test]
+    fn contact_is_read_from_update() {
+        let stored = contact(1, "Alice", Some(KUSAMA));
+        let contact = Contact::from_update(&stored.encode()).unwrap();
+        assert_eq!(contact.label, "Alice");
+        assert_eq!(contact.genesis_hash, Some(KUSAMA));
+        assert!(Contact::from_update(&[0; 3]).is_none());
+    }
+}
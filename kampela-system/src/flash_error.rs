@@ -0,0 +1,167 @@
+//! Last fatal error kept in flash, to be shown again on next boot
+//!
+//! Report is assembled in panic handler, so it is built without heap, in fixed size buffers.
+
+use core::fmt::{self, Write};
+
+use crate::flash_map;
+use crate::hal::{FlashBus, FlashErr};
+
+pub const ERROR_BASE: u32 = flash_map::ERROR.base();
+const ERROR_SLOT_SIZE: usize = 256;
+
+const ERROR_SET: u8 = 1;
+
+/// Module name limit, bytes
+pub const MODULE_LEN: usize = 48;
+/// Error message limit, bytes
+pub const MESSAGE_LEN: usize = 192;
+
+/// Text truncated at limit on char boundary, for formatting without heap
+#[derive(Clone, Copy, Debug)]
+pub struct BoundedText<const N: usize> {
+    data: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> BoundedText<N> {
+    pub fn new() -> Self {
+        Self {
+            data: [0; N],
+            len: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.data[..self.len]).expect("only whole chars are written")
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut out = Self::new();
+        let text = match core::str::from_utf8(bytes) {
+            Ok(a) => a,
+            Err(e) => core::str::from_utf8(&bytes[..e.valid_up_to()]).expect("checked just now"),
+        };
+        let _ = out.write_str(text);
+        out
+    }
+}
+
+impl<const N: usize> Default for BoundedText<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Never fails: text that does not fit is dropped
+impl<const N: usize> Write for BoundedText<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let c_len = c.len_utf8();
+            if self.len + c_len > N { break }
+            c.encode_utf8(&mut self.data[self.len..self.len + c_len]);
+            self.len += c_len;
+        }
+        Ok(())
+    }
+}
+
+/// Fatal error report
+#[derive(Clone, Copy, Debug)]
+pub struct ErrorReport {
+    /// Short code of source location, same for the same location across boots and devices
+    pub id: u16,
+    /// Module path, e.g. `kampela-system::devices::display`
+    pub module: BoundedText<MODULE_LEN>,
+    pub line: u32,
+    pub message: BoundedText<MESSAGE_LEN>,
+}
+
+impl ErrorReport {
+    /// Report for error at source location; message is written afterwards
+    pub fn new(file: &str, line: u32) -> Self {
+        let mut module = BoundedText::new();
+        write_module(file, &mut module);
+        Self {
+            id: error_id(file, line),
+            module,
+            line,
+            message: BoundedText::new(),
+        }
+    }
+
+    /// Full report, e.g. for QR code
+    pub fn write_full<W: Write>(&self, out: &mut W) -> fmt::Result {
+        write!(out, "E{:04X} {}:{} {}", self.id, self.module.as_str(), self.line, self.message.as_str())
+    }
+
+    fn encode(&self) -> [u8; ERROR_SLOT_SIZE] {
+        let mut data = [0xffu8; ERROR_SLOT_SIZE];
+        data[0] = ERROR_SET;
+        data[1..3].copy_from_slice(&self.id.to_le_bytes());
+        data[3..7].copy_from_slice(&self.line.to_le_bytes());
+        let module = self.module.as_str().as_bytes();
+        data[7] = module.len() as u8;
+        data[8..8 + module.len()].copy_from_slice(module);
+        let message = self.message.as_str().as_bytes();
+        let message_start = 8 + MODULE_LEN;
+        data[message_start] = message.len() as u8;
+        data[message_start + 1..message_start + 1 + message.len()].copy_from_slice(message);
+        data
+    }
+
+    fn decode(data: &[u8; ERROR_SLOT_SIZE]) -> Option<Self> {
+        if data[0] != ERROR_SET { return None }
+        let module_len = (data[7] as usize).min(MODULE_LEN);
+        let message_start = 8 + MODULE_LEN;
+        let message_len = (data[message_start] as usize).min(MESSAGE_LEN);
+        Some(Self {
+            id: u16::from_le_bytes([data[1], data[2]]),
+            line: u32::from_le_bytes(data[3..7].try_into().expect("static length")),
+            module: BoundedText::from_bytes(&data[8..8 + module_len]),
+            message: BoundedText::from_bytes(&data[message_start + 1..message_start + 1 + message_len]),
+        })
+    }
+}
+
+/// FNV-1a of file and line, folded to 16 bits
+fn error_id(file: &str, line: u32) -> u16 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in file.bytes().chain(line.to_le_bytes()) {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    ((hash >> 16) ^ (hash & 0xffff)) as u16
+}
+
+/// Module path from source file path: crate directory, then path within `src` without extension
+fn write_module<W: Write>(file: &str, out: &mut W) {
+    let file = file.strip_suffix(".rs").unwrap_or(file);
+    let (crate_dir, path) = match file.rsplit_once("/src/") {
+        Some((crate_path, path)) => (crate_path.rsplit('/').next(), path),
+        None => (None, file),
+    };
+    if let Some(crate_dir) = crate_dir {
+        let _ = write!(out, "{}::", crate_dir);
+    }
+    for (i, part) in path.split('/').enumerate() {
+        let _ = if i == 0 { out.write_str(part) } else { write!(out, "::{}", part) };
+    }
+}
+
+/// Store report, replacing previous one
+pub fn store_error<F: FlashBus>(flash: &mut F, report: &ErrorReport) -> Result<(), FlashErr> {
+    flash.store_data(ERROR_BASE, &report.encode())
+}
+
+/// Read stored report, if any
+pub fn read_error<F: FlashBus>(flash: &mut F) -> Option<ErrorReport> {
+    let mut data = [0u8; ERROR_SLOT_SIZE];
+    flash.read_data(ERROR_BASE, &mut data).ok()?;
+    ErrorReport::decode(&data)
+}
+
+/// Forget stored report, once it was shown
+pub fn clear_error<F: FlashBus>(flash: &mut F) {
+    flash.erase_data(ERROR_BASE, 1);
+}
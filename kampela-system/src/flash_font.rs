@@ -0,0 +1,74 @@
+//! Proportional font image in flash
+//!
+//! Image is built with `kampela-host font` and written at [`FONT_BASE`] with flash programmer,
+//! same as wordlist. Erased flash holds no font, and UI then keeps to ASCII mono fonts.
+
+use alloc::collections::VecDeque;
+use core::cell::RefCell;
+
+use kampela_display_common::font::FontStorage;
+
+use crate::devices::flash::read_data;
+use crate::flash_map;
+use crate::hal::FLASH_PAGE_SIZE;
+
+/// Font record in [`flash_map`], past the end of wordlist
+pub const FONT_BASE: u32 = flash_map::FONT.base();
+
+const CACHE_SIZE: usize = 4;
+
+struct CachedChunk {
+    chunk_index: u32,
+    cache: [u8; FLASH_PAGE_SIZE],
+}
+
+/// Font image read page by page, with a few recent pages cached: glyph lookup reads index
+/// entries close to each other
+pub struct FlashFont {
+    cached_chunks_cell: RefCell<VecDeque<CachedChunk>>,
+}
+
+impl FlashFont {
+    pub fn new() -> Self {
+        Self {
+            cached_chunks_cell: RefCell::new(VecDeque::with_capacity(CACHE_SIZE)),
+        }
+    }
+
+    fn read_font_chunk(&self, chunk_index: u32) -> [u8; FLASH_PAGE_SIZE] {
+        let mut cached_chunk = self.cached_chunks_cell.borrow_mut();
+        if let Some(c) = cached_chunk.iter().find(|c| c.chunk_index == chunk_index) {
+            return c.cache;
+        }
+        let mut c = CachedChunk { chunk_index, cache: [0; FLASH_PAGE_SIZE]};
+        if let Err(_) = read_data(FONT_BASE + chunk_index * FLASH_PAGE_SIZE as u32, &mut c.cache) {
+            panic!("couldn't read from flash font chunk №{}", chunk_index)
+        };
+        if cached_chunk.len() >= CACHE_SIZE {
+            cached_chunk.pop_front();
+        }
+        let cache = c.cache;
+        cached_chunk.push_back(c);
+        cache
+    }
+}
+
+impl Default for FlashFont {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FontStorage for FlashFont {
+    fn read(&self, offset: u32, data: &mut [u8]) {
+        let mut done = 0;
+        while done < data.len() {
+            let position = offset + done as u32;
+            let chunk = self.read_font_chunk(position / FLASH_PAGE_SIZE as u32);
+            let in_chunk = position as usize % FLASH_PAGE_SIZE;
+            let len = (FLASH_PAGE_SIZE - in_chunk).min(data.len() - done);
+            data[done..done + len].copy_from_slice(&chunk[in_chunk..in_chunk + len]);
+            done += len;
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Page map of external flash
+//!
+//! Every record kept in flash has its pages listed here, and flash modules take their base
+//! addresses from this map, so records could not overlap. Pages between records are free.
+
+use crate::hal::FLASH_PAGE_SIZE;
+
+/// Pages taken by record: `count` pages from page `first`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pages {
+    pub first: u32,
+    pub count: u32,
+}
+
+impl Pages {
+    /// Address of first page
+    pub const fn base(&self) -> u32 {
+        self.first * FLASH_PAGE_SIZE as u32
+    }
+
+    /// Page right after the record
+    pub const fn end(&self) -> u32 {
+        self.first + self.count
+    }
+
+    /// Record size limit, bytes
+    pub const fn len(&self) -> usize {
+        self.count as usize * FLASH_PAGE_SIZE
+    }
+}
+
+/// Encrypted seed entropy
+pub const SEED: Pages = Pages { first: 0, count: 1 };
+
+/// Reserved. Update verifier key was kept here until it got provisioned at build time; page is
+/// not reused, so that key left by older firmware is never read as another record.
+pub const VERIFIER_RESERVED: Pages = Pages { first: 8, count: 1 };
+
+pub const SETTINGS: Pages = Pages { first: 9, count: 1 };
+
+/// Firmware keeps PIN in memory only; page is used by simulator
+pub const PIN: Pages = Pages { first: 10, count: 1 };
+
+/// Last fatal error report
+pub const ERROR: Pages = Pages { first: 11, count: 1 };
+
+/// Added networks, one per page
+pub const NETWORKS: Pages = Pages { first: 16, count: 16 };
+
+/// Contacts, one per page
+pub const ADDRESS_BOOK: Pages = Pages { first: 32, count: 32 };
+
+/// BIP39 wordlist written with flash programmer, 2048 words, 32 words per page
+pub const WORDLIST: Pages = Pages { first: 128, count: 64 };
+
+/// Proportional font image written with flash programmer
+pub const FONT: Pages = Pages { first: 256, count: 256 };
+
+/// All records, in flash order
+pub const ALL: [(&str, Pages); 9] = [
+    ("seed", SEED),
+    ("verifier (reserved)", VERIFIER_RESERVED),
+    ("settings", SETTINGS),
+    ("pin", PIN),
+    ("error", ERROR),
+    ("networks", NETWORKS),
+    ("address book", ADDRESS_BOOK),
+    ("wordlist", WORDLIST),
+    ("font", FONT),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mnemonic_external::TOTAL_WORDS;
+
+    use crate::flash_mnemonic::CHUNK_WORDS;
+
+    #[test]
+    fn records_do_not_overlap() {
+        for pair in ALL.windows(2) {
+            let (name, pages) = pair[0];
+            let (next_name, next) = pair[1];
+            assert!(pages.count > 0, "{name} is empty");
+            assert!(pages.end() <= next.first, "{name} overlaps {next_name}");
+        }
+    }
+
+    #[test]
+    fn wordlist_fits() {
+        assert_eq!(WORDLIST.count as usize * CHUNK_WORDS, TOTAL_WORDS);
+    }
+}
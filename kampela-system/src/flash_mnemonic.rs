@@ -3,10 +3,13 @@ use alloc::{vec::Vec, string::String, collections::VecDeque};
 
 use core::cell::RefCell;
 
-//use crate::wordlist::WORDLIST_ENGLISH;
 use mnemonic_external::{AsWordList, Bits11, WordListElement, TOTAL_WORDS, WORD_MAX_LEN, error::ErrorWordList};
 
-use crate::devices::flash::read_data;
+#[cfg(feature = "efm32")]
+use crate::devices::flash::FreeFlash;
+use crate::flash_map;
+use crate::hal::{FlashBus, FlashErr};
+
 const WORDLIST_STARTS: [usize; 26] = [
     0, 4, 7, 13, 17, 20, 23,
     26, 28, 29, 30, 31, 33, 36,
@@ -17,19 +20,30 @@ const FIRST_WORDLIST_STARTS: u8 = 0x61;
 
 const CACHE_SIZE: usize = 5;
 pub const MAX_PROPOSAL: usize = 3;
-const WORDLIST_BASE: u32 = 128*256;
+pub const WORDLIST_BASE: u32 = flash_map::WORDLIST.base();
+/// Words in flash page
+pub(crate) const CHUNK_WORDS: usize = 32;
 
 struct CachedChunk {
     chunk_index: usize,
     cache: [u8; 256]
 }
-pub struct FlashWordList {
+pub struct FlashWordList<F: FlashBus> {
+    flash: RefCell<F>,
     cached_chunks_cell: RefCell<VecDeque<CachedChunk>>
 }
 
-impl FlashWordList {
+#[cfg(feature = "efm32")]
+impl FlashWordList<FreeFlash> {
     pub fn new() -> Self {
+        Self::with_flash(FreeFlash)
+    }
+}
+
+impl<F: FlashBus> FlashWordList<F> {
+    pub fn with_flash(flash: F) -> Self {
         Self {
+            flash: RefCell::new(flash),
             cached_chunks_cell: RefCell::new(VecDeque::with_capacity(CACHE_SIZE))
         }
     }
@@ -42,7 +56,7 @@ impl FlashWordList {
             }
         }
         let mut c = CachedChunk { chunk_index, cache: [0; 256]};
-        if let Err(_) = read_data(WORDLIST_BASE + chunk_index as u32 * 256, &mut c.cache) {
+        if let Err(_) = self.flash.borrow_mut().read_data(WORDLIST_BASE + chunk_index as u32 * 256, &mut c.cache) {
             panic!("couldn't read from flash wordlist chunk №{}", chunk_index)
         };
         if cached_chunk.len() >= CACHE_SIZE {
@@ -53,7 +67,7 @@ impl FlashWordList {
     }
 }
 
-impl AsWordList for FlashWordList {
+impl<F: FlashBus> AsWordList for FlashWordList<F> {
     type Word = String;
     fn get_word(&self, bits: Bits11) -> Result<Self::Word, ErrorWordList> {
         let word_order = bits.bits() as usize;
@@ -132,15 +146,58 @@ impl AsWordList for FlashWordList {
         Err(ErrorWordList::NoWord)
     }
 }
-/*
-pub fn store_wordlist() {
-    for (i, chunk) in WORDLIST_ENGLISH.chunks(32).enumerate() {
-        let mut data: [u8; 256] = [0x20u8; 256];
-        for (j, w) in chunk.iter().enumerate() {
-            data[j*8..j*8+w.len()].copy_from_slice((*w).as_bytes())
+/// Put wordlist in flash as [`FlashWordList`] reads it: 32 words per page, each padded with
+/// spaces to [`WORD_MAX_LEN`]
+pub fn store_wordlist<F: FlashBus>(flash: &mut F, words: &[&str]) -> Result<(), FlashErr> {
+    for (i, chunk) in words.chunks(CHUNK_WORDS).enumerate() {
+        let mut data = [b' '; CHUNK_WORDS * WORD_MAX_LEN];
+        for (j, word) in chunk.iter().enumerate() {
+            data[j * WORD_MAX_LEN..j * WORD_MAX_LEN + word.len()].copy_from_slice(word.as_bytes());
         }
-        if let Err(e) = store_data(((i+128)*256) as u32, &data) {
-            panic!("could not store wordlist chunk {}", i)
-        };
+        flash.store_data(WORDLIST_BASE + (i * data.len()) as u32, &data)?;
     }
-}*/
\ No newline at end of file
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use mnemonic_external::regular::InternalWordList;
+
+    use crate::hal::mock::MockFlash;
+
+    fn flash_with_wordlist() -> MockFlash {
+        let words: Vec<String> = (0..TOTAL_WORDS as u16)
+            .map(|i| InternalWordList.get_word(Bits11::from(i).unwrap()).unwrap())
+            .collect();
+        let words: Vec<&str> = words.iter().map(|word| word.as_str()).collect();
+        let mut flash = MockFlash::new(flash_map::WORDLIST.end() as usize);
+        store_wordlist(&mut flash, &words).unwrap();
+        flash
+    }
+
+    #[test]
+    fn every_word_is_read_back() {
+        let wordlist = FlashWordList::with_flash(flash_with_wordlist());
+        for i in 0..TOTAL_WORDS as u16 {
+            let bits = Bits11::from(i).unwrap();
+            let word = wordlist.get_word(bits).unwrap();
+            assert_eq!(word, InternalWordList.get_word(bits).unwrap());
+            assert_eq!(wordlist.bits11_for_word(&word).unwrap().bits() as usize, i as usize);
+        }
+    }
+
+    #[test]
+    fn words_are_proposed_by_prefix() {
+        let wordlist = FlashWordList::with_flash(flash_with_wordlist());
+        let proposals: Vec<String> = wordlist
+            .get_words_by_prefix("ab")
+            .unwrap()
+            .into_iter()
+            .map(|element| element.word)
+            .collect();
+        assert_eq!(proposals, ["abandon", "ability", "able"]);
+        assert!(wordlist.bits11_for_word("abacus").is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Registry of known networks
+//!
+//! Polkadot, Kusama and Westend are built in; other networks are added through signed NFC
+//! payload and kept in flash, one network per page. Built-in networks could not be replaced.
+//!
+//! Registry only names network and gives its logo; decimals and unit for amounts are always taken
+//! from metadata.
+
+use alloc::{borrow::ToOwned, string::String};
+
+use kampela_display_common::display_def::NETWORK_LOGO_LEN;
+use parity_scale_codec::{Decode, DecodeAll, Encode};
+
+use crate::flash_map;
+use crate::hal::{FlashBus, FlashErr};
+
+const NETWORKS_BASE: u32 = flash_map::NETWORKS.base();
+const NETWORK_SLOT_SIZE: usize = 256;
+pub const MAX_STORED_NETWORKS: usize = flash_map::NETWORKS.len() / NETWORK_SLOT_SIZE;
+
+const SLOT_SET: u8 = 1;
+
+pub const GENESIS_HASH_LEN: usize = 32;
+
+/// Everything device needs to know about network
+#[derive(Clone, Debug, Decode, Encode)]
+pub struct NetworkSpecs {
+    pub genesis_hash: [u8; GENESIS_HASH_LEN],
+    pub name: String,
+    pub base58prefix: u16,
+    /// Informational only, amounts are shown with decimals from metadata
+    pub decimals: u8,
+    /// Informational only, amounts are shown with unit from metadata
+    pub unit: String,
+    pub logo: [u8; NETWORK_LOGO_LEN],
+}
+
+impl NetworkSpecs {
+    /// Read specs from verified NFC update content
+    pub fn from_update(content: &[u8]) -> Option<Self> {
+        Self::decode_all(&mut &content[..]).ok()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum NetworkStoreError {
+    /// Genesis hash is one of built-in networks
+    Builtin,
+    Flash(FlashErr),
+    RegistryFull,
+    TooLarge,
+}
+
+struct BuiltinNetwork {
+    genesis_hash: [u8; GENESIS_HASH_LEN],
+    name: &'static str,
+    base58prefix: u16,
+    decimals: u8,
+    unit: &'static str,
+    logo: [u8; NETWORK_LOGO_LEN],
+}
+
+impl BuiltinNetwork {
+    fn to_specs(&self) -> NetworkSpecs {
+        NetworkSpecs {
+            genesis_hash: self.genesis_hash,
+            name: self.name.to_owned(),
+            base58prefix: self.base58prefix,
+            decimals: self.decimals,
+            unit: self.unit.to_owned(),
+            logo: self.logo,
+        }
+    }
+}
+
+const BUILTIN_NETWORKS: [BuiltinNetwork; 3] = [
+    BuiltinNetwork {
+        genesis_hash: [
+            0x91, 0xb1, 0x71, 0xbb, 0x15, 0x8e, 0x2d, 0x38, 0x48, 0xfa, 0x23, 0xa9, 0xf1, 0xc2, 0x51, 0x82,
+            0xfb, 0x8e, 0x20, 0x31, 0x3b, 0x2c, 0x1e, 0xb4, 0x92, 0x19, 0xda, 0x7a, 0x70, 0xce, 0x90, 0xc3,
+        ],
+        name: "Polkadot",
+        base58prefix: 0,
+        decimals: 10,
+        unit: "DOT",
+        logo: [
+            0x00, 0x00, 0x07, 0xe0, 0x1f, 0xf8, 0x3c, 0x3c, 0x70, 0x0e, 0x61, 0x86, 0xe3, 0xc7, 0xc7, 0xe3,
+            0xc7, 0xe3, 0xe3, 0xc7, 0x61, 0x86, 0x70, 0x0e, 0x3c, 0x3c, 0x1f, 0xf8, 0x07, 0xe0, 0x00, 0x00,
+        ],
+    },
+    BuiltinNetwork {
+        genesis_hash: [
+            0xb0, 0xa8, 0xd4, 0x93, 0x28, 0x5c, 0x2d, 0xf7, 0x32, 0x90, 0xdf, 0xb7, 0xe6, 0x1f, 0x87, 0x0f,
+            0x17, 0xb4, 0x18, 0x01, 0x19, 0x7a, 0x14, 0x9c, 0xa9, 0x36, 0x54, 0x49, 0x9e, 0xa3, 0xda, 0xfe,
+        ],
+        name: "Kusama",
+        base58prefix: 2,
+        decimals: 12,
+        unit: "KSM",
+        logo: [
+            0x00, 0x00, 0x70, 0x1c, 0x70, 0x38, 0x70, 0x70, 0x70, 0xe0, 0x71, 0xc0, 0x73, 0x80, 0x7f, 0x80,
+            0x7f, 0xc0, 0x71, 0xc0, 0x70, 0xe0, 0x70, 0x70, 0x70, 0x38, 0x70, 0x1c, 0x70, 0x0e, 0x00, 0x00,
+        ],
+    },
+    BuiltinNetwork {
+        genesis_hash: [
+            0xe1, 0x43, 0xf2, 0x38, 0x03, 0xac, 0x50, 0xe8, 0xf6, 0xf8, 0xe6, 0x26, 0x95, 0xd1, 0xce, 0x9e,
+            0x4e, 0x1d, 0x68, 0xaa, 0x36, 0xc1, 0xcd, 0x2c, 0xfd, 0x15, 0x34, 0x02, 0x13, 0xf3, 0x42, 0x3e,
+        ],
+        name: "Westend",
+        base58prefix: 42,
+        decimals: 12,
+        unit: "WND",
+        logo: [
+            0x00, 0x00, 0xc0, 0x03, 0xc0, 0x03, 0xc0, 0x03, 0xc1, 0x83, 0xc1, 0x83, 0xc3, 0xc3, 0x63, 0xc6,
+            0x66, 0x66, 0x66, 0x66, 0x3c, 0x3c, 0x3c, 0x3c, 0x38, 0x1c, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00,
+        ],
+    },
+];
+
+fn slot_address(slot: usize) -> u32 {
+    NETWORKS_BASE + (slot * NETWORK_SLOT_SIZE) as u32
+}
+
+fn read_slot<F: FlashBus>(flash: &mut F, slot: usize) -> Option<NetworkSpecs> {
+    let mut data = [0u8; NETWORK_SLOT_SIZE];
+    if let Err(_) = flash.read_data(slot_address(slot), &mut data) {
+        panic!("Failed to read network slot {}", slot);
+    }
+    match data[0] {
+        // corrupted records are treated as free slots and get overwritten
+        SLOT_SET => NetworkSpecs::decode(&mut &data[1..]).ok(),
+        _ => None,
+    }
+}
+
+fn find_builtin(genesis_hash: &[u8; GENESIS_HASH_LEN]) -> Option<&'static BuiltinNetwork> {
+    BUILTIN_NETWORKS.iter().find(|network| &network.genesis_hash == genesis_hash)
+}
+
+/// Find network by genesis hash; built-in networks are checked first
+pub fn find_network<F: FlashBus>(flash: &mut F, genesis_hash: &[u8; GENESIS_HASH_LEN]) -> Option<NetworkSpecs> {
+    if let Some(network) = find_builtin(genesis_hash) {
+        return Some(network.to_specs())
+    }
+    (0..MAX_STORED_NETWORKS)
+        .filter_map(|slot| read_slot(flash, slot))
+        .find(|specs| &specs.genesis_hash == genesis_hash)
+}
+
+/// Put network in flash, replacing older record with the same genesis hash if there is one
+pub fn store_network<F: FlashBus>(flash: &mut F, specs: &NetworkSpecs) -> Result<(), NetworkStoreError> {
+    if find_builtin(&specs.genesis_hash).is_some() {
+        return Err(NetworkStoreError::Builtin)
+    }
+    let encoded = specs.encode();
+    if encoded.len() + 1 > NETWORK_SLOT_SIZE {
+        return Err(NetworkStoreError::TooLarge)
+    }
+
+    let mut free_slot = None;
+    let mut same_slot = None;
+    for slot in 0..MAX_STORED_NETWORKS {
+        match read_slot(flash, slot) {
+            Some(stored) => {
+                if stored.genesis_hash == specs.genesis_hash {
+                    same_slot = Some(slot);
+                    break
+                }
+            },
+            None => {
+                if free_slot.is_none() {
+                    free_slot = Some(slot);
+                }
+            },
+        }
+    }
+    let slot = same_slot.or(free_slot).ok_or(NetworkStoreError::RegistryFull)?;
+
+    let mut data = [0xffu8; NETWORK_SLOT_SIZE];
+    data[0] = SLOT_SET;
+    data[1..1 + encoded.len()].copy_from_slice(&encoded);
+    flash.store_data(slot_address(slot), &data).map_err(NetworkStoreError::Flash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hal::mock::MockFlash;
+
+    fn flash() -> MockFlash {
+        MockFlash::new(flash_map::NETWORKS.end() as usize)
+    }
+
+    fn specs(genesis_hash: [u8; GENESIS_HASH_LEN], name: &str) -> NetworkSpecs {
+        NetworkSpecs {
+            genesis_hash,
+            name: name.to_owned(),
+            base58prefix: 42,
+            decimals: 18,
+            unit: "FAKE".to_owned(),
+            logo: [0; NETWORK_LOGO_LEN],
+        }
+    }
+
+    #[test]
+    fn stored_network_is_found() {
+        let mut flash = flash();
+        store_network(&mut flash, &specs([7; GENESIS_HASH_LEN], "Testnet")).unwrap();
+        assert_eq!(find_network(&mut flash, &[7; GENESIS_HASH_LEN]).unwrap().name, "Testnet");
+        assert!(find_network(&mut flash, &[8; GENESIS_HASH_LEN]).is_none());
+    }
+
+    #[test]
+    fn builtin_network_could_not_be_replaced() {
+        let mut flash = flash();
+        let polkadot = BUILTIN_NETWORKS[0].genesis_hash;
+        assert!(matches!(store_network(&mut flash, &specs(polkadot, "Not Polkadot")), Err(NetworkStoreError::Builtin)));
+        assert_eq!(find_network(&mut flash, &polkadot).unwrap().name, "Polkadot");
+    }
+
+    #[test]
+    fn builtin_network_wins_over_stored_record() {
+        let mut flash = flash();
+        let kusama = BUILTIN_NETWORKS[1].genesis_hash;
+        // record that got in flash before built-in networks were protected
+        let mut data = [0xffu8; NETWORK_SLOT_SIZE];
+        let encoded = specs(kusama, "Not Kusama").encode();
+        data[0] = SLOT_SET;
+        data[1..1 + encoded.len()].copy_from_slice(&encoded);
+        flash.store_data(slot_address(0), &data).unwrap();
+        assert_eq!(find_network(&mut flash, &kusama).unwrap().name, "Kusama");
+    }
+
+    #[test]
+    fn registry_fills_up() {
+        let mut flash = flash();
+        for i in 0..MAX_STORED_NETWORKS {
+            store_network(&mut flash, &specs([i as u8; GENESIS_HASH_LEN], "Testnet")).unwrap();
+        }
+        assert!(matches!(store_network(&mut flash, &specs([0xee; GENESIS_HASH_LEN], "Testnet")), Err(NetworkStoreError::RegistryFull)));
+        // same genesis hash replaces older record
+        store_network(&mut flash, &specs([3; GENESIS_HASH_LEN], "Renamed")).unwrap();
+        assert_eq!(find_network(&mut flash, &[3; GENESIS_HASH_LEN]).unwrap().name, "Renamed");
+    }
+}
@@ -0,0 +1,115 @@
+//! User settings kept in flash
+
+use kampela_display_common::{language::Language, orientation::Orientation};
+
+use crate::flash_map;
+use crate::hal::{FlashBus, FlashErr};
+
+pub const SETTINGS_BASE: u32 = flash_map::SETTINGS.base();
+const SETTINGS_SLOT_SIZE: usize = 256;
+
+const SETTINGS_SET: u8 = 1;
+
+const ALLOW_BLIND_SIGNING: u8 = 0b0000_0001;
+/// Two bits of [`Orientation::code`]; zero in settings stored before it was introduced
+const ORIENTATION_SHIFT: u8 = 1;
+const ORIENTATION_MASK: u8 = 0b11;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Settings {
+    /// Sign transactions that could not be decoded, after showing payload hash
+    pub allow_blind_signing: bool,
+    /// Screen orientation, for drawing and touch
+    pub orientation: Orientation,
+    /// Language of UI strings
+    pub language: Language,
+}
+
+/// Read settings; untouched flash gives defaults
+pub fn read_settings<F: FlashBus>(flash: &mut F) -> Settings {
+    let mut data = [0u8; 3];
+    if let Err(_) = flash.read_data(SETTINGS_BASE, &mut data) {
+        panic!("Failed to read settings");
+    }
+    match data[0] {
+        SETTINGS_SET => Settings {
+            allow_blind_signing: data[1] & ALLOW_BLIND_SIGNING != 0,
+            // portrait codes are never stored; should one be found, default orientation is used
+            orientation: Orientation::from_code((data[1] >> ORIENTATION_SHIFT) & ORIENTATION_MASK).unwrap_or_default(),
+            // erased in settings stored before it was introduced
+            language: Language::from_code(data[2]),
+        },
+        _ => Settings::default(),
+    }
+}
+
+pub fn store_settings<F: FlashBus>(flash: &mut F, settings: &Settings) -> Result<(), FlashErr> {
+    let mut data = [0xffu8; SETTINGS_SLOT_SIZE];
+    data[0] = SETTINGS_SET;
+    data[1] = if settings.allow_blind_signing {ALLOW_BLIND_SIGNING} else {0};
+    data[1] |= settings.orientation.code() << ORIENTATION_SHIFT;
+    data[2] = settings.language.code();
+    flash.store_data(SETTINGS_BASE, &data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hal::mock::MockFlash;
+
+    fn flash() -> MockFlash {
+        MockFlash::new(flash_map::SETTINGS.end() as usize)
+    }
+
+    #[test]
+    fn erased_flash_gives_defaults() {
+        let settings = read_settings(&mut flash());
+        assert!(!settings.allow_blind_signing);
+        assert_eq!(settings.orientation, Orientation::Deg0);
+        assert_eq!(settings.language, Language::English);
+    }
+
+    #[test]
+    fn settings_are_read_back() {
+        let mut flash = flash();
+        for allow_blind_signing in [false, true] {
+            for orientation in Orientation::ALL {
+                for language in Language::ALL {
+                    store_settings(&mut flash, &Settings { allow_blind_signing, orientation, language }).unwrap();
+                    let settings = read_settings(&mut flash);
+                    assert_eq!(settings.allow_blind_signing, allow_blind_signing);
+                    assert_eq!(settings.orientation, orientation);
+                    assert_eq!(settings.language, language);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unsupported_orientation_gives_default() {
+        let mut flash = flash();
+        let mut data = [0xffu8; SETTINGS_SLOT_SIZE];
+        data[0] = SETTINGS_SET;
+        data[1] = ALLOW_BLIND_SIGNING | (1 << ORIENTATION_SHIFT);
+        data[2] = Language::Spanish.code();
+        flash.store_data(SETTINGS_BASE, &data).unwrap();
+        let settings = read_settings(&mut flash);
+        assert!(settings.allow_blind_signing);
+        assert_eq!(settings.orientation, Orientation::Deg0);
+        assert_eq!(settings.language, Language::Spanish);
+    }
+
+    #[test]
+    fn settings_stored_before_language_are_english() {
+        let mut flash = flash();
+        let mut data = [0xffu8; SETTINGS_SLOT_SIZE];
+        data[0] = SETTINGS_SET;
+        data[1] = ALLOW_BLIND_SIGNING;
+        flash.store_data(SETTINGS_BASE, &data).unwrap();
+        let settings = read_settings(&mut flash);
+        assert!(settings.allow_blind_signing);
+        assert_eq!(settings.orientation, Orientation::Deg0);
+        assert_eq!(settings.language, Language::English);
+    }
+}
@@ -0,0 +1,132 @@
+//! Trusted key for signed updates received through NFC
+//!
+//! Verifier public key is provisioned at build time, as hex in `KAMPELA_VERIFIER` environment
+//! variable. Firmware built without it rejects all network and address book updates: key is
+//! never learned from updates themselves, so device could not be taken over through NFC.
+
+use substrate_crypto_light::sr25519::{Public, Signature};
+
+pub const VERIFIER_KEY_LEN: usize = 32;
+pub const VERIFIER_SIGNATURE_LEN: usize = 64;
+
+/// Key provisioned at build time, if any
+pub const VERIFIER: Option<[u8; VERIFIER_KEY_LEN]> = match option_env!("KAMPELA_VERIFIER") {
+    Some(hex) => Some(parse_key(hex)),
+    None => None,
+};
+
+const fn hex_digit(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        b'A'..=b'F' => c - b'A' + 10,
+        _ => panic!("KAMPELA_VERIFIER should be hex"),
+    }
+}
+
+/// Parsed at compile time, so malformed key fails the build
+const fn parse_key(hex: &str) -> [u8; VERIFIER_KEY_LEN] {
+    let bytes = hex.as_bytes();
+    assert!(bytes.len() == 2 * VERIFIER_KEY_LEN, "KAMPELA_VERIFIER should be 32 bytes");
+    let mut key = [0u8; VERIFIER_KEY_LEN];
+    let mut i = 0;
+    while i < VERIFIER_KEY_LEN {
+        key[i] = hex_digit(bytes[2 * i]) << 4 | hex_digit(bytes[2 * i + 1]);
+        i += 1;
+    }
+    key
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VerifierError {
+    BadSignature,
+    /// Firmware was built without verifier key
+    NoVerifier,
+    TooShort,
+    UnknownVerifier,
+}
+
+/// Signed update is verifier public key, followed by signature, followed by signed content.
+///
+/// Content is returned if signed by key provisioned at build time.
+pub fn verified_content(update: &[u8]) -> Result<&[u8], VerifierError> {
+    verified_content_with(update, VERIFIER.as_ref())
+}
+
+fn verified_content_with<'a>(update: &'a [u8], verifier: Option<&[u8; VERIFIER_KEY_LEN]>) -> Result<&'a [u8], VerifierError> {
+    let verifier = verifier.ok_or(VerifierError::NoVerifier)?;
+    if update.len() < VERIFIER_KEY_LEN + VERIFIER_SIGNATURE_LEN {
+        return Err(VerifierError::TooShort)
+    }
+    let public: [u8; VERIFIER_KEY_LEN] = update[..VERIFIER_KEY_LEN].try_into().expect("static length");
+    let signature: [u8; VERIFIER_SIGNATURE_LEN] = update[VERIFIER_KEY_LEN..VERIFIER_KEY_LEN + VERIFIER_SIGNATURE_LEN]
+        .try_into()
+        .expect("static length");
+    let content = &update[VERIFIER_KEY_LEN + VERIFIER_SIGNATURE_LEN..];
+
+    if &public != verifier {
+        return Err(VerifierError::UnknownVerifier)
+    }
+    if !Public(public).verify(content, &Signature(signature)) {
+        return Err(VerifierError::BadSignature)
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec::Vec;
+    use rand::{rngs::StdRng, SeedableRng};
+    use substrate_crypto_light::sr25519::Pair;
+
+    const CONTENT: &[u8] = b"network specs";
+
+    fn pair(seed: u8) -> Pair {
+        Pair::from_entropy_and_pwd(&[seed; 32], "").ok().expect("valid entropy length")
+    }
+
+    fn signed_update(pair: &Pair, content: &[u8]) -> Vec<u8> {
+        let signature = pair.sign_external_rng(content, &mut StdRng::seed_from_u64(0));
+        [&pair.public().0[..], &signature.0[..], content].concat()
+    }
+
+    #[test]
+    fn key_is_parsed() {
+        assert_eq!(parse_key("00ff10Ab00000000000000000000000000000000000000000000000000000001")[..4], [0x00, 0xff, 0x10, 0xab]);
+    }
+
+    #[test]
+    fn accepts_update_from_verifier() {
+        let verifier = pair(1);
+        let update = signed_update(&verifier, CONTENT);
+        assert_eq!(verified_content_with(&update, Some(&verifier.public().0)), Ok(CONTENT));
+    }
+
+    #[test]
+    fn rejects_update_without_provisioned_verifier() {
+        let update = signed_update(&pair(1), CONTENT);
+        assert_eq!(verified_content_with(&update, None), Err(VerifierError::NoVerifier));
+    }
+
+    #[test]
+    fn rejects_update_from_other_signer() {
+        let update = signed_update(&pair(2), CONTENT);
+        assert_eq!(verified_content_with(&update, Some(&pair(1).public().0)), Err(VerifierError::UnknownVerifier));
+    }
+
+    #[test]
+    fn rejects_tampered_update() {
+        let verifier = pair(1);
+        let mut update = signed_update(&verifier, CONTENT);
+        *update.last_mut().unwrap() ^= 1;
+        assert_eq!(verified_content_with(&update, Some(&verifier.public().0)), Err(VerifierError::BadSignature));
+    }
+
+    #[test]
+    fn rejects_short_update() {
+        let verifier = pair(1);
+        assert_eq!(verified_content_with(&[0; 95], Some(&verifier.public().0)), Err(VerifierError::TooShort));
+    }
+}
@@ -0,0 +1,353 @@
+//! In-memory devices for running device logic off-target
+
+use alloc::{collections::VecDeque, vec, vec::Vec};
+use blake2_rfc::blake2b::blake2b;
+
+use crate::devices::psram::{AddressPsram, PSRAM_PAGE_SIZE};
+use super::{EpdBus, FlashBus, PsramBus, SeMailbox, TouchBus, VoltageSource, FLASH_PAGE_SIZE, TOUCH_DATA_LEN};
+
+/// Voltage source following recorded or made up curve, one sample per measurement
+///
+/// After samples run out, last one is kept.
+pub struct MockVoltage {
+    pub samples: VecDeque<i32>,
+    current: i32,
+}
+
+impl MockVoltage {
+    pub fn new(initial: i32) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            current: initial,
+        }
+    }
+
+    /// Queue linear change to `target` over `steps` measurements
+    pub fn ramp(&mut self, target: i32, steps: usize) {
+        let start = self.samples.back().copied().unwrap_or(self.current);
+        for i in 1..=steps {
+            self.samples.push_back(start + (target - start) * i as i32 / steps as i32);
+        }
+    }
+}
+
+impl VoltageSource for MockVoltage {
+    fn advance(&mut self) {
+        if let Some(sample) = self.samples.pop_front() {
+            self.current = sample;
+        }
+    }
+
+    fn read(&self) -> i32 {
+        self.current
+    }
+}
+
+/// Flash with NOR semantics: erased bytes are `0xff`, programming only clears bits
+pub struct MockFlash {
+    pub data: Vec<u8>,
+    awake: bool,
+    /// Times flash was brought out of power down
+    pub wakeups: usize,
+}
+
+impl MockFlash {
+    pub fn new(pages: usize) -> Self {
+        Self {
+            data: vec![0xff; pages * FLASH_PAGE_SIZE],
+            awake: false,
+            wakeups: 0,
+        }
+    }
+
+    /// Page operation on flash in power down would not work on device
+    fn page_start(&self, addr: u32) -> usize {
+        assert!(self.awake, "flash page operation in power down");
+        let start = addr as usize / FLASH_PAGE_SIZE * FLASH_PAGE_SIZE;
+        assert!(start < self.data.len(), "flash address {addr} out of range");
+        start
+    }
+}
+
+impl FlashBus for MockFlash {
+    fn wake(&mut self) {
+        self.awake = true;
+        self.wakeups += 1;
+    }
+
+    fn sleep(&mut self) {
+        self.awake = false;
+    }
+
+    fn erase_page(&mut self, addr: u32) {
+        let start = self.page_start(addr);
+        self.data[start..start + FLASH_PAGE_SIZE].fill(0xff);
+    }
+
+    fn write_page(&mut self, addr: u32, data: &[u8]) {
+        let start = self.page_start(addr);
+        for (stored, new) in self.data[start..start + FLASH_PAGE_SIZE].iter_mut().zip(data.iter()) {
+            *stored &= new;
+        }
+    }
+
+    fn read_page(&mut self, addr: u32, data: &mut [u8; FLASH_PAGE_SIZE]) {
+        let start = self.page_start(addr);
+        data.copy_from_slice(&self.data[start..start + FLASH_PAGE_SIZE]);
+    }
+}
+
+/// PSRAM of given size, wrapping at page end within transaction as real chip does
+pub struct MockPsram {
+    pub data: Vec<u8>,
+    growing: bool,
+}
+
+impl MockPsram {
+    pub fn new(pages: usize) -> Self {
+        Self {
+            data: vec![0; pages * PSRAM_PAGE_SIZE as usize],
+            growing: false,
+        }
+    }
+
+    /// PSRAM holding only pages up to the last one written, for data of size not known in
+    /// advance; unwritten bytes read as zero
+    pub fn growing() -> Self {
+        Self {
+            data: Vec::new(),
+            growing: true,
+        }
+    }
+
+    fn reserve_page(&mut self, address: AddressPsram) {
+        let page_end = (address.as_u32() / PSRAM_PAGE_SIZE + 1) * PSRAM_PAGE_SIZE;
+        if self.growing && self.data.len() < page_end as usize {
+            self.data.resize(page_end as usize, 0);
+        }
+    }
+
+    fn positions(address: AddressPsram, len: usize) -> impl Iterator<Item = usize> {
+        let start = address.as_u32();
+        let page_start = start / PSRAM_PAGE_SIZE * PSRAM_PAGE_SIZE;
+        (0..len as u32).map(move |i| (page_start + (start - page_start + i) % PSRAM_PAGE_SIZE) as usize)
+    }
+}
+
+impl PsramBus for MockPsram {
+    fn reset(&mut self) {}
+
+    fn read_in_page(&mut self, address: AddressPsram, len: usize) -> Vec<u8> {
+        self.reserve_page(address);
+        Self::positions(address, len).map(|position| self.data[position]).collect()
+    }
+
+    fn write_in_page(&mut self, address: AddressPsram, data: &[u8]) {
+        self.reserve_page(address);
+        for (position, byte) in Self::positions(address, data.len()).zip(data.iter()) {
+            self.data[position] = *byte;
+        }
+    }
+}
+
+/// Touch panel replaying queued touch reports
+///
+/// Each report takes `transfer_steps` calls of [`TouchBus::read_touch`] to arrive, to exercise
+/// code waiting for async transfer.
+pub struct MockTouch {
+    pub reports: VecDeque<[u8; TOUCH_DATA_LEN]>,
+    pub transfer_steps: usize,
+    progress: usize,
+}
+
+#[derive(Debug)]
+pub struct NoTouch;
+
+impl MockTouch {
+    pub fn new(transfer_steps: usize) -> Self {
+        Self {
+            reports: VecDeque::new(),
+            transfer_steps,
+            progress: 0,
+        }
+    }
+
+    /// Queue single touch at point, in panel coordinates (panel is rotated against screen)
+    pub fn touch(&mut self, x: u16, y: u16) {
+        let [x_high, x_low] = x.to_be_bytes();
+        let [y_high, y_low] = y.to_be_bytes();
+        self.reports.push_back([1, x_high, x_low, y_high, y_low]);
+    }
+}
+
+impl TouchBus for MockTouch {
+    type Error = NoTouch;
+
+    fn touch_detected(&mut self) -> bool {
+        !self.reports.is_empty()
+    }
+
+    fn read_touch(&mut self) -> Result<Option<[u8; TOUCH_DATA_LEN]>, NoTouch> {
+        if self.reports.is_empty() {
+            return Err(NoTouch)
+        }
+        if self.progress < self.transfer_steps {
+            self.progress += 1;
+            return Ok(None)
+        }
+        self.progress = 0;
+        Ok(self.reports.pop_front())
+    }
+}
+
+/// Display controller command to write black and white image into RAM
+const EPD_WRITE_BW_RAM: u8 = 0x24;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EpdTransfer {
+    Reset,
+    Command(Vec<u8>),
+    Data(Vec<u8>),
+}
+
+/// Display controller recording everything sent to it
+///
+/// Controller stays busy for `busy_polls` checks after each command.
+pub struct MockEpd {
+    pub log: Vec<EpdTransfer>,
+    pub busy_polls: usize,
+    busy_left: usize,
+}
+
+impl MockEpd {
+    pub fn new(busy_polls: usize) -> Self {
+        Self {
+            log: Vec::new(),
+            busy_polls,
+            busy_left: 0,
+        }
+    }
+}
+
+impl MockEpd {
+    /// Black and white image last written into display RAM, if any
+    ///
+    /// Image is data sent after RAM write command, until the next command.
+    pub fn image(&self) -> Option<Vec<u8>> {
+        let start = self.log
+            .iter()
+            .rposition(|transfer| transfer == &EpdTransfer::Command(vec![EPD_WRITE_BW_RAM]))?;
+        let image: Vec<u8> = self.log[start + 1..]
+            .iter()
+            .map_while(|transfer| match transfer {
+                EpdTransfer::Data(data) => Some(data.as_slice()),
+                _ => None,
+            })
+            .flatten()
+            .copied()
+            .collect();
+        if image.is_empty() { None } else { Some(image) }
+    }
+}
+
+impl EpdBus for MockEpd {
+    fn is_busy(&mut self) -> bool {
+        if self.busy_left == 0 {
+            false
+        } else {
+            self.busy_left -= 1;
+            true
+        }
+    }
+
+    fn reset(&mut self) {
+        self.busy_left = 0;
+        self.log.push(EpdTransfer::Reset);
+    }
+
+    fn write_command(&mut self, command_set: &[u8]) {
+        self.busy_left = self.busy_polls;
+        self.log.push(EpdTransfer::Command(command_set.to_vec()));
+    }
+
+    fn write_data(&mut self, data_set: &[u8]) {
+        self.log.push(EpdTransfer::Data(data_set.to_vec()));
+    }
+}
+
+/// Security element with deterministic random numbers and keyed stream in place of AES GCM
+///
+/// Not secure in any way; only keeps encoding reversible and tag checked.
+pub struct MockSe {
+    state: u64,
+    key: Option<[u8; 32]>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum MockSeError {
+    NoKey,
+    InvalidSignature,
+}
+
+impl MockSe {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            key: None,
+        }
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        // xorshift64
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 24) as u8
+    }
+
+    fn keystream(&self) -> Result<impl Iterator<Item = u8>, MockSeError> {
+        let key = self.key.ok_or(MockSeError::NoKey)?;
+        Ok(key.into_iter().cycle())
+    }
+
+    fn tag(&self, encoded: &[u8], tag_len: usize) -> Result<Vec<u8>, MockSeError> {
+        let key = self.key.ok_or(MockSeError::NoKey)?;
+        Ok(blake2b(tag_len, &key, encoded).as_bytes().to_vec())
+    }
+}
+
+impl SeMailbox for MockSe {
+    type Error = MockSeError;
+
+    fn fill_random(&mut self, dest: &mut [u8]) -> Result<(), MockSeError> {
+        for byte in dest.iter_mut() {
+            *byte = self.next_byte();
+        }
+        Ok(())
+    }
+
+    fn create_key(&mut self) -> Result<(), MockSeError> {
+        let mut key = [0u8; 32];
+        self.fill_random(&mut key)?;
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn aes_gcm_encrypt(&mut self, secret: &[u8], encoded: &mut [u8], tag: &mut [u8]) -> Result<(), MockSeError> {
+        for ((out, byte), key_byte) in encoded.iter_mut().zip(secret.iter()).zip(self.keystream()?) {
+            *out = byte ^ key_byte;
+        }
+        tag.copy_from_slice(&self.tag(encoded, tag.len())?);
+        Ok(())
+    }
+
+    fn aes_gcm_decrypt(&mut self, encoded: &[u8], tag: &[u8], decoded: &mut [u8]) -> Result<(), MockSeError> {
+        if self.tag(encoded, tag.len())? != tag {
+            return Err(MockSeError::InvalidSignature)
+        }
+        for ((out, byte), key_byte) in decoded.iter_mut().zip(encoded.iter()).zip(self.keystream()?) {
+            *out = byte ^ key_byte;
+        }
+        Ok(())
+    }
+}
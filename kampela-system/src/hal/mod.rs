@@ -0,0 +1,217 @@
+//! Hardware abstraction for devices on Kampela board
+//!
+//! Device logic is written against traits here. EFM32 implementations live next to device
+//! drivers and are built with `efm32` feature; in-memory mocks from [`mock`] work on any host.
+//!
+//! EFM32 implementations expect to be called within critical section, with peripherals already
+//! borrowed, same as `*_cs` device functions.
+
+use alloc::vec::Vec;
+use core::{cmp, fmt::Debug};
+
+use crate::devices::psram::AddressPsram;
+
+pub mod mock;
+
+/// Flash is programmed and erased page by page
+pub const FLASH_PAGE_SIZE: usize = 256;
+
+/// Length of touch report, starting at touch count register
+pub const TOUCH_DATA_LEN: usize = 5;
+
+#[derive(Clone, Copy, Debug)]
+pub enum FlashErr {
+    WriteNotMatch
+}
+
+/// Supply voltage measurement, as used to gate power-hungry operations
+pub trait VoltageSource {
+    /// Progress measurement; call on every event loop iteration
+    fn advance(&mut self);
+
+    /// Last measured voltage
+    fn read(&self) -> i32;
+}
+
+/// External SPI flash
+///
+/// Flash is kept in power down between operations; page operations expect it woken up with
+/// [`FlashBus::wake`], which takes time, so it is done once per series of pages.
+pub trait FlashBus {
+    /// Bring flash out of power down
+    fn wake(&mut self) {}
+
+    /// Put flash back to power down
+    fn sleep(&mut self) {}
+
+    /// Erase page at address; erased flash reads as `0xff`
+    fn erase_page(&mut self, addr: u32);
+
+    /// Program page at address; programming could only clear bits, so page should be erased
+    fn write_page(&mut self, addr: u32, data: &[u8]);
+
+    fn read_page(&mut self, addr: u32, data: &mut [u8; FLASH_PAGE_SIZE]);
+
+    /// Store data from page boundary at or below address, reading it back to verify
+    fn store_data<const N: usize>(&mut self, addr: u32, payload: &[u8; N]) -> Result<(), FlashErr> {
+        let mut data = [0u8; N];
+        let mut read_data_chunk = [0u8; FLASH_PAGE_SIZE];
+        let initial_addr = addr / FLASH_PAGE_SIZE as u32 * FLASH_PAGE_SIZE as u32;
+        self.wake();
+        for (i, chunk) in payload.chunks(FLASH_PAGE_SIZE).enumerate() {
+            let addr = initial_addr + i as u32 * FLASH_PAGE_SIZE as u32;
+            self.erase_page(addr);
+            self.write_page(addr, chunk);
+            self.read_page(addr, &mut read_data_chunk);
+            let chunk_start = i * FLASH_PAGE_SIZE;
+            let chunk_len = cmp::min(N - chunk_start, FLASH_PAGE_SIZE);
+            data[chunk_start..chunk_start + chunk_len].clone_from_slice(&read_data_chunk[0..chunk_len]);
+        }
+        self.sleep();
+
+        if &data != payload {
+            Err(FlashErr::WriteNotMatch)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Read data from page boundary at or below address
+    fn read_data(&mut self, addr: u32, data: &mut [u8]) -> Result<(), FlashErr> {
+        let mut read_data_chunk = [0u8; FLASH_PAGE_SIZE];
+        let initial_addr = addr / FLASH_PAGE_SIZE as u32 * FLASH_PAGE_SIZE as u32;
+        self.wake();
+        for i in 0..data.len().div_ceil(FLASH_PAGE_SIZE) {
+            let addr = initial_addr + i as u32 * FLASH_PAGE_SIZE as u32;
+            self.read_page(addr, &mut read_data_chunk);
+            let chunk_start = i * FLASH_PAGE_SIZE;
+            let chunk_len = cmp::min(data.len() - chunk_start, FLASH_PAGE_SIZE);
+            data[chunk_start..chunk_start + chunk_len].clone_from_slice(&read_data_chunk[0..chunk_len]);
+        }
+        self.sleep();
+        Ok(())
+    }
+
+    fn erase_data(&mut self, addr: u32, pages: u32) {
+        let initial_addr = addr / FLASH_PAGE_SIZE as u32 * FLASH_PAGE_SIZE as u32;
+        self.wake();
+        for i in 0..pages {
+            self.erase_page(initial_addr + i * FLASH_PAGE_SIZE as u32);
+        }
+        self.sleep();
+    }
+}
+
+/// External paged RAM
+///
+/// Within single transaction address wraps at page end; transactions spanning several pages are
+/// assembled in `devices::psram`.
+pub trait PsramBus {
+    /// Reset chip before starting new series of transactions
+    fn reset(&mut self);
+
+    fn read_in_page(&mut self, address: AddressPsram, len: usize) -> Vec<u8>;
+
+    fn write_in_page(&mut self, address: AddressPsram, data: &[u8]);
+}
+
+/// FT6336 touch panel on I2C bus
+pub trait TouchBus {
+    type Error: Debug;
+
+    /// Touch interrupt line is active
+    fn touch_detected(&mut self) -> bool;
+
+    /// Advance reading of touch report; `Ok(None)` while transfer is not finished
+    fn read_touch(&mut self) -> Result<Option<[u8; TOUCH_DATA_LEN]>, Self::Error>;
+}
+
+/// E-paper display controller on SPI bus
+pub trait EpdBus {
+    /// BUSY line is set, controller does not accept commands
+    fn is_busy(&mut self) -> bool;
+
+    /// Hardware reset through RES line
+    fn reset(&mut self);
+
+    fn write_command(&mut self, command_set: &[u8]);
+
+    fn write_data(&mut self, data_set: &[u8]);
+}
+
+/// Security element mailbox
+///
+/// AES GCM operations use key created by [`SeMailbox::create_key`], wrapped by SE and stored
+/// outside of it.
+pub trait SeMailbox {
+    type Error: Debug;
+
+    fn fill_random(&mut self, dest: &mut [u8]) -> Result<(), Self::Error>;
+
+    fn create_key(&mut self) -> Result<(), Self::Error>;
+
+    /// Encrypt secret into `encoded` of the same length, producing tag
+    fn aes_gcm_encrypt(&mut self, secret: &[u8], encoded: &mut [u8], tag: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Decrypt `encoded` into `decoded` of the same length, checking tag
+    fn aes_gcm_decrypt(&mut self, encoded: &[u8], tag: &[u8], decoded: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::mock::MockFlash;
+
+    #[test]
+    fn data_over_several_pages_is_read_back() {
+        let mut flash = MockFlash::new(4);
+        let payload: [u8; 2 * FLASH_PAGE_SIZE + 10] = core::array::from_fn(|i| i as u8);
+        flash.store_data(FLASH_PAGE_SIZE as u32, &payload).unwrap();
+
+        let mut data = [0u8; 2 * FLASH_PAGE_SIZE + 10];
+        flash.read_data(FLASH_PAGE_SIZE as u32, &mut data).unwrap();
+        assert_eq!(data, payload);
+        // page before is untouched, data starts at page boundary
+        assert!(flash.data[..FLASH_PAGE_SIZE].iter().all(|byte| *byte == 0xff));
+        flash.read_data(FLASH_PAGE_SIZE as u32 + 5, &mut data).unwrap();
+        assert_eq!(data, payload);
+    }
+
+    #[test]
+    fn data_is_overwritten() {
+        let mut flash = MockFlash::new(1);
+        flash.store_data(0, &[0x0f; 4]).unwrap();
+        flash.store_data(0, &[0xf0; 4]).unwrap();
+        let mut data = [0u8; 4];
+        flash.read_data(0, &mut data).unwrap();
+        assert_eq!(data, [0xf0; 4]);
+    }
+
+    #[test]
+    fn data_is_erased() {
+        let mut flash = MockFlash::new(3);
+        flash.store_data(0, &[0; 3 * FLASH_PAGE_SIZE]).unwrap();
+        flash.erase_data(FLASH_PAGE_SIZE as u32, 2);
+        assert!(flash.data[..FLASH_PAGE_SIZE].iter().all(|byte| *byte == 0));
+        assert!(flash.data[FLASH_PAGE_SIZE..].iter().all(|byte| *byte == 0xff));
+    }
+
+    #[test]
+    fn flash_is_woken_once_per_operation() {
+        let mut flash = MockFlash::new(4);
+        flash.store_data(0, &[0; 4 * FLASH_PAGE_SIZE]).unwrap();
+        assert_eq!(flash.wakeups, 1);
+        flash.read_data(0, &mut [0; 4 * FLASH_PAGE_SIZE]).unwrap();
+        assert_eq!(flash.wakeups, 2);
+        flash.erase_data(0, 4);
+        assert_eq!(flash.wakeups, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "power down")]
+    fn page_operation_needs_awake_flash() {
+        let mut flash = MockFlash::new(1);
+        flash.read_page(0, &mut [0; FLASH_PAGE_SIZE]);
+    }
+}
@@ -6,37 +6,71 @@
 //! - [devboard user guide](https://www.silabs.com/documents/public/user-guides/ug515-efm32pg23-brd2504a-user-guide.pdf)
 //! - [official API docs](https://docs.silabs.com/gecko-platform/latest/emlib/api/efm32xg23/modules)
 //! - [published official open source SDK in C](https://github.com/SiliconLabs/gecko_sdk/tree/gsdk_4.2/platform)
+//!
+//! Without default `efm32` feature only platform-independent parts are built, for use on host;
+//! device logic then runs on mocks from [`hal::mock`]. Host tests are run the same way:
+//! `cargo test --no-default-features`.
 
 #![no_std]
-#![deny(unused_crate_dependencies)]
+#![cfg_attr(feature = "efm32", deny(unused_crate_dependencies))]
 
 extern crate alloc;
 
+pub mod devices;
+pub mod flash_address_book;
+pub mod flash_error;
+pub mod flash_map;
+pub mod flash_mnemonic;
+pub mod flash_networks;
+pub mod flash_settings;
+pub mod flash_verifier;
+pub mod hal;
+pub mod nfc;
+pub mod parser;
+pub mod thresholds;
+pub mod triple_buffer;
+
+#[cfg(feature = "efm32")]
 pub mod init;
+#[cfg(feature = "efm32")]
 pub mod peripherals;
-pub mod devices;
+#[cfg(feature = "efm32")]
 pub mod draw;
-pub mod flash_mnemonic;
+#[cfg(feature = "efm32")]
+pub mod flash_font;
+#[cfg(feature = "efm32")]
 pub mod debug_display;
+#[cfg(feature = "efm32")]
+pub mod panic_screen;
+#[cfg(feature = "efm32")]
 pub mod parallel;
 
+#[cfg(feature = "efm32")]
 use efm32pg23_fix::{CorePeripherals, Peripherals};
 
+#[cfg(feature = "efm32")]
 pub use peripherals::ldma::{BUF_THIRD, CH_TIM0, LINK_1, LINK_2, LINK_DESCRIPTORS, TIMER0_CC0_ICF, NfcXfer, NfcXferBlock};
 
+#[cfg(feature = "efm32")]
 use core::cell::RefCell;
+#[cfg(feature = "efm32")]
 use core::ops::DerefMut;
+#[cfg(feature = "efm32")]
 use cortex_m::interrupt::free;
+#[cfg(feature = "efm32")]
 use cortex_m::interrupt::Mutex;
 
+#[cfg(feature = "efm32")]
 use lazy_static::lazy_static;
 
+#[cfg(feature = "efm32")]
 lazy_static!{
     pub static ref CORE_PERIPHERALS: Mutex<RefCell<CorePeripherals>> = Mutex::new(RefCell::new(CorePeripherals::take().unwrap()));
     pub static ref PERIPHERALS: Mutex<RefCell<Option<Peripherals>>> = Mutex::new(RefCell::new(None));
 }
 
 /// Mutexed global access to peripherals
+#[cfg(feature = "efm32")]
 pub fn in_free<F>(mut action: F)
     where F: FnMut(&mut Peripherals)
 {
@@ -48,6 +82,7 @@ pub fn in_free<F>(mut action: F)
 }
 
 /// Mutexed global access to peripherals
+#[cfg(feature = "efm32")]
 pub fn if_in_free<F>(mut action: F) -> Result<bool, FreeError>
     where F: FnMut(&mut Peripherals) -> bool
 {
@@ -0,0 +1,351 @@
+//! Decoding of NFC captures into received payload
+//!
+//! LDMA fills NFC buffer thirds with TIMER0 capture values of reader field edges. Each third is
+//! decoded into Miller-encoded frames, frames carry LT-coded packets, and packets are collected
+//! in PSRAM until payload could be restored.
+//!
+//! Nothing here touches hardware, so recorded captures could be replayed on host.
+
+use alloc::vec::Vec;
+use lt_codes::{decoder_metal::ExternalData, mock_worst_case::DecoderMetal, packet::{Packet, PACKET_SIZE}};
+use nfca_parser::frame::Frame;
+
+use crate::devices::psram::{AddressPsram, ExternalPsram};
+use crate::hal::PsramBus;
+
+/// Number of capture values in each of three parts of NFC buffer
+pub const BUF_THIRD: usize = 2048;
+
+/// Timer ticks per Miller bit period
+pub const FREQ: u16 = 22;
+
+/// Bytes in recorded capture per buffer third
+pub const CAPTURE_THIRD_LEN: usize = 2 * BUF_THIRD;
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum CaptureError {
+    /// Capture length is not a positive multiple of [`CAPTURE_THIRD_LEN`]
+    Length(usize),
+}
+
+/// Buffer thirds from recorded capture
+///
+/// Capture is a dump of NFC buffer thirds in the order LDMA filled them, each third `BUF_THIRD`
+/// little-endian `u16` TIMER0 capture values.
+pub fn capture_thirds(capture: &[u8]) -> Result<Vec<[u16; BUF_THIRD]>, CaptureError> {
+    if capture.is_empty() || capture.len() % CAPTURE_THIRD_LEN != 0 {
+        return Err(CaptureError::Length(capture.len()))
+    }
+    Ok(capture
+        .chunks(CAPTURE_THIRD_LEN)
+        .map(|third| {
+            let mut values = [0u16; BUF_THIRD];
+            for (value, bytes) in values.iter_mut().zip(third.chunks(2)) {
+                *value = u16::from_le_bytes([bytes[0], bytes[1]]);
+            }
+            values
+        })
+        .collect())
+}
+
+/// Recorded capture of buffer thirds, in format read by [`capture_thirds`]
+pub fn capture_bytes(thirds: &[[u16; BUF_THIRD]]) -> Vec<u8> {
+    thirds.iter().flat_map(|third| third.iter().flat_map(|value| value.to_le_bytes())).collect()
+}
+
+fn frame_selected(frame: &Frame) -> bool {
+    if let Frame::Standard(standard_frame) = frame {
+        if standard_frame.len() >= PACKET_SIZE {true}
+        else {false}
+    }
+    else {false}
+}
+
+/// Serialized LT packets found in a single buffer third
+pub fn serialized_packets(capture: &[u16]) -> Vec<[u8; PACKET_SIZE]> {
+    let frames = Frame::process_buffer_miller_skip_tails::<_, FREQ>(capture, |frame| frame_selected(&frame));
+
+    let mut packets = Vec::new();
+    for frame in frames.into_iter() {
+        if let Frame::Standard(standard_frame) = frame {
+            packets.push(standard_frame[standard_frame.len() - PACKET_SIZE..].try_into().expect("static length, always fits"));
+        }
+        else {unreachable!()}
+    }
+    packets
+}
+
+#[derive(Debug)]
+pub enum CollectorError {
+    /// Packet does not fit into transfer started by earlier packets
+    Packet,
+}
+
+pub enum NfcCollector {
+    Empty,
+    InProgress(DecoderMetal<AddressPsram>),
+    Done(ExternalData<AddressPsram>)
+}
+
+impl NfcCollector {
+    pub fn new() -> Self {
+        Self::Empty
+    }
+
+    pub fn add_packet<P: PsramBus>(&mut self, external_psram: &mut ExternalPsram<P>, serialized_packet: [u8; PACKET_SIZE]) -> Result<(), CollectorError> {
+        let nfc_packet = Packet::deserialize(serialized_packet);
+        match self {
+            NfcCollector::Empty => {
+                let decoder_metal = DecoderMetal::init(external_psram, nfc_packet).map_err(|_| CollectorError::Packet)?;
+                match decoder_metal.try_read(external_psram) {
+                    None => *self = NfcCollector::InProgress(decoder_metal),
+                    Some(a) => *self = NfcCollector::Done(a),
+                }
+            },
+            NfcCollector::InProgress(decoder_metal) => {
+                decoder_metal.add_packet(external_psram, nfc_packet).map_err(|_| CollectorError::Packet)?;
+                if let Some(a) = decoder_metal.try_read(external_psram) {
+                    *self = NfcCollector::Done(a);
+                }
+            },
+            NfcCollector::Done(_) => {},
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use lt_codes::encoder::Encoder;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    use crate::devices::psram::psram_read_at_address;
+    use crate::hal::mock::MockPsram;
+    use crate::triple_buffer::TripleBuffer;
+
+    /// ISO-DEP block header preceding packet in frame
+    const FRAME_HEADER: u8 = 0x02;
+
+    /// Bit periods without field pauses between frames
+    const FRAME_GAP: u32 = 40;
+
+    /// CRC_A of ISO/IEC 14443-3, low byte first
+    fn crc_a(data: &[u8]) -> [u8; 2] {
+        let mut crc: u16 = 0x6363;
+        for byte in data {
+            let mut b = byte ^ crc as u8;
+            b ^= b << 4;
+            crc = (crc >> 8) ^ ((b as u16) << 8) ^ ((b as u16) << 3) ^ ((b as u16) >> 4);
+        }
+        crc.to_le_bytes()
+    }
+
+    /// Times of reader field pauses for standard frame with CRC_A, in modified Miller code,
+    /// starting at `start`; returns pauses and time of frame end
+    fn miller_pauses(start: u32, frame: &[u8]) -> (Vec<u32>, u32) {
+        let period = FREQ as u32;
+        let mut data = frame.to_vec();
+        data.extend_from_slice(&crc_a(frame));
+
+        // start of communication is pause at the start of bit period
+        let mut pauses = vec![start];
+        let mut time = start + period;
+        let mut previous_one = false;
+        for byte in data {
+            let bits = (0..8).map(|i| byte >> i & 1 == 1).chain([byte.count_ones() % 2 == 0]);
+            for bit in bits {
+                if bit {
+                    pauses.push(time + period / 2);
+                } else if !previous_one {
+                    pauses.push(time);
+                }
+                previous_one = bit;
+                time += period;
+            }
+        }
+        // end of communication is logical 0 followed by bit period without pause
+        if !previous_one {
+            pauses.push(time);
+        }
+        (pauses, time + 2 * period)
+    }
+
+    /// Capture of packet frames sent one after another, cut into `thirds` buffer thirds; frames
+    /// are taken from `packets` for as long as capture is not filled
+    ///
+    /// TIMER0 is restarted on each pause, so capture values are intervals between pauses.
+    fn capture(packets: &mut impl Iterator<Item = [u8; PACKET_SIZE]>, thirds: usize) -> Vec<[u16; BUF_THIRD]> {
+        let mut intervals: Vec<u16> = Vec::new();
+        let mut last_pause = 0;
+        let mut time = FRAME_GAP * FREQ as u32;
+        while intervals.len() < thirds * BUF_THIRD {
+            let packet = packets.next().expect("enough packets to fill capture");
+            let frame = [&[FRAME_HEADER][..], &packet].concat();
+            let (pauses, end) = miller_pauses(time, &frame);
+            for pause in pauses {
+                intervals.push((pause - last_pause) as u16);
+                last_pause = pause;
+            }
+            time = end + FRAME_GAP * FREQ as u32;
+        }
+        intervals
+            .chunks_exact(BUF_THIRD)
+            .take(thirds)
+            .map(|third| third.try_into().expect("exact chunks"))
+            .collect()
+    }
+
+    fn payload(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 256) as u8).collect()
+    }
+
+    /// Endless LT packet stream for payload, as companion sends it
+    fn packet_stream(payload: &[u8]) -> impl Iterator<Item = [u8; PACKET_SIZE]> {
+        let mut encoder = Encoder::init(payload).expect("payload is not empty");
+        core::iter::repeat_with(move || encoder.make_packet().serialize())
+    }
+
+    /// Feed packets to collector until payload is restored
+    fn restore(packets: impl IntoIterator<Item = [u8; PACKET_SIZE]>) -> Option<Vec<u8>> {
+        let mut psram = MockPsram::growing();
+        let mut collector = NfcCollector::new();
+        for packet in packets {
+            collector.add_packet(&mut ExternalPsram{psram: &mut psram}, packet).expect("packet from the same stream");
+            if let NfcCollector::Done(ref data) = collector {
+                return Some(psram_read_at_address(&mut psram, data.start_address, data.len).expect("restored data is within PSRAM"))
+            }
+        }
+        None
+    }
+
+    /// Packets from every third, in capture order
+    fn decode(thirds: &[[u16; BUF_THIRD]]) -> Vec<[u8; PACKET_SIZE]> {
+        thirds.iter().flat_map(|third| serialized_packets(third)).collect()
+    }
+
+    /// Thirds enough to carry every packet of payload several times
+    fn thirds_for(payload: &[u8]) -> usize {
+        let frame_bits = (1 + PACKET_SIZE + 2) * 9 + FRAME_GAP as usize;
+        let frames_per_third = BUF_THIRD / frame_bits;
+        (4 * payload.len().div_ceil(PACKET_SIZE)).div_ceil(frames_per_third.max(1)) + 1
+    }
+
+    #[test]
+    fn capture_format_round_trip() {
+        let thirds = capture(&mut packet_stream(&payload(1000)), 2);
+        let bytes = capture_bytes(&thirds);
+        assert_eq!(bytes.len(), 2 * CAPTURE_THIRD_LEN);
+        assert_eq!(capture_thirds(&bytes), Ok(thirds));
+    }
+
+    #[test]
+    fn capture_of_partial_third_is_rejected() {
+        assert_eq!(capture_thirds(&[]), Err(CaptureError::Length(0)));
+        assert_eq!(capture_thirds(&[0; CAPTURE_THIRD_LEN + 2]), Err(CaptureError::Length(CAPTURE_THIRD_LEN + 2)));
+    }
+
+    #[test]
+    fn whole_frames_in_third_are_decoded_in_order() {
+        let sent: Vec<[u8; PACKET_SIZE]> = packet_stream(&payload(1000)).take(64).collect();
+        let third = capture(&mut sent.clone().into_iter(), 1).remove(0);
+        let received = serialized_packets(&third);
+        // frame cut at the end of third is a tail, and is skipped
+        assert!(!received.is_empty());
+        assert_eq!(received[..], sent[..received.len()]);
+    }
+
+    #[test]
+    fn clean_capture_restores_payload() {
+        let payload = payload(3000);
+        let thirds = capture(&mut packet_stream(&payload), thirds_for(&payload));
+        assert_eq!(restore(decode(&thirds)), Some(payload));
+    }
+
+    #[test]
+    fn noisy_capture_restores_payload() {
+        let payload = payload(3000);
+        let mut thirds = capture(&mut packet_stream(&payload), thirds_for(&payload));
+        let mut rng = StdRng::seed_from_u64(0);
+        for value in thirds.iter_mut().flat_map(|third| third.iter_mut()) {
+            if rng.gen_range(0..10) == 0 {
+                *value = if rng.gen() { *value + 1 } else { *value - 1 };
+            }
+        }
+        assert_eq!(restore(decode(&thirds)), Some(payload));
+    }
+
+    #[test]
+    fn capture_with_dropped_thirds_restores_payload() {
+        let payload = payload(3000);
+        let thirds = capture(&mut packet_stream(&payload), 2 * thirds_for(&payload));
+        let kept: Vec<[u16; BUF_THIRD]> = thirds.into_iter().enumerate().filter(|(i, _)| i % 3 != 2).map(|(_, third)| third).collect();
+        assert_eq!(restore(decode(&kept)), Some(payload));
+    }
+
+    #[test]
+    fn out_of_order_packets_restore_payload() {
+        let payload = payload(3000);
+        let mut packets = decode(&capture(&mut packet_stream(&payload), thirds_for(&payload)));
+        packets.shuffle(&mut StdRng::seed_from_u64(0));
+        assert_eq!(restore(packets), Some(payload));
+    }
+
+    /// Capture thirds go through NFC buffer as on device: LDMA interrupt completes region with
+    /// `write_done`, and main loop reads one region after every `writes_per_read` thirds captured
+    ///
+    /// Returns capture indices of thirds read, in reading order, and packets found in them.
+    fn replay_through_buffer(thirds: &[[u16; BUF_THIRD]], writes_per_read: usize) -> (Vec<usize>, Vec<[u8; PACKET_SIZE]>, TripleBuffer<u16, BUF_THIRD>) {
+        let mut buffer = TripleBuffer::new();
+        let mut read = Vec::new();
+        let mut packets = Vec::new();
+
+        let mut read_one = |buffer: &mut TripleBuffer<u16, BUF_THIRD>| {
+            if let Some(third) = buffer.read_third() {
+                read.push(thirds.iter().position(|captured| captured == third).expect("region holds captured third"));
+                packets.extend(serialized_packets(third));
+                buffer.read_done().expect("region was being read");
+            }
+        };
+
+        for (i, third) in thirds.iter().enumerate() {
+            // halted LDMA captures nothing, and third is lost
+            if let Some(region) = buffer.write_third() {
+                *region = *third;
+                buffer.write_done().expect("writer is not halted");
+            }
+            if i % writes_per_read == writes_per_read - 1 {
+                read_one(&mut buffer);
+            }
+        }
+        while buffer.read_region().is_some() {
+            read_one(&mut buffer);
+        }
+        (read, packets, buffer)
+    }
+
+    #[test]
+    fn every_third_is_read_when_reader_keeps_up() {
+        let payload = payload(3000);
+        let thirds = capture(&mut packet_stream(&payload), thirds_for(&payload));
+        let (read, packets, status) = replay_through_buffer(&thirds, 1);
+        assert_eq!(read, (0..thirds.len()).collect::<Vec<usize>>());
+        assert_eq!(status.overruns(), 0);
+        assert_eq!(packets, decode(&thirds));
+        assert_eq!(restore(packets), Some(payload));
+    }
+
+    #[test]
+    fn slow_reader_loses_thirds_but_restores_payload() {
+        let payload = payload(3000);
+        let thirds = capture(&mut packet_stream(&payload), 3 * thirds_for(&payload));
+        let (read, packets, status) = replay_through_buffer(&thirds, 3);
+        // thirds are read in capture order, none twice, and some are lost while writer is halted
+        assert!(read.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(read.len() < thirds.len());
+        assert_eq!(status.overruns(), 0);
+        assert!(!status.is_write_halted());
+        assert_eq!(restore(packets), Some(payload));
+    }
+}
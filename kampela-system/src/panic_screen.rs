@@ -0,0 +1,199 @@
+//! Error screen for panic handler
+//!
+//! Panic could happen anywhere, in allocator or with peripherals borrowed, and on harvested
+//! power. So error screen uses neither heap nor peripherals mutex, its frame is reserved and
+//! pre-rendered at boot, and EPD is started only when voltage allows full refresh. Report is
+//! stored in flash before anything else, so that it is shown on next boot even if power runs out
+//! before screen is drawn.
+
+use core::cell::RefCell;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use cortex_m::{asm::delay, interrupt::{free, Mutex}};
+use efm32pg23_fix::Peripherals;
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point, Size},
+    mono_font::{ascii::{FONT_10X20, FONT_6X10}, MonoTextStyle},
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+    Drawable, Pixel,
+};
+use embedded_text::TextBox;
+use qrcodegen_no_heap::{QrCode, QrCodeEcc, Version};
+
+use kampela_display_common::display_def::*;
+
+use crate::devices::display_transmission::{display_is_busy_cs, epaper_deep_sleep, epaper_hw_init_cs};
+use crate::devices::epd_sequence::{EpdRunner, BUFSIZE, FULL_AREA, FULL_DRAW};
+use crate::devices::power::adc_to_voltage;
+use crate::draw::pixel_index;
+use crate::flash_error::{store_error, BoundedText, ErrorReport};
+use crate::peripherals::adc::{read_adc_cs, read_int_flag, request_adc_measure_cs, reset_int_flags_cs};
+use crate::thresholds::FULL_REFRESH_POWER;
+
+/// Error screen frame, reserved so that panic does not need heap
+static FRAME: Mutex<RefCell<[u8; BUFSIZE]>> = Mutex::new(RefCell::new([0xff; BUFSIZE]));
+
+/// Largest QR version; holds full report of any length with low error correction
+const QR_MAX_VERSION: u8 = 10;
+const QR_BUFFER_LEN: usize = Version::new(QR_MAX_VERSION).buffer_len();
+
+const HEADER: &str = "Error";
+const MARGIN: i32 = 4;
+const HEADER_HEIGHT: i32 = 20;
+const HINT_HEIGHT: i32 = 20;
+/// QR is drawn at the right, in square below header
+const QR_ZONE: i32 = SCREEN_SIZE_Y as i32 - HEADER_HEIGHT - 3*MARGIN;
+const COLUMN_WIDTH: i32 = SCREEN_SIZE_X as i32 - QR_ZONE - 3*MARGIN;
+const COLUMN_TOP: i32 = HEADER_HEIGHT + 2*MARGIN;
+const LOCATION_HEIGHT: i32 = 30;
+
+/// ADC polls before voltage is considered unknown, e.g. when panic happened before ADC init
+const ADC_TIMEOUT: usize = 100000;
+/// Pause between voltage measurements while waiting for power
+const POWER_POLL_DELAY: u32 = 100000;
+
+/// Frame drawn in panel coordinates, orientation is not applied
+struct PanicFrame<'a> {
+    data: &'a mut [u8; BUFSIZE],
+}
+
+impl Dimensions for PanicFrame<'_> {
+    fn bounding_box(&self) -> Rectangle {
+        SCREEN_AREA
+    }
+}
+
+impl DrawTarget for PanicFrame<'_> {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if let Some(n) = pixel_index(point) {
+                let mask = 0x80 >> (n % 8);
+                match color {
+                    BinaryColor::Off => self.data[n / 8] |= mask, //white
+                    BinaryColor::On => self.data[n / 8] &= !mask, //black
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Draw parts of error screen that do not depend on error; call once at boot
+pub fn prepare_panic_screen() {
+    free(|cs| {
+        let mut data = FRAME.borrow(cs).borrow_mut();
+        let mut frame = PanicFrame{data: &mut data};
+        let header_style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
+        let _ = Text::with_baseline(HEADER, Point::new(MARGIN, MARGIN), header_style, Baseline::Top).draw(&mut frame);
+        let hint_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+        let hint_bounds = Rectangle::new(
+            Point::new(MARGIN, SCREEN_SIZE_Y as i32 - MARGIN - HINT_HEIGHT),
+            Size::new(COLUMN_WIDTH as u32, HINT_HEIGHT as u32),
+        );
+        let _ = TextBox::new("Restart device. Full report is in QR", hint_bounds, hint_style).draw(&mut frame);
+    });
+}
+
+/// Report for panic, without heap
+pub fn panic_report(panic: &PanicInfo<'_>) -> ErrorReport {
+    let mut report = match panic.location() {
+        Some(location) => ErrorReport::new(location.file(), location.line()),
+        None => ErrorReport::new("", 0),
+    };
+    let _ = write!(report.message, "{}", panic.message());
+    report
+}
+
+/// Store report and show it on error screen; call from panic handler only
+pub fn show_panic(peripherals: &mut Peripherals, report: &ErrorReport) {
+    let _ = store_error(peripherals, report);
+    free(|cs| {
+        // borrowed only if panic happened in preparation, then there is nothing to show
+        if let Ok(mut data) = FRAME.borrow(cs).try_borrow_mut() {
+            draw_report(&mut PanicFrame{data: &mut data}, report);
+            wait_for_power(peripherals, FULL_REFRESH_POWER);
+            show(peripherals, &data[..]);
+        }
+    });
+}
+
+fn draw_report(frame: &mut PanicFrame<'_>, report: &ErrorReport) {
+    let id_style = MonoTextStyle::new(&FONT_10X20, BinaryColor::On);
+    let mut id = BoundedText::<8>::new();
+    let _ = write!(id, "E{:04X}", report.id);
+    let id_x = 2*MARGIN + (HEADER.len() as u32 * FONT_10X20.character_size.width) as i32;
+    let _ = Text::with_baseline(id.as_str(), Point::new(id_x, MARGIN), id_style, Baseline::Top).draw(frame);
+
+    let text_style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+    let mut location = BoundedText::<64>::new();
+    let _ = write!(location, "{}:{}", report.module.as_str(), report.line);
+    let location_bounds = Rectangle::new(Point::new(MARGIN, COLUMN_TOP), Size::new(COLUMN_WIDTH as u32, LOCATION_HEIGHT as u32));
+    let _ = TextBox::new(location.as_str(), location_bounds, text_style).draw(frame);
+
+    let message_top = COLUMN_TOP + LOCATION_HEIGHT + MARGIN;
+    let message_height = SCREEN_SIZE_Y as i32 - HINT_HEIGHT - 2*MARGIN - message_top;
+    let message_bounds = Rectangle::new(Point::new(MARGIN, message_top), Size::new(COLUMN_WIDTH as u32, message_height as u32));
+    let _ = TextBox::new(report.message.as_str(), message_bounds, text_style).draw(frame);
+
+    draw_report_qr(frame, report);
+}
+
+fn draw_report_qr(frame: &mut PanicFrame<'_>, report: &ErrorReport) {
+    let mut full = BoundedText::<QR_BUFFER_LEN>::new();
+    let _ = report.write_full(&mut full);
+    let len = full.as_str().len();
+    let mut data_and_temp = [0u8; QR_BUFFER_LEN];
+    let mut out_buffer = [0u8; QR_BUFFER_LEN];
+    data_and_temp[..len].copy_from_slice(full.as_str().as_bytes());
+    let qr_code = match QrCode::encode_binary(&mut data_and_temp, len, &mut out_buffer, QrCodeEcc::Low, Version::MIN, Version::new(QR_MAX_VERSION), None, true) {
+        Ok(a) => a,
+        Err(_) => return,
+    };
+
+    let scaling = (QR_ZONE / qr_code.size()).max(1);
+    let size = qr_code.size() * scaling;
+    let zone_top_left = Point::new(SCREEN_SIZE_X as i32 - MARGIN - QR_ZONE, COLUMN_TOP);
+    let top_left = zone_top_left + Point::new((QR_ZONE - size) / 2, (QR_ZONE - size) / 2);
+    let black = PrimitiveStyle::with_fill(BinaryColor::On);
+    for y in 0..qr_code.size() {
+        for x in 0..qr_code.size() {
+            if qr_code.get_module(x, y) {
+                let _ = Rectangle::new(top_left + Point::new(x, y) * scaling, Size::new(scaling as u32, scaling as u32))
+                    .into_styled(black)
+                    .draw(frame);
+            }
+        }
+    }
+}
+
+/// Wait until voltage is above threshold; gives up waiting if ADC does not respond
+fn wait_for_power(peripherals: &mut Peripherals, threshold: i32) {
+    loop {
+        reset_int_flags_cs(peripherals);
+        request_adc_measure_cs(peripherals);
+        let mut polls = 0;
+        while !read_int_flag(peripherals) {
+            polls += 1;
+            if polls > ADC_TIMEOUT { return }
+        }
+        if adc_to_voltage(read_adc_cs(peripherals)) > threshold { return }
+        delay(POWER_POLL_DELAY);
+    }
+}
+
+/// Full refresh with frame, then EPD goes to sleep
+fn show(peripherals: &mut Peripherals, data: &[u8]) {
+    epaper_hw_init_cs(peripherals);
+    let mut runner = EpdRunner::new(FULL_DRAW, FULL_AREA);
+    while !runner.advance(peripherals, data) {}
+    while display_is_busy_cs(peripherals) {}
+    epaper_deep_sleep(peripherals);
+}
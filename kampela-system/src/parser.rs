@@ -0,0 +1,435 @@
+//! Transaction payload parsing and decoding, independent of memory the payload is kept in
+//!
+//! Device keeps payload in PSRAM, simulator in plain host memory; both go through the same code
+//! here.
+
+use alloc::{borrow::ToOwned, format, string::{String, ToString}, vec::Vec};
+use core::{any::TypeId, fmt::{Debug, Display, Formatter, Result as FmtResult}, ops::Range};
+
+use external_memory_tools::{AddressableBuffer, BufferError, ExternalMemory};
+use parity_scale_codec::{Decode, DecodeAll, Encode};
+use primitive_types::H256;
+use scale_info::{form::PortableForm, interner::UntrackedSymbol, Type};
+use substrate_parser::{
+    AsMetadata, ResolveType, ShortSpecs,
+    cards::{Call, ExtendedData, ParsedData},
+    compacts::find_compact,
+    decode_as_call_unmarked, decode_extensions_unmarked,
+    error::{RegistryError, RegistryInternalError},
+    special_indicators::SpecialtyPrimitive,
+    traits::{SignedExtensionMetadata, SpecNameVersion},
+};
+
+pub const GENESIS_HASH_LEN: usize = 32;
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// Location of transaction parts within NFC payload
+pub struct TransactionPayload {
+    pub genesis_hash: Range<usize>,
+    pub metadata: Range<usize>,
+    pub call: Range<usize>,
+    pub extension: Range<usize>,
+    pub public_key: Range<usize>,
+}
+
+#[derive(Debug)]
+pub enum PayloadError {
+    Format,
+    TooShort,
+}
+
+/// Transaction payload is a single byte of payload type, then genesis hash, compact-prefixed
+/// metadata, doubly compact-prefixed transaction, and public key of signer.
+///
+/// Transaction itself is compact-prefixed call followed by extensions.
+pub fn transaction_payload<B, E>(payload: &B, ext_memory: &mut E) -> Result<TransactionPayload, PayloadError>
+where
+    B: AddressableBuffer<E>,
+    E: ExternalMemory,
+{
+    let genesis_hash = 1..1 + GENESIS_HASH_LEN;
+
+    let compact_meta = find_compact::<u32, B, E>(payload, ext_memory, genesis_hash.end).map_err(|_| PayloadError::Format)?;
+    let metadata = compact_meta.start_next_unit..compact_meta.start_next_unit + compact_meta.compact as usize;
+
+    let compact_transaction_1 = find_compact::<u32, B, E>(payload, ext_memory, metadata.end).map_err(|_| PayloadError::Format)?;
+    let compact_transaction_2 = find_compact::<u32, B, E>(payload, ext_memory, compact_transaction_1.start_next_unit).map_err(|_| PayloadError::Format)?;
+    let transaction = compact_transaction_2.start_next_unit..compact_transaction_2.start_next_unit + compact_transaction_2.compact as usize;
+
+    let compact_call = find_compact::<u32, B, E>(payload, ext_memory, transaction.start).map_err(|_| PayloadError::Format)?;
+    let call = compact_call.start_next_unit..compact_call.start_next_unit + compact_call.compact as usize;
+    if call.end > transaction.end {
+        return Err(PayloadError::Format)
+    }
+    let extension = call.end..transaction.end;
+
+    let public_key = transaction.end..transaction.end + PUBLIC_KEY_LEN;
+    if public_key.end > payload.total_len() {
+        return Err(PayloadError::TooShort)
+    }
+
+    Ok(TransactionPayload {
+        genesis_hash,
+        metadata,
+        call,
+        extension,
+        public_key,
+    })
+}
+
+/// Why transaction could not be shown to user; platform words it in UI language
+#[derive(Debug)]
+pub enum DecodeError {
+    Metadata(ReceivedMetadataError),
+    Call(String),
+    Extensions(String),
+    /// Metadata version differs from the one transaction was made for
+    MetadataOutdated{in_metadata: String, in_transaction: String},
+    NoSpecVersion,
+}
+
+impl DecodeError {
+    /// Payload could be offered for blind signing.
+    ///
+    /// Only call decoding failure qualifies: by then extensions are decoded and their spec
+    /// version matches metadata. Anything else could be payload for other runtime version.
+    pub fn is_blind_signable(&self) -> bool {
+        matches!(self, DecodeError::Call(_))
+    }
+}
+
+pub fn decode_call<B, E>(
+    call_data: &[u8],
+    checked_metadata_metal: &CheckedMetadataMetal<B>,
+    ext_memory: &mut E,
+) -> Result<Call, DecodeError>
+where
+    B: AddressableBuffer<E> + Clone + Debug,
+    E: ExternalMemory,
+{
+    let mut decoding_postition = 0;
+    decode_as_call_unmarked(
+        &call_data,
+        &mut decoding_postition,
+        ext_memory,
+        checked_metadata_metal,
+    ).map_err(|e| DecodeError::Call(format!("{e}")))
+}
+
+/// Decode extensions and make sure they were made for the same metadata version
+pub fn decode_extensions<B, E>(
+    extension_data: &[u8],
+    checked_metadata_metal: &CheckedMetadataMetal<B>,
+    genesis_hash: H256,
+    ext_memory: &mut E,
+) -> Result<Vec<ExtendedData>, DecodeError>
+where
+    B: AddressableBuffer<E> + Clone + Debug,
+    E: ExternalMemory,
+{
+    // Decoding with metadata of other version could show user a completely different transaction,
+    // or just fail; version is checked before decoding, so that mismatch is reported as such
+    let in_metadata = checked_metadata_metal.spec_name_version.printed_spec_version.to_owned();
+    if let Some(in_transaction) = raw_spec_version(extension_data, genesis_hash) {
+        let in_transaction = in_transaction.to_string();
+        if in_transaction != in_metadata {
+            return Err(DecodeError::MetadataOutdated{in_metadata, in_transaction})
+        }
+    }
+
+    let mut decoding_postition = 0;
+    let decoded_extension = decode_extensions_unmarked(
+        &extension_data,
+        &mut decoding_postition,
+        ext_memory,
+        checked_metadata_metal,
+        Some(genesis_hash),
+    ).map_err(|e| DecodeError::Extensions(format!("{e}")))?;
+
+    match decoded_extension.iter().find_map(|extended_data| spec_version(&extended_data.data)) {
+        Some(in_transaction) => {
+            if in_transaction != in_metadata {
+                return Err(DecodeError::MetadataOutdated{in_metadata, in_transaction})
+            }
+        },
+        None => return Err(DecodeError::NoSpecVersion),
+    }
+    Ok(decoded_extension)
+}
+
+/// Spec version read from undecoded extensions.
+///
+/// Substrate runtimes sign spec version and transaction version, both `u32`, followed by genesis
+/// hash and block hash (`CheckSpecVersion`, `CheckTxVersion`, `CheckGenesis`, `CheckMortality`).
+/// Genesis hash is known, so spec version is found without metadata.
+fn raw_spec_version(extension_data: &[u8], genesis_hash: H256) -> Option<u32> {
+    const VERSION_LEN: usize = 4;
+    (2 * VERSION_LEN..extension_data.len())
+        .filter(|position| position + 2 * GENESIS_HASH_LEN <= extension_data.len())
+        .find(|position| extension_data[*position..*position + GENESIS_HASH_LEN] == genesis_hash.0)
+        .map(|position| {
+            let spec_version = &extension_data[position - 2 * VERSION_LEN..position - VERSION_LEN];
+            u32::from_le_bytes(spec_version.try_into().expect("static length"))
+        })
+}
+
+/// Find spec version in decoded extension data
+fn spec_version(data: &ParsedData) -> Option<String> {
+    match data {
+        ParsedData::PrimitiveU8 {
+            value,
+            specialty: SpecialtyPrimitive::SpecVersion,
+        } => Some(value.to_string()),
+        ParsedData::PrimitiveU16 {
+            value,
+            specialty: SpecialtyPrimitive::SpecVersion,
+        } => Some(value.to_string()),
+        ParsedData::PrimitiveU32 {
+            value,
+            specialty: SpecialtyPrimitive::SpecVersion,
+        } => Some(value.to_string()),
+        ParsedData::PrimitiveU64 {
+            value,
+            specialty: SpecialtyPrimitive::SpecVersion,
+        } => Some(value.to_string()),
+        ParsedData::PrimitiveU128 {
+            value,
+            specialty: SpecialtyPrimitive::SpecVersion,
+        } => Some(value.to_string()),
+        ParsedData::Composite(fields) => fields.iter().find_map(|field| spec_version(&field.data.data)),
+        _ => None,
+    }
+}
+
+/// Types registry that stays in external memory; only positions of type entries are kept
+#[derive(Clone, Debug)]
+pub struct MetalRegistry<B> {
+    pub metadata: B,
+    pub registry: Vec<EntryPsram>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EntryPsram {
+    pub id: u32,
+    pub position: usize,
+    pub entry_len: usize,
+}
+
+impl <B, E> ResolveType<E> for MetalRegistry<B>
+where
+    B: AddressableBuffer<E>,
+    E: ExternalMemory,
+{
+    fn resolve_ty(&self, id: u32, ext_memory: &mut E) -> Result<Type<PortableForm>, RegistryError<E>> {
+        for entry_psram in self.registry.iter() {
+            if entry_psram.id == id {
+                let encoded_type_data = self.metadata
+                    .read_slice(ext_memory, entry_psram.position, entry_psram.entry_len)
+                    .map_err(|e| match e {
+                        BufferError::External(e) => RegistryError::External(e),
+                        _ => RegistryError::Internal(RegistryInternalError::TypeNotResolved { id }),
+                    })?;
+                let ty = Type::<PortableForm>::decode_all(&mut encoded_type_data.as_ref())
+                    .map_err(|_| RegistryError::Internal(RegistryInternalError::TypeNotResolved { id }))?;
+                return Ok(ty)
+            }
+        }
+        Err(RegistryError::Internal(RegistryInternalError::TypeNotResolved { id }))
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckedMetadataMetal<B> {
+    pub types: MetalRegistry<B>,
+    pub call_ty: UntrackedSymbol<TypeId>,
+    pub signed_extensions: Vec<SignedExtensionMetadata>,
+    pub spec_name_version: SpecNameVersion,
+    pub base58prefix: u16,
+    pub decimals: u8,
+    pub unit: String,
+}
+
+#[derive(Debug, Decode, Encode)]
+pub struct CheckedMeadataMetalTail {
+    pub call_ty: UntrackedSymbol<TypeId>,
+    pub signed_extensions: Vec<SignedExtensionMetadata>,
+    pub spec_name_version: SpecNameVersion,
+    pub base58prefix: u16,
+    pub decimals: u8,
+    pub unit: String,
+}
+
+impl <B, E> AsMetadata<E> for CheckedMetadataMetal<B>
+where
+    B: AddressableBuffer<E> + Clone + Debug,
+    E: ExternalMemory,
+{
+    type TypeRegistry = MetalRegistry<B>;
+    type MetaStructureError = NoEntries;
+    fn types(&self) -> Self::TypeRegistry {
+        self.types.to_owned()
+    }
+    fn spec_name_version(&self) -> Result<SpecNameVersion, Self::MetaStructureError> {
+        Ok(self.spec_name_version.to_owned())
+    }
+    fn call_ty(&self) -> Result<UntrackedSymbol<TypeId>, Self::MetaStructureError> {
+        Ok(self.call_ty.to_owned())
+    }
+    fn signed_extensions(&self) -> Result<Vec<SignedExtensionMetadata>, Self::MetaStructureError> {
+        Ok(self.signed_extensions.to_owned())
+    }
+}
+
+/// Empty error enum, for cases with fault-free memory access.
+#[derive(Debug, Eq, PartialEq)]
+pub enum NoEntries {}
+
+impl Display for NoEntries {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "")
+    }
+}
+
+fn force_decode_at<T, B, E>(data_buffer: &B, ext_memory: &mut E, start_position: usize, err_at: ReceivedMetadataError) -> Result<(T, usize), ReceivedMetadataError>
+where
+    T: Decode,
+    B: AddressableBuffer<E>,
+    E: ExternalMemory,
+{
+    let mut data = Vec::with_capacity(data_buffer.total_len() - start_position);
+    let mut out: Option<(T, usize)> = None;
+    for i in 0..data_buffer.total_len() - start_position {
+        let byte = data_buffer.read_slice(ext_memory, start_position + i, 1usize).map_err(|_| err_at.to_owned())?.as_ref()[0];
+        data.push(byte);
+        if let Ok(a) = T::decode(&mut &data[..]) {
+            out = Some((a, i+1));
+            break;
+        }
+    }
+    match out {
+        Some(a) => Ok(a),
+        None => Err(err_at),
+    }
+}
+
+impl <B> CheckedMetadataMetal<B> {
+    /// Assume here that the metadata is received as SCALE-encoded
+    /// `ShortMetadata` with known length.
+    ///
+    /// Provided buffer corresponds to whole encoded metadata.
+    pub fn from<E>(data_buffer: &B, ext_memory: &mut E) -> Result<Self, ReceivedMetadataError>
+    where
+        B: AddressableBuffer<E> + Clone,
+        E: ExternalMemory,
+    {
+        let mut position = 0usize;
+
+        // Metadata starts with types registry, a vec of Type descriptors.
+        // Search for compact, the number of `PortableType` entries to follow.
+        let found_compact = find_compact::<u32, B, E>(data_buffer, ext_memory, position).map_err(|_| ReceivedMetadataError::Format)?;
+
+        let types_set_len = found_compact.compact;
+        let mut registry: Vec<EntryPsram> = Vec::with_capacity(types_set_len as usize);
+        position = found_compact.start_next_unit;
+
+        for _entry_number in 0..types_set_len {
+            // Each `PortableType` starts with compact of the id.
+            let entry_number_compact = find_compact::<u32, B, E>(data_buffer, ext_memory, position).map_err(|_| ReceivedMetadataError::Format)?;
+            position = entry_number_compact.start_next_unit;
+
+            // And is followed by encoded `Type<PortableForm>` entry.
+            let (_ty, entry_len) = force_decode_at::<Type<PortableForm>, B, E>(data_buffer, ext_memory, position, ReceivedMetadataError::Format)?;
+
+            registry.push(EntryPsram{id: entry_number_compact.compact, position, entry_len});
+
+            position += entry_len;
+        }
+
+        let types = MetalRegistry {
+            metadata: data_buffer.clone(),
+            registry,
+        };
+
+        // The rest corresponds to `CheckedMeadataMetalTail`
+
+        let tail_data = data_buffer.read_slice(ext_memory, position, data_buffer.total_len() - position).map_err(|_| ReceivedMetadataError::Format)?;
+        let tail = CheckedMeadataMetalTail::decode_all(&mut tail_data.as_ref()).map_err(|_| ReceivedMetadataError::Format)?;
+
+        Ok(CheckedMetadataMetal{
+            types,
+            call_ty: tail.call_ty,
+            signed_extensions: tail.signed_extensions,
+            spec_name_version: tail.spec_name_version,
+            base58prefix: tail.base58prefix,
+            decimals: tail.decimals,
+            unit: tail.unit,
+        })
+    }
+
+    pub fn to_specs(&self) -> ShortSpecs {
+        ShortSpecs {
+            base58prefix: self.base58prefix,
+            decimals: self.decimals,
+            unit: self.unit.to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ReceivedMetadataError {
+    Format,
+//    Memory(MemoryError),
+//    UnableToDecode,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENESIS_HASH: [u8; GENESIS_HASH_LEN] = [0xe1; GENESIS_HASH_LEN];
+    const BLOCK_HASH: [u8; GENESIS_HASH_LEN] = [0xb1; GENESIS_HASH_LEN];
+
+    /// Era, nonce and tip, then spec version, transaction version, genesis hash and block hash
+    fn extensions(spec_version: u32, block_hash: [u8; GENESIS_HASH_LEN]) -> Vec<u8> {
+        [
+            &[0xb5, 0x03, 0x10, 0x00][..],
+            &spec_version.to_le_bytes(),
+            &19u32.to_le_bytes(),
+            &GENESIS_HASH,
+            &block_hash,
+        ].concat()
+    }
+
+    #[test]
+    fn spec_version_is_found_without_metadata() {
+        assert_eq!(raw_spec_version(&extensions(9430, BLOCK_HASH), H256(GENESIS_HASH)), Some(9430));
+    }
+
+    #[test]
+    fn spec_version_is_found_in_immortal_transaction() {
+        // block hash of immortal transaction is genesis hash
+        assert_eq!(raw_spec_version(&extensions(9430, GENESIS_HASH), H256(GENESIS_HASH)), Some(9430));
+    }
+
+    #[test]
+    fn spec_version_is_found_before_trailing_extensions() {
+        let mut data = extensions(1_002_000, BLOCK_HASH);
+        data.push(0);
+        assert_eq!(raw_spec_version(&data, H256(GENESIS_HASH)), Some(1_002_000));
+    }
+
+    #[test]
+    fn no_spec_version_for_other_network() {
+        assert_eq!(raw_spec_version(&extensions(9430, BLOCK_HASH), H256([0; GENESIS_HASH_LEN])), None);
+        assert_eq!(raw_spec_version(&GENESIS_HASH, H256(GENESIS_HASH)), None);
+    }
+
+    #[test]
+    fn only_undecodable_call_is_blind_signable() {
+        assert!(DecodeError::Call(String::new()).is_blind_signable());
+        assert!(!DecodeError::Extensions(String::new()).is_blind_signable());
+        assert!(!DecodeError::NoSpecVersion.is_blind_signable());
+        assert!(!DecodeError::Metadata(ReceivedMetadataError::Format).is_blind_signable());
+        assert!(!DecodeError::MetadataOutdated{in_metadata: String::from("9430"), in_transaction: String::from("9420")}.is_blind_signable());
+    }
+}
@@ -5,20 +5,31 @@ use crate::in_free;
 
 /// request single ADC measurement
 pub fn request_adc_measure() {
-    in_free(|peripherals|
-        peripherals
-            .IADC0_S
-            .cmd
-            .write(|w_reg| w_reg.singlestart().set_bit())
-    );
+    in_free(|peripherals| request_adc_measure_cs(peripherals));
+}
+
+/// request single ADC measurement
+///
+/// for critical section (panic)
+pub fn request_adc_measure_cs(peripherals: &mut Peripherals) {
+    peripherals
+        .IADC0_S
+        .cmd
+        .write(|w_reg| w_reg.singlestart().set_bit())
 }
 
 /// read value from ADC
 pub fn read_adc() -> i32 {
     let mut value = 0;
-    in_free(|peripherals|
-        value = peripherals.IADC0_S.singledata.read().data().bits() & 0x00FFFFFF
-    );
+    in_free(|peripherals| value = read_adc_cs(peripherals));
+    value
+}
+
+/// read value from ADC
+///
+/// for critical section (panic)
+pub fn read_adc_cs(peripherals: &mut Peripherals) -> i32 {
+    let value = peripherals.IADC0_S.singledata.read().data().bits() & 0x00FFFFFF;
     (if value & 0x00800000 == 0 {
         value
     } else {
@@ -36,12 +47,14 @@ pub fn read_int_flag(peripherals: &mut Peripherals) -> bool {
 }
 
 pub fn reset_int_flags() {
-    in_free(|peripherals|
-        peripherals
-            .IADC0_S
-            .if_
-            .reset()
-    );
+    in_free(|peripherals| reset_int_flags_cs(peripherals));
+}
+
+pub fn reset_int_flags_cs(peripherals: &mut Peripherals) {
+    peripherals
+        .IADC0_S
+        .if_
+        .reset()
 }
 
 /// Initialize ADC
@@ -8,7 +8,7 @@ pub const LINK_2: u32 = 0b11111111111111111111111111100011;
 
 pub const TIMER0_CC0_ICF: u32 = 0x40048074;
 
-pub const BUF_THIRD: usize = 2048;
+pub use crate::nfc::BUF_THIRD;
 
 #[repr(C)]
 #[derive(Debug)]
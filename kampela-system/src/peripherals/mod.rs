@@ -5,5 +5,6 @@ pub mod gpio_pins;
 pub mod i2c;
 pub mod ldma;
 pub mod se_command;
+pub mod temperature;
 pub mod timers;
 pub mod usart;
@@ -0,0 +1,21 @@
+//! Chip temperature from EMU sensor
+//!
+//! EMU measures temperature on its own, periodically in EM0 to EM2, so reading it needs no setup.
+
+use efm32pg23_fix::Peripherals;
+use crate::in_free;
+
+/// Kelvin to Celsius offset, rounded as EMU reports whole degrees
+const KELVIN_ZERO: i32 = 273;
+
+/// Last measured chip temperature, whole degrees Celsius
+pub fn temperature(peripherals: &mut Peripherals) -> i32 {
+    peripherals.EMU_S.temp.read().temp().bits() as i32 - KELVIN_ZERO
+}
+
+/// Last measured chip temperature, whole degrees Celsius
+pub fn read_temperature() -> i32 {
+    let mut value = 0;
+    in_free(|peripherals| value = temperature(peripherals));
+    value
+}
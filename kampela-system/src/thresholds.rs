@@ -0,0 +1,21 @@
+//! Voltage and temperature thresholds gating power-hungry and cold-sensitive operations
+//!
+//! Kampela runs on power harvested from NFC field; operations wait until supply voltage is
+//! above threshold. Shared with simulator energy model, so that thresholds could be tuned there.
+
+/// These are voltage thresholds to allow screen updates;
+/// for wired debug, set both well below 5000
+///
+//TODO tune these values for prod; something like 12k and 8k
+pub const FAST_REFRESH_POWER: i32 = 5000;
+pub const FULL_REFRESH_POWER: i32 = 5000;
+pub const PART_REFRESH_POWER: i32 = 5000;
+
+/// Lowest chip temperature, degrees Celsius, for display updates with fixed waveform: fast one
+/// forces LUT made for 100 degrees, partial one is made for room temperature.
+/// Below it EPD leaves faded and ghosted images, so such updates are replaced with full
+/// refresh, which takes LUT for temperature measured by panel itself.
+pub const FAST_REFRESH_MIN_TEMPERATURE: i32 = 5;
+
+/// Affects initiation time, but lower values result in unreliable nfc reception
+pub const NFC_MIN_VOLTAGE: i32 = 6000;
@@ -0,0 +1,266 @@
+//! Buffer of three regions, filled by writer in turn and read by reader
+//!
+//! Writer works in interrupt (NFC capture is written by LDMA), reader in main loop. At most one
+//! region is being read, at most one region is being written, and they are never the same. When
+//! writer fills region while reader is busy, filled region waits for reader and writer halts,
+//! as the only free region left would be overwritten before it could be read.
+
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+
+/// One of three buffer regions
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Region {
+    Reg0,
+    Reg1,
+    Reg2,
+}
+
+impl Region {
+    fn next(self) -> Self {
+        match self {
+            Region::Reg0 => Region::Reg1,
+            Region::Reg1 => Region::Reg2,
+            Region::Reg2 => Region::Reg0,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Region::Reg0 => 0,
+            Region::Reg1 => 1,
+            Region::Reg2 => 2,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BufferError {
+    /// Writer completed region while halted, so region waiting for reader got overwritten
+    Overrun,
+    /// Reader completed region while reading nothing
+    NothingRead,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// Nothing to read, writer fills region
+    Idle{write: Region},
+    /// Reader reads region; writer fills next one, or has filled it and is halted
+    Reading{read: Region, write_halted: bool},
+}
+
+/// Three regions of `LEN` values each, with state of their writer and reader
+#[derive(Clone)]
+pub struct TripleBuffer<T, const LEN: usize> {
+    regions: [[T; LEN]; 3],
+    state: State,
+    overruns: usize,
+}
+
+impl<T: Copy + Default, const LEN: usize> TripleBuffer<T, LEN> {
+    /// Writer starts with first region
+    pub fn new() -> Self {
+        Self {
+            regions: [[T::default(); LEN]; 3],
+            state: State::Idle{write: Region::Reg0},
+            overruns: 0,
+        }
+    }
+}
+
+impl<T: Copy + Default, const LEN: usize> Default for TripleBuffer<T, LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// region contents are left out, state is what matters in reports
+impl<T, const LEN: usize> Debug for TripleBuffer<T, LEN> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("TripleBuffer")
+            .field("state", &self.state)
+            .field("overruns", &self.overruns)
+            .finish()
+    }
+}
+
+impl<T, const LEN: usize> TripleBuffer<T, LEN> {
+    /// Start of region, for writer outside of program (DMA); stays the same while buffer is not
+    /// moved
+    pub fn region_address(&self, region: Region) -> *const T {
+        self.regions[region.index()].as_ptr()
+    }
+
+    /// Content of region reader should read now, if any is filled
+    pub fn read_third(&self) -> Option<&[T; LEN]> {
+        self.read_region().map(|region| &self.regions[region.index()])
+    }
+
+    /// Region writer fills now, for writer in program; `None` if writer is halted
+    pub fn write_third(&mut self) -> Option<&mut [T; LEN]> {
+        self.write_region().map(|region| &mut self.regions[region.index()])
+    }
+
+    /// Region writer fills now, if it is not halted
+    pub fn write_region(&self) -> Option<Region> {
+        match self.state {
+            State::Idle{write} => Some(write),
+            State::Reading{read, write_halted: false} => Some(read.next()),
+            State::Reading{write_halted: true, ..} => None,
+        }
+    }
+
+    /// Region reader should read now, if any is filled
+    pub fn read_region(&self) -> Option<Region> {
+        match self.state {
+            State::Idle{..} => None,
+            State::Reading{read, ..} => Some(read),
+        }
+    }
+
+    pub fn is_write_halted(&self) -> bool {
+        self.write_region().is_none()
+    }
+
+    /// Times writer completed region while halted
+    pub fn overruns(&self) -> usize {
+        self.overruns
+    }
+
+    /// Writer filled its region; returns `true` if writer should go on to the next one
+    pub fn write_done(&mut self) -> Result<bool, BufferError> {
+        self.state = match self.state {
+            State::Idle{write} => State::Reading{read: write, write_halted: false},
+            State::Reading{read, write_halted: false} => State::Reading{read, write_halted: true},
+            State::Reading{write_halted: true, ..} => {
+                self.overruns += 1;
+                return Err(BufferError::Overrun)
+            },
+        };
+        Ok(!self.is_write_halted())
+    }
+
+    /// Reader finished its region; returns `true` if halted writer should be restarted
+    pub fn read_done(&mut self) -> Result<bool, BufferError> {
+        let (state, restart) = match self.state {
+            State::Idle{..} => return Err(BufferError::NothingRead),
+            State::Reading{read, write_halted: false} => (State::Idle{write: read.next()}, false),
+            State::Reading{read, write_halted: true} => (State::Reading{read: read.next(), write_halted: false}, true),
+        };
+        self.state = state;
+        Ok(restart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::collections::VecDeque;
+
+    /// Every sequence of writes and reads up to this length is checked
+    const DEPTH: u32 = 14;
+
+    /// Buffer next to model of what it should hold: stamps of filled regions, oldest first
+    struct Checked {
+        buffer: TripleBuffer<u32, 4>,
+        sequence: u32,
+        unread: VecDeque<u32>,
+        overruns: usize,
+    }
+
+    impl Checked {
+        fn new() -> Self {
+            Self {
+                buffer: TripleBuffer::new(),
+                sequence: 0,
+                unread: VecDeque::new(),
+                overruns: 0,
+            }
+        }
+
+        /// Writer stamps region it fills with sequence number; halted writer has no region, and
+        /// completing it anyway is an overrun
+        fn write(&mut self) {
+            self.sequence += 1;
+            match self.buffer.write_third() {
+                Some(third) => {
+                    *third = [self.sequence; 4];
+                    self.unread.push_back(self.sequence);
+                    // writer goes on while one region is left to write in
+                    assert_eq!(self.buffer.write_done(), Ok(self.unread.len() < 2));
+                },
+                None => {
+                    assert_eq!(self.buffer.write_done(), Err(BufferError::Overrun));
+                    self.overruns += 1;
+                },
+            }
+        }
+
+        fn read(&mut self) {
+            match self.buffer.read_third() {
+                Some(third) => {
+                    // region is read in order it was filled, and was not written over meanwhile
+                    assert_eq!(*third, [self.unread.pop_front().expect("filled region is pending"); 4]);
+                    let was_halted = self.unread.len() == 1;
+                    assert_eq!(self.buffer.read_done(), Ok(was_halted));
+                },
+                None => {
+                    assert!(self.unread.is_empty());
+                    assert_eq!(self.buffer.read_done(), Err(BufferError::NothingRead));
+                },
+            }
+        }
+
+        fn check(&self) {
+            if let (Some(read), Some(write)) = (self.buffer.read_region(), self.buffer.write_region()) {
+                assert_ne!(read, write);
+            }
+            assert_eq!(self.buffer.overruns(), self.overruns);
+            assert!(self.unread.len() <= 2);
+            assert_eq!(self.buffer.is_write_halted(), self.unread.len() == 2);
+        }
+    }
+
+    /// Bit `i` of trace is operation `i`: set for write, clear for read. Every prefix is checked
+    /// on the way, so shorter traces are covered too.
+    #[test]
+    fn all_interleavings_keep_regions_apart() {
+        for trace in 0..1u32 << DEPTH {
+            let mut checked = Checked::new();
+            for step in 0..DEPTH {
+                if trace >> step & 1 == 1 {
+                    checked.write();
+                } else {
+                    checked.read();
+                }
+                checked.check();
+            }
+        }
+    }
+
+    #[test]
+    fn halted_writer_is_restarted_by_reader() {
+        let mut buffer: TripleBuffer<u8, 1> = TripleBuffer::new();
+        assert_eq!(buffer.read_region(), None);
+        assert_eq!(buffer.write_done(), Ok(true));
+        assert_eq!(buffer.read_region(), Some(Region::Reg0));
+        assert_eq!(buffer.write_done(), Ok(false));
+        assert!(buffer.is_write_halted());
+        assert_eq!(buffer.write_done(), Err(BufferError::Overrun));
+        assert_eq!(buffer.read_done(), Ok(true));
+        assert_eq!(buffer.read_region(), Some(Region::Reg1));
+        assert_eq!(buffer.write_region(), Some(Region::Reg2));
+        assert_eq!(buffer.read_done(), Ok(false));
+        assert_eq!(buffer.write_region(), Some(Region::Reg2));
+        assert_eq!(buffer.overruns(), 1);
+    }
+
+    #[test]
+    fn region_addresses_are_distinct_thirds() {
+        let buffer: TripleBuffer<u16, 8> = TripleBuffer::new();
+        let start = buffer.region_address(Region::Reg0);
+        assert_eq!(buffer.region_address(Region::Reg1), start.wrapping_add(8));
+        assert_eq!(buffer.region_address(Region::Reg2), start.wrapping_add(16));
+    }
+}
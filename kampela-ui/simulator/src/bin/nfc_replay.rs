@@ -0,0 +1,141 @@
+//! Replay of recorded NFC captures through the same decoding firmware uses
+//!
+//! Capture file is a dump of NFC buffer thirds in the order LDMA filled them: each third is
+//! `BUF_THIRD` little-endian `u16` TIMER0 capture values. Thirds go through Miller frame
+//! decoding, packet deserialization and LT decoding into emulated PSRAM.
+//!
+//! If file `<capture>.expected` exists next to capture, it should contain hex blake2b-256 hash
+//! of restored data; replay fails if data is not restored or hash differs. Exit code is 1 if any
+//! replay fails, so that a directory of captures could be checked in CI.
+//!
+//! Captures could be distorted to check decoder resilience: thirds dropped, packets reordered,
+//! capture values jittered.
+use blake2_rfc::blake2b::blake2b;
+use clap::Parser;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::{fs, path::{Path, PathBuf}, process::exit};
+
+use kampela_system::{
+    devices::psram::{psram_read_at_address, ExternalPsram},
+    hal::mock::MockPsram,
+    nfc::{capture_thirds, serialized_packets, CaptureError, NfcCollector, BUF_THIRD, CAPTURE_THIRD_LEN},
+};
+
+#[derive(Parser, Debug)]
+#[command(about = "Replay recorded NFC captures through firmware decoding")]
+struct Args {
+    /// Capture files
+    #[arg(required = true)]
+    captures: Vec<PathBuf>,
+
+    /// Drop every N-th buffer third, as if reading it was skipped
+    #[arg(long)]
+    drop_every: Option<usize>,
+
+    /// Feed packets in random order
+    #[arg(long)]
+    shuffle: bool,
+
+    /// Percentage of capture values shifted by one timer tick
+    #[arg(long, default_value_t = 0)]
+    noise: u8,
+
+    /// Seed for shuffle and noise
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+enum Outcome {
+    Restored(Vec<u8>),
+    Incomplete,
+    CollectorError(usize),
+}
+
+fn read_capture(path: &Path) -> Result<Vec<[u16; BUF_THIRD]>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("could not be read: {}", e))?;
+    capture_thirds(&bytes).map_err(|CaptureError::Length(len)| format!("length {} is not a multiple of buffer third, {} bytes", len, CAPTURE_THIRD_LEN))
+}
+
+fn replay(thirds: Vec<[u16; BUF_THIRD]>, args: &Args, rng: &mut StdRng) -> (usize, Outcome) {
+    let mut packets = Vec::new();
+    for (i, mut third) in thirds.into_iter().enumerate() {
+        if args.drop_every.is_some_and(|n| n != 0 && (i + 1) % n == 0) {
+            continue
+        }
+        for value in third.iter_mut() {
+            if rng.gen_range(0..100) < args.noise {
+                *value = if rng.gen() { value.wrapping_add(1) } else { value.wrapping_sub(1) };
+            }
+        }
+        packets.extend(serialized_packets(&third));
+    }
+    if args.shuffle {
+        packets.shuffle(rng);
+    }
+
+    let mut psram = MockPsram::growing();
+    let mut collector = NfcCollector::new();
+    let packets_found = packets.len();
+    for (i, packet) in packets.into_iter().enumerate() {
+        let mut external_psram = ExternalPsram{psram: &mut psram};
+        if collector.add_packet(&mut external_psram, packet).is_err() {
+            return (packets_found, Outcome::CollectorError(i))
+        }
+        if let NfcCollector::Done(ref data) = collector {
+            let restored = psram_read_at_address(&mut psram, data.start_address, data.len).expect("restored data is within PSRAM");
+            return (packets_found, Outcome::Restored(restored))
+        }
+    }
+    (packets_found, Outcome::Incomplete)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut rng = StdRng::seed_from_u64(args.seed);
+
+    let mut failed = false;
+    for capture in args.captures.iter() {
+        let thirds = match read_capture(capture) {
+            Ok(a) => a,
+            Err(e) => {
+                println!("{}: {}", capture.display(), e);
+                failed = true;
+                continue
+            },
+        };
+        let thirds_count = thirds.len();
+        let (packets_found, outcome) = replay(thirds, &args, &mut rng);
+        let expected_path = PathBuf::from(format!("{}.expected", capture.display()));
+        let expected = fs::read_to_string(&expected_path).ok().map(|a| a.trim().to_lowercase());
+
+        let status = match outcome {
+            Outcome::Restored(data) => {
+                let hash = hex(blake2b(32, &[], &data).as_bytes());
+                match expected {
+                    Some(ref expected) if expected != &hash => {
+                        failed = true;
+                        format!("restored {} bytes, hash {} does not match expected {}", data.len(), hash, expected)
+                    },
+                    _ => format!("restored {} bytes, hash {}", data.len(), hash),
+                }
+            },
+            Outcome::Incomplete => {
+                failed |= expected.is_some();
+                String::from("not enough packets to restore data")
+            },
+            Outcome::CollectorError(i) => {
+                failed |= expected.is_some();
+                format!("packet {} rejected by collector", i)
+            },
+        };
+        println!("{}: {} thirds, {} packets; {}", capture.display(), thirds_count, packets_found, status);
+    }
+
+    if failed {
+        exit(1)
+    }
+}
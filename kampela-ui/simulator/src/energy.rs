@@ -0,0 +1,169 @@
+//! Energy model of NFC-powered device
+//!
+//! Kampela has no battery: storage capacitor is charged by reader field and drained by display
+//! refreshes and security element operations. Firmware waits for capacitor voltage to pass
+//! thresholds from [`kampela_system::thresholds`] before refreshing display; simulator follows
+//! the same thresholds, so that UX under weak field could be checked and thresholds tuned without
+//! hardware.
+//!
+//! Model is rough: capacitor charges towards field open-circuit voltage exponentially, each
+//! operation takes fixed energy out of it. Costs are estimates and are all configurable.
+use clap::Args;
+use std::time::Duration;
+
+use kampela_system::{
+    hal::VoltageSource,
+    thresholds::{FAST_REFRESH_POWER, FULL_REFRESH_POWER, NFC_MIN_VOLTAGE, PART_REFRESH_POWER},
+};
+use kampela_ui::uistate::UpdateRequest;
+
+#[derive(Args, Clone, Debug)]
+pub struct EnergyArgs {
+    /// Model storage capacitor voltage and hold display refreshes until it passes thresholds
+    #[arg(long)]
+    pub energy: bool,
+
+    /// Open-circuit voltage of reader field, mV; 0 is device out of field
+    #[arg(long, default_value_t = 15000)]
+    pub field: i32,
+
+    /// Storage capacitance, uF
+    #[arg(long, default_value_t = 1000.0)]
+    pub capacitance: f64,
+
+    /// Charge time constant in field, ms
+    #[arg(long, default_value_t = 2000)]
+    pub charge_time: u64,
+
+    /// Energy of full refresh, mJ
+    #[arg(long, default_value_t = 30.0)]
+    pub full_refresh_cost: f64,
+
+    /// Energy of fast refresh, mJ
+    #[arg(long, default_value_t = 15.0)]
+    pub fast_refresh_cost: f64,
+
+    /// Energy of partial refresh, mJ
+    #[arg(long, default_value_t = 8.0)]
+    pub part_refresh_cost: f64,
+
+    /// Energy of single security element operation, mJ
+    #[arg(long, default_value_t = 2.0)]
+    pub se_cost: f64,
+
+    /// Time of single event loop iteration in headless mode, ms
+    #[arg(long, default_value_t = 100)]
+    pub energy_step: u64,
+}
+
+#[derive(Debug)]
+pub struct EnergyModel {
+    /// Capacitor voltage, mV
+    voltage: f64,
+    field: i32,
+    capacitance: f64,
+    charge_time: Duration,
+    full_refresh_cost: f64,
+    fast_refresh_cost: f64,
+    part_refresh_cost: f64,
+    se_cost: f64,
+    step: Duration,
+}
+
+impl EnergyModel {
+    /// Model from command line, if enabled; capacitor starts discharged
+    pub fn new(args: &EnergyArgs) -> Option<Self> {
+        if !args.energy {
+            return None
+        }
+        Some(Self {
+            voltage: 0.0,
+            field: args.field,
+            capacitance: args.capacitance * 1e-6,
+            charge_time: Duration::from_millis(args.charge_time),
+            full_refresh_cost: args.full_refresh_cost * 1e-3,
+            fast_refresh_cost: args.fast_refresh_cost * 1e-3,
+            part_refresh_cost: args.part_refresh_cost * 1e-3,
+            se_cost: args.se_cost * 1e-3,
+            step: Duration::from_millis(args.energy_step),
+        })
+    }
+
+    pub fn set_field(&mut self, field: i32) {
+        self.field = field;
+        println!("field set to {} mV", field);
+    }
+
+    /// Capacitor voltage could eventually reach level in current field
+    fn reachable(&self, voltage: i32) -> bool {
+        self.voltage >= voltage as f64 || self.field > voltage
+    }
+
+    /// Capacitor voltage could eventually reach NFC reception threshold
+    pub fn nfc_reachable(&self) -> bool {
+        self.reachable(NFC_MIN_VOLTAGE)
+    }
+
+    /// Let time pass, charging capacitor from field or leaking it out of field
+    pub fn charge(&mut self, time: Duration) {
+        let decay = (-time.as_secs_f64() / self.charge_time.as_secs_f64()).exp();
+        self.voltage = self.field as f64 + (self.voltage - self.field as f64) * decay;
+    }
+
+    /// Take energy in J from capacitor
+    fn drain(&mut self, energy: f64) {
+        let volts = self.voltage * 1e-3;
+        self.voltage = (volts * volts - 2.0 * energy / self.capacitance).max(0.0).sqrt() * 1e3;
+    }
+
+    /// Threshold and cost of refresh requested by UI; hidden updates do not touch display
+    fn refresh(&self, update: &UpdateRequest) -> Option<(i32, f64)> {
+        match update {
+            UpdateRequest::Hidden => None,
+            UpdateRequest::Slow => Some((FULL_REFRESH_POWER, self.full_refresh_cost)),
+            UpdateRequest::Fast => Some((FAST_REFRESH_POWER, self.fast_refresh_cost)),
+            UpdateRequest::UltraFast | UpdateRequest::Part(_) => Some((PART_REFRESH_POWER, self.part_refresh_cost)),
+        }
+    }
+
+    /// Check voltage before display refresh, as firmware frame buffer does, and spend energy
+    /// on refresh if it is allowed
+    pub fn try_refresh(&mut self, update: &UpdateRequest) -> bool {
+        match self.refresh(update) {
+            None => true,
+            Some((threshold, cost)) => {
+                let voltage = self.read();
+                if voltage > threshold {
+                    self.drain(cost);
+                    println!("refresh at {} mV, {} mV left", voltage, self.read());
+                    true
+                } else {
+                    false
+                }
+            },
+        }
+    }
+
+    pub fn se_operation(&mut self) {
+        self.drain(self.se_cost);
+    }
+}
+
+impl VoltageSource for EnergyModel {
+    /// Single event loop iteration of headless mode
+    fn advance(&mut self) {
+        self.charge(self.step);
+    }
+
+    fn read(&self) -> i32 {
+        self.voltage as i32
+    }
+}
+
+/// Pending update could be rendered now; always true without energy model
+pub fn refresh_allowed(energy: &mut Option<EnergyModel>, update: &Option<UpdateRequest>) -> bool {
+    match (energy, update) {
+        (Some(energy), Some(update)) => energy.try_refresh(update),
+        _ => true,
+    }
+}
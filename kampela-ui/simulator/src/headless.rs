@@ -0,0 +1,338 @@
+//! Headless mode: UI is driven by script and every rendered frame is saved as PNG
+//!
+//! Script is a text file with one command per line; empty lines and lines starting with `#` are
+//! skipped:
+//!
+//! - `tap X Y` - touch screen at point
+//! - `nfc transaction` - receive demo transaction through NFC
+//! - `nfc FILE` - receive transaction payload from file through NFC
+//! - `wait N` - let N event loop iterations pass without input
+//! - `field MV` - set reader field open-circuit voltage, with energy model enabled
+//!
+//! Each command takes one event loop iteration, as the window event loop does.
+//!
+//! With energy model, every iteration charges capacitor for fixed time, and pending update is
+//! rendered only when voltage allows it. NFC reception waits for voltage as well.
+//!
+//! If golden directory is given, frames are compared against files with the same names there,
+//! pixel by pixel, so that PNG encoder details do not matter. With `--bless`, rendered frames
+//! replace golden ones instead.
+//!
+//! Scripts in `scripts` directory are checked against golden frames in `golden/<script name>` by
+//! `cargo test`; command line options a script needs are given in its `# args:` line.
+use embedded_graphics_core::{geometry::Point, pixelcolor::BinaryColor};
+use embedded_graphics_simulator::{OutputSettings, SimulatorDisplay};
+use std::{fs::{self, File}, path::{Path, PathBuf}};
+
+use kampela_system::{hal::VoltageSource, thresholds::NFC_MIN_VOLTAGE};
+use kampela_ui::{
+    platform::Platform,
+    refresh::RefreshPolicy,
+    uistate::{UIState, UpdateRequest, UpdateRequestMutate},
+};
+
+use crate::{energy::refresh_allowed, DesktopSimulator, HALHandle, NfcTransactionData};
+
+enum Command {
+    Tap(Point),
+    NfcTransaction(Option<PathBuf>),
+    Wait(usize),
+    Field(i32),
+}
+
+fn parse_script(script: &str) -> Result<Vec<Command>, String> {
+    let mut commands = Vec::new();
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| word.parse().map_err(|_| format!("line {}: {} is not a number", i + 1, word));
+        let command = match words.as_slice() {
+            ["tap", x, y] => Command::Tap(Point::new(number(x)?, number(y)?)),
+            ["nfc", "transaction"] => Command::NfcTransaction(None),
+            ["nfc", file] => Command::NfcTransaction(Some(PathBuf::from(file))),
+            ["wait", n] => Command::Wait(number(n)? as usize),
+            ["field", mv] => Command::Field(number(mv)?),
+            _ => return Err(format!("line {}: unknown command {}", i + 1, line)),
+        };
+        commands.push(command);
+    }
+    Ok(commands)
+}
+
+pub struct Headless<'a> {
+    output_settings: &'a OutputSettings,
+    output: PathBuf,
+    golden: Option<PathBuf>,
+    bless: bool,
+    frame: usize,
+    mismatches: Vec<String>,
+    refresh_policy: RefreshPolicy,
+}
+
+impl<'a> Headless<'a> {
+    pub fn new(output_settings: &'a OutputSettings, output: PathBuf, golden: Option<PathBuf>, bless: bool) -> Self {
+        Self {
+            output_settings,
+            output,
+            golden,
+            bless,
+            frame: 0,
+            mismatches: Vec::new(),
+            refresh_policy: RefreshPolicy::new(),
+        }
+    }
+
+    /// Run script; returns `false` if any frame does not match golden one
+    pub fn run(
+        &mut self,
+        script: &Path,
+        state: &mut UIState<DesktopSimulator, SimulatorDisplay<BinaryColor>>,
+        h: &mut HALHandle,
+    ) -> bool {
+        let script = fs::read_to_string(script).expect("script file could not be read");
+        let commands = parse_script(&script).unwrap_or_else(|e| panic!("invalid script, {}", e));
+        fs::create_dir_all(&self.output).expect("output directory could not be created");
+        if let Some(ref golden) = self.golden {
+            if self.bless {
+                // frames of previous runs could be more than rendered now
+                let _ = fs::remove_dir_all(golden);
+                fs::create_dir_all(golden).expect("golden directory could not be created");
+            }
+        }
+
+        let mut update = Some(UpdateRequest::Slow);
+        self.step(&mut update, state, h);
+        for command in commands {
+            match command {
+                Command::Tap(point) => {
+                    update.propagate(state.handle_tap(point, h));
+                    self.step(&mut update, state, h);
+                },
+                Command::NfcTransaction(payload) => {
+                    if !self.wait_for_nfc(&mut update, state, h) {
+                        println!("field is too weak for NFC reception, transaction skipped");
+                        continue
+                    }
+                    let transaction = match payload {
+                        Some(file) => NfcTransactionData::from_file(&file, &mut state.platform.flash),
+                        None => NfcTransactionData::demo(),
+                    };
+                    state.platform.set_transaction(transaction);
+                    update.propagate(state.handle_transaction(h));
+                    self.step(&mut update, state, h);
+                },
+                Command::Wait(n) => {
+                    for _ in 0..n {
+                        self.step(&mut update, state, h);
+                    }
+                },
+                Command::Field(mv) => {
+                    match state.platform.energy {
+                        Some(ref mut energy) => energy.set_field(mv),
+                        None => println!("energy model is not enabled, field ignored"),
+                    }
+                    self.step(&mut update, state, h);
+                },
+            }
+        }
+
+        self.check_golden_count();
+        for mismatch in self.mismatches.iter() {
+            println!("{}", mismatch);
+        }
+        self.mismatches.is_empty()
+    }
+
+    /// Single event loop iteration: render pending update, if any
+    fn step(
+        &mut self,
+        update: &mut Option<UpdateRequest>,
+        state: &mut UIState<DesktopSimulator, SimulatorDisplay<BinaryColor>>,
+        h: &mut HALHandle,
+    ) {
+        if let Some(ref mut energy) = state.platform.energy {
+            energy.advance();
+        }
+        let important = state.is_important_screen();
+        if let Some(u) = update.take() {
+            *update = Some(self.refresh_policy.adjust(u, important));
+        }
+        if !refresh_allowed(&mut state.platform.energy, update) {
+            return
+        }
+        if let Some(u) = update.take() {
+            self.refresh_policy.record(&u, important);
+            let is_clear_update = matches!(u, UpdateRequest::Slow) || matches!(u, UpdateRequest::Fast);
+            match state.render(is_clear_update, h) {
+                Ok(a) => update.propagate(a),
+                Err(e) => println!("{:?}", e),
+            };
+            self.save_frame(&state.display);
+        }
+    }
+
+    /// Let event loop iterations pass until voltage is enough to receive NFC; returns `false` if
+    /// it could never be in current field
+    fn wait_for_nfc(
+        &mut self,
+        update: &mut Option<UpdateRequest>,
+        state: &mut UIState<DesktopSimulator, SimulatorDisplay<BinaryColor>>,
+        h: &mut HALHandle,
+    ) -> bool {
+        loop {
+            match state.platform.energy {
+                Some(ref energy) if energy.read() < NFC_MIN_VOLTAGE => {
+                    if !energy.nfc_reachable() {
+                        return false
+                    }
+                },
+                _ => return true,
+            }
+            self.step(update, state, h);
+        }
+    }
+
+    fn save_frame(&mut self, display: &SimulatorDisplay<BinaryColor>) {
+        let name = format!("{:04}.png", self.frame);
+        self.frame += 1;
+
+        let path = self.output.join(&name);
+        display
+            .to_rgb_output_image(self.output_settings)
+            .save_png(&path)
+            .expect("frame could not be saved");
+        println!("frame {} saved", path.display());
+
+        if let Some(ref golden) = self.golden {
+            let golden_path = golden.join(&name);
+            if self.bless {
+                fs::copy(&path, &golden_path).expect("golden frame could not be saved");
+                return
+            }
+            match (decode_png(&path), decode_png(&golden_path)) {
+                (Ok(rendered), Ok(expected)) => {
+                    if let Some(difference) = rendered.difference(&expected) {
+                        self.mismatches.push(format!("frame {} differs from {}: {}", name, golden_path.display(), difference));
+                    }
+                },
+                (Err(e), _) => panic!("frame {} could not be read back: {}", path.display(), e),
+                (_, Err(e)) => self.mismatches.push(format!("frame {} has no golden counterpart: {}", name, e)),
+            }
+        }
+    }
+
+    /// Golden frames not rendered in this run are mismatches too
+    fn check_golden_count(&mut self) {
+        if self.bless {
+            return
+        }
+        if let Some(ref golden) = self.golden {
+            let golden_frames = fs::read_dir(golden)
+                .map(|entries| entries.filter(|entry| entry.as_ref().is_ok_and(|entry| entry.path().extension() == Some("png".as_ref()))).count())
+                .unwrap_or(0);
+            if golden_frames > self.frame {
+                self.mismatches.push(format!("{} frames rendered, {} golden ones expected", self.frame, golden_frames));
+            }
+        }
+    }
+}
+
+/// Decoded image, as 8-bit RGBA
+struct Frame {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl Frame {
+    /// Description of difference, if frames differ
+    fn difference(&self, other: &Frame) -> Option<String> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Some(format!("size {}x{} instead of {}x{}", self.width, self.height, other.width, other.height))
+        }
+        let differing = self.rgba
+            .chunks(4)
+            .zip(other.rgba.chunks(4))
+            .filter(|(a, b)| a != b)
+            .count();
+        (differing != 0).then(|| format!("{} pixels differ", differing))
+    }
+}
+
+fn decode_png(path: &Path) -> Result<Frame, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+    buffer.truncate(info.buffer_size());
+    let rgba = match info.color_type {
+        png::ColorType::Grayscale => buffer.iter().flat_map(|g| [*g, *g, *g, u8::MAX]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]]).collect(),
+        png::ColorType::Rgb => buffer.chunks(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX]).collect(),
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Indexed => return Err(String::from("palette was not expanded")),
+    };
+    Ok(Frame {
+        width: info.width,
+        height: info.height,
+        rgba,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use clap::Parser;
+    use std::env;
+
+    use crate::{run_script, Args};
+
+    /// Set to replace golden frames with rendered ones
+    const BLESS_VARIABLE: &str = "KAMPELA_BLESS_GOLDEN";
+
+    fn script_args(script: &Path) -> Vec<String> {
+        let text = fs::read_to_string(script).expect("script could be read");
+        text.lines()
+            .find_map(|line| line.strip_prefix("# args:"))
+            .map(|args| args.split_whitespace().map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn scripts_match_golden_frames() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut scripts: Vec<PathBuf> = fs::read_dir(root.join("scripts"))
+            .expect("scripts directory exists")
+            .map(|entry| entry.expect("script entry").path())
+            .filter(|path| path.extension() == Some("txt".as_ref()))
+            .collect();
+        scripts.sort();
+        assert!(!scripts.is_empty());
+
+        let mut failed = Vec::new();
+        for script in scripts {
+            let name = script.file_stem().and_then(|stem| stem.to_str()).expect("script name").to_owned();
+            let mut command_line = vec![String::from("kampela-ui-simulator")];
+            command_line.extend(script_args(&script));
+            command_line.extend([
+                String::from("-S"), script.display().to_string(),
+                String::from("-O"), env::temp_dir().join("kampela-frames").join(&name).display().to_string(),
+                String::from("-G"), root.join("golden").join(&name).display().to_string(),
+            ]);
+            if env::var_os(BLESS_VARIABLE).is_some() {
+                command_line.push(String::from("--bless"));
+            }
+            let args = Args::try_parse_from(&command_line).unwrap_or_else(|e| panic!("invalid args in script {}: {}", name, e));
+            if !run_script(args) {
+                failed.push(name);
+            }
+        }
+        assert!(failed.is_empty(), "frames differ from golden ones for scripts {:?}; if change is intended, rerun with {} set", failed, BLESS_VARIABLE);
+    }
+}
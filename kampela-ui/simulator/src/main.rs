@@ -1,44 +1,72 @@
 //! This is simulator to develop Kampela UI mocks
 #![deny(unused_crate_dependencies)]
-use embedded_graphics_core::{
-    primitives::PointsIter,
-    Drawable,
-    pixelcolor::BinaryColor,
-    Pixel,
-};
+use embedded_graphics_core::pixelcolor::BinaryColor;
 
 use embedded_graphics_simulator::{
-    BinaryColorTheme, OutputSettingsBuilder, SimulatorDisplay, SimulatorEvent, Window,
+    BinaryColorTheme, OutputSettings, OutputSettingsBuilder, SimulatorDisplay,
 };
-use rand::{rngs::ThreadRng, thread_rng};
-use std::{collections::VecDeque, thread::sleep, time::Duration};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{fs, path::PathBuf, process::exit, sync::OnceLock};
+use blake2_rfc::blake2b::blake2b;
 use clap::Parser;
 use substrate_crypto_light::sr25519::Public;
 use mnemonic_external::regular::InternalWordList;
 
-/// Amount of time required for full screen update; debounce
-///  should be quite large as screen takes this much to clean
-const SLOW_UPDATE_TIME: Duration = Duration::new(1, 0);
-const BLINK_UPDATE_TIME: Duration = Duration::new(0, 5000000);
-const SLOW_UPDATE_ITER: usize = 8;
-const FAST_UPDATE_TIME: Duration = Duration::new(1, 0);
-const ULTRAFAST_UPDATE_TIME: Duration = Duration::new(1, 0);
-const UPDATE_DELAY_TIME: Duration = Duration::new(0, 500000000);
+/// Font image given on command line, as it would be in device flash
+static FONT: OnceLock<Vec<u8>> = OnceLock::new();
+
+mod energy;
+use energy::{EnergyArgs, EnergyModel};
+
+mod headless;
+use headless::Headless;
+
+mod payload;
+use payload::decode_error_text;
 
-const MAX_TOUCH_QUEUE: usize = 2;
+mod storage;
+use storage::{EmulatedFlash, Record};
 
+#[cfg(feature = "window")]
+mod window;
+
+use kampela_system::{
+    flash_address_book::{store_contact, AddressBookError, Contact, ACCOUNT_ID_LEN},
+    devices::psram::signing_payload,
+    flash_settings::{read_settings, store_settings, Settings},
+    parser::DecodeError,
+};
 use kampela_ui::{
     data_state::{AppStateInit, NFCState, DataInit, StorageState},
     display_def::*,
-    platform::{PinCode, Platform},
-    uistate::{UIState, UpdateRequest, UpdateRequestMutate},
+    locale::{Language, Text},
+    platform::{encode_signature, verify_encoded_signature, Network, PinCode, Platform, TransactionError, UnsavedAccount},
+    uistate::UIState,
 };
 
 #[derive(Debug)]
 pub struct NfcTransactionData {
     pub call: String,
     pub extension: String,
-    pub signature: [u8; 130],
+    pub data_to_sign: Vec<u8>,
+    pub decode_error: Option<DecodeError>,
+    /// Accounts in call that are not in address book, with their cards as shown
+    pub unsaved_accounts: Vec<([u8; ACCOUNT_ID_LEN], String)>,
+    /// Received payload, empty for demo transaction
+    pub payload: Vec<u8>,
+}
+
+impl NfcTransactionData {
+    pub fn demo() -> Self {
+        NfcTransactionData{
+            call: String::from("Hello, this is a transaction!"),
+            extension: String::from("Hello, this is a transaction!"),
+            data_to_sign: Vec::new(),
+            decode_error: None,
+            unsaved_accounts: Vec::new(),
+            payload: Vec::new(),
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -49,6 +77,51 @@ struct Args {
 
     #[arg(short = 'T')]
     transaction_received: bool,
+
+    /// Transaction payload file, in the format device receives through NFC; replaces demo
+    /// transaction
+    #[arg(short = 'P', long)]
+    payload: Option<PathBuf>,
+
+    /// Run without window, driving UI with script
+    #[arg(short = 'S', long)]
+    script: Option<PathBuf>,
+
+    /// Directory for frames rendered in headless mode
+    #[arg(short = 'O', long, default_value = "frames")]
+    output: PathBuf,
+
+    /// Directory with reference frames to compare rendered frames against
+    #[arg(short = 'G', long)]
+    golden: Option<PathBuf>,
+
+    /// Save rendered frames into golden directory, instead of comparing
+    #[arg(long, requires = "golden")]
+    bless: bool,
+
+    /// Emulated flash image, kept between runs; created if missing
+    #[arg(short = 'F', long)]
+    flash: Option<PathBuf>,
+
+    /// Erase emulated flash before start
+    #[arg(long)]
+    fresh: bool,
+
+    /// Store well-known test mnemonic as seed before start
+    #[arg(long)]
+    test_mnemonic: bool,
+
+    /// Corrupt record in emulated flash before start, to check recovery
+    #[arg(long, value_enum)]
+    corrupt: Vec<Record>,
+
+    /// Proportional font image, as built by `kampela-host font`; without it text is drawn with
+    /// ASCII mono fonts
+    #[arg(long)]
+    font: Option<PathBuf>,
+
+    #[command(flatten)]
+    energy: EnergyArgs,
 }
 
 impl DataInit<Args> for AppStateInit {
@@ -71,16 +144,23 @@ impl DataInit<Args> for AppStateInit {
 }
 
 struct HALHandle {
-    pub rng: ThreadRng,
+    pub rng: StdRng,
 }
 
 impl HALHandle {
     pub fn new() -> Self {
-        let rng = thread_rng();
+        let rng = StdRng::from_entropy();
         Self {
             rng: rng,
         }
     }
+
+    /// Same random sequence on every run, so that rendered frames are reproducible
+    pub fn new_seeded() -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(0),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -89,33 +169,39 @@ struct DesktopSimulator {
     entropy: Option<Vec<u8>>,
     address: Option<[u8; 76]>,
     transaction: Option<NfcTransactionData>,
-    stored_entropy: Option<Vec<u8>>,
+    settings: Settings,
+    flash: EmulatedFlash,
+    energy: Option<EnergyModel>,
+    /// Signing randomness, kept apart from UI one so that UI stays reproducible
+    signing_rng: StdRng,
+    /// Font image from `--font`, empty without it
+    font: &'static [u8],
 }
 
 impl DesktopSimulator {
-    pub fn new(init_state: &AppStateInit) -> Self {
-        let pin = [0; 4];
+    pub fn new(init_state: &AppStateInit, mut flash: EmulatedFlash, signing_rng: StdRng) -> Self {
+        let pin = flash.read_pin().unwrap_or([0; 4]);
         let transaction = match init_state.nfc {
             NFCState::Empty => None,
-            NFCState::Transaction => Some(NfcTransactionData{
-                call: String::from("Hello, this is a transaction!"),
-                extension: String::from("Hello, this is a transaction!"),
-                signature: [0u8; 130],
-            }),
+            NFCState::Transaction => Some(NfcTransactionData::demo()),
         };
         Self {
             pin,
             entropy: None,
             address: None,
             transaction: transaction,
-            stored_entropy: None,
+            settings: read_settings(&mut flash),
+            flash,
+            energy: None,
+            signing_rng,
+            font: FONT.get().map(|font| &font[..]).unwrap_or(&[]),
         }
     }
 }
 
 impl Platform for DesktopSimulator {
     type HAL = HALHandle;
-    type Rng<'a> = &'a mut ThreadRng;
+    type Rng<'a> = &'a mut StdRng;
     type NfcTransaction = NfcTransactionData;
     type AsWordList = InternalWordList;
 
@@ -123,6 +209,12 @@ impl Platform for DesktopSimulator {
         InternalWordList
     }
 
+    type FontStorage = &'static [u8];
+
+    fn font_storage(&self) -> &Self::FontStorage {
+        &self.font
+    }
+
     fn rng<'a>(h: &'a mut Self::HAL) -> Self::Rng<'a> {
         &mut h.rng
     }
@@ -136,13 +228,25 @@ impl Platform for DesktopSimulator {
     }
 
     fn store_entropy(&mut self, e: &[u8]) {
-        self.entropy = Some(e.to_vec());
-        println!("entropy stored (not really, this is emulator)");
+        if let Some(ref mut energy) = self.energy {
+            energy.se_operation();
+        }
+        self.entropy = if e.len() != 0 {
+            self.flash.store_seed(e);
+            self.flash.store_pin(&self.pin);
+            println!("entropy stored in emulated flash");
+            Some(e.to_vec())
+        } else {
+            None
+        }
     }
 
     fn read_entropy(&mut self) {
-        self.entropy = self.stored_entropy.clone();
-        println!("entropy read from emulated storage: {:?}", &self.entropy);
+        if let Some(ref mut energy) = self.energy {
+            energy.se_operation();
+        }
+        self.entropy = self.flash.read_seed();
+        println!("entropy read from emulated flash: {:?}", &self.entropy);
     }
 
     fn public(&self) -> Option<Public> {
@@ -161,6 +265,29 @@ impl Platform for DesktopSimulator {
         self.transaction = Some(transaction);
     }
 
+    fn network(&mut self) -> Option<Network> {
+        None
+    }
+
+    fn transaction_error(&mut self) -> Option<TransactionError> {
+        match self.transaction {
+            Some(NfcTransactionData{decode_error: Some(DecodeError::MetadataOutdated{ref in_metadata, ref in_transaction}), ..}) => Some(TransactionError::MetadataOutdated{
+                in_metadata: in_metadata.clone(),
+                in_transaction: in_transaction.clone(),
+            }),
+            Some(NfcTransactionData{decode_error: Some(ref e), ..}) if e.is_blind_signable() => Some(TransactionError::Undecodable(decode_error_text(e))),
+            Some(NfcTransactionData{decode_error: Some(ref e), ..}) => Some(TransactionError::Unsignable(decode_error_text(e))),
+            _ => None,
+        }
+    }
+
+    fn payload_hash(&mut self) -> [u8; 32] {
+        match self.transaction {
+            Some(ref a) => blake2b(32, &[], &a.data_to_sign).as_bytes().try_into().expect("static length"),
+            None => panic!("no transaction to hash"),
+        }
+    }
+
     fn call(&mut self) -> Option<String> {
         match self.transaction {
             Some(ref a) => Some(a.call.to_owned()),
@@ -175,11 +302,50 @@ impl Platform for DesktopSimulator {
         }
     }
 
-    fn signature(&mut self) -> [u8; 130] {
+    fn unsaved_accounts(&mut self) -> Vec<UnsavedAccount> {
         match self.transaction {
-            Some(ref a) => a.signature,
+            Some(ref a) => a.unsaved_accounts
+                .iter()
+                .map(|(account_id, address)| UnsavedAccount {
+                    account_id: *account_id,
+                    address: address.clone(),
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn save_contact(&mut self, account_id: [u8; ACCOUNT_ID_LEN], label: String) -> Result<(), String> {
+        let contact = Contact {
+            account_id,
+            label,
+            genesis_hash: None,
+        };
+        store_contact(&mut self.flash, &contact).map_err(|e| match e {
+            AddressBookError::AddressBookFull => String::from(Text::AddressBookFull.get()),
+            AddressBookError::TooLarge => String::from(Text::ContactTooLarge.get()),
+            AddressBookError::Flash(_) => String::from(Text::ContactSaveFailed.get()),
+        })?;
+        println!("contact {} saved in emulated flash", contact.label);
+
+        // labels appear in shown call only after decoding again
+        if let Some(ref mut transaction) = self.transaction {
+            transaction.redecode(&mut self.flash);
+        }
+        Ok(())
+    }
+
+    fn signature(&mut self) -> [u8; 130] {
+        let data_to_sign = match self.transaction {
+            Some(ref a) => signing_payload(&a.data_to_sign),
             None =>  panic!("qr not ready!"),
+        };
+        let pair = self.pair().expect("entropy should be stored at this point");
+        let signature = encode_signature(&pair.sign_external_rng(&data_to_sign, &mut self.signing_rng));
+        if !verify_encoded_signature(&pair.public(), &data_to_sign, &signature) {
+            panic!("signature could not be verified");
         }
+        signature
     }
 
     fn address(&mut self) -> &[u8; 76] {
@@ -189,126 +355,174 @@ impl Platform for DesktopSimulator {
             panic!("address qr not ready!");
         }
     }
+
+    fn allow_blind_signing(&self) -> bool {
+        self.settings.allow_blind_signing
+    }
+
+    fn set_allow_blind_signing(&mut self, allow: bool) {
+        self.settings.allow_blind_signing = allow;
+        if let Err(_) = store_settings(&mut self.flash, &self.settings) {
+            panic!("Failed to save settings");
+        }
+        println!("blind signing allowed: {}", allow);
+    }
+
+    fn language(&self) -> Language {
+        self.settings.language
+    }
+
+    fn set_language(&mut self, language: Language) {
+        self.settings.language = language;
+        if let Err(_) = store_settings(&mut self.flash, &self.settings) {
+            panic!("Failed to save settings");
+        }
+        println!("language: {}", language.name());
+    }
+
+    /// Stored only; simulator window always shows UI coordinates
+    fn orientation(&self) -> Orientation {
+        self.settings.orientation
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.settings.orientation = orientation;
+        if let Err(_) = store_settings(&mut self.flash, &self.settings) {
+            panic!("Failed to save settings");
+        }
+        println!("screen rotation: {}", orientation.degrees());
+    }
 }
 
 
-fn main() {
-    let args = Args::parse();
+type SimulatorState = UIState<DesktopSimulator, SimulatorDisplay<BinaryColor>>;
+
+/// Simulator set up as command line asks
+fn init(args: Args) -> (SimulatorState, HALHandle) {
+    let scripted = args.script.is_some();
+    let payload = args.payload.clone();
+    let energy = EnergyModel::new(&args.energy);
+
+    let mut flash = EmulatedFlash::open(args.flash.clone(), args.fresh);
+    if args.test_mnemonic {
+        flash.store_test_seed();
+    }
+    for record in args.corrupt.iter() {
+        flash.corrupt(*record);
+    }
+    if let Some(ref path) = args.font {
+        match fs::read(path) {
+            Ok(font) => { let _ = FONT.set(font); },
+            Err(e) => {
+                println!("font {} could not be read: {}", path.display(), e);
+                exit(1);
+            },
+        }
+    }
     let init_data_state = AppStateInit::new(args);
     println!("{:?}", init_data_state);
 
-    /*
-    // Prepare
-    let mut display: SimulatorDisplay<BinaryColor> =
-        SimulatorDisplay::new(Size::new(SCREEN_SIZE_X, SCREEN_SIZE_Y));
-*/
-    let mut h = HALHandle::new();
-    let desktop = DesktopSimulator::new(&init_data_state);
+    let mut h = if scripted {
+        HALHandle::new_seeded()
+    } else {
+        HALHandle::new()
+    };
+    let signing_rng = if scripted {
+        StdRng::seed_from_u64(1)
+    } else {
+        StdRng::from_entropy()
+    };
+    let mut desktop = DesktopSimulator::new(&init_data_state, flash, signing_rng);
+    if let Some(ref payload) = payload {
+        desktop.transaction = Some(NfcTransactionData::from_file(payload, &mut desktop.flash));
+    }
+    desktop.energy = energy;
     let display = SimulatorDisplay::new(SCREEN_SIZE);
-    let mut state = UIState::new(desktop, display, &mut h);
+    let state = UIState::new(desktop, display, &mut h);
+    (state, h)
+}
 
-    // Draw
-    let output_settings = OutputSettingsBuilder::new()
+fn output_settings() -> OutputSettings {
+    OutputSettingsBuilder::new()
         .theme(BinaryColorTheme::Inverted)
-        .build();
-    let mut window = Window::new("Hello world", &output_settings); //.show_static(&display);
-    
-    let mut update = Some(UpdateRequest::Slow);
-
-    let mut touches = VecDeque::new();
-
-    // event loop:
-    //
-    // 1. draw
-    // 2. collect input
-    // 3. handle input
-    // 4. do internal things
-    loop {
-        // touch event
-        if let Some(point) = touches.pop_front() {
-            update.propagate(state.handle_tap(point, &mut h));
-        };
-        // display event; it would be delayed
-        if let Some(u) = update.take() {
-            sleep(UPDATE_DELAY_TIME);
-            let is_clear_update = matches!(u, UpdateRequest::Slow) || matches!(u, UpdateRequest::Fast);
-            match state.render(is_clear_update, &mut h) {
-                Ok(a) => update.propagate(a),
-                Err(e) => println!("{:?}", e),
-            };
-
-            match u {
-                UpdateRequest::Hidden => {
-                    window.update(&state.display);
-                    println!("skip {} events in hidden update", window.events().count());
-                },
-                UpdateRequest::Slow => {
-                    invert_display(&mut state.display);
-                    window.update(&state.display);
-                    sleep(SLOW_UPDATE_TIME);
-                    invert_display(&mut state.display);
-                    window.update(&state.display);
-                    for _i in 0..SLOW_UPDATE_ITER {
-                        invert_display(&mut state.display);
-                        window.update(&state.display);
-                        sleep(BLINK_UPDATE_TIME);
-                        invert_display(&mut state.display);
-                        window.update(&state.display);
-                        sleep(BLINK_UPDATE_TIME);
-                    }
-
-                    window.update(&state.display);
-                    println!("skip {} events in slow update", window.events().count());
-                },
-                UpdateRequest::Fast => {
-                    invert_display(&mut state.display);
-                    window.update(&state.display);
-                    sleep(FAST_UPDATE_TIME);
-                    invert_display(&mut state.display);
-                    window.update(&state.display);
-                    println!("fast update");
-                },
-                UpdateRequest::UltraFast => {
-                    window.update(&state.display);
-                    println!("ultrafast update");
-                    sleep(ULTRAFAST_UPDATE_TIME);
-                },
-                UpdateRequest::Part(a) => {
-                    window.update(&state.display);
-                    println!("part update of area {:?}", a);
-                    sleep(ULTRAFAST_UPDATE_TIME);
-                },
-            }
+        .build()
+}
+
+/// Run script given on command line; returns `false` if any frame does not match golden one
+fn run_script(args: Args) -> bool {
+    let script = args.script.clone().expect("script is given");
+    let output = args.output.clone();
+    let golden = args.golden.clone();
+    let bless = args.bless;
+    let (mut state, mut h) = init(args);
+    let output_settings = output_settings();
+    Headless::new(&output_settings, output, golden, bless).run(&script, &mut state, &mut h)
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.script.is_some() {
+        if !run_script(args) {
+            exit(1);
         }
-        // this collects ui events, do not remove or simulator will crash
-        window.update(&state.display);
-
-        // register input (only pushes are valid in Kampela)
-        for event in window.events() {
-            match event {
-                SimulatorEvent::MouseButtonDown {
-                    mouse_btn: _,
-                    point,
-                } => {
-                    if touches.len() < MAX_TOUCH_QUEUE {
-                        touches.push_back(point);
-                        println!("point {} registered", point);
-                    } else {
-                        println!("point {} omitted", point);
-                    }
-                }
-                SimulatorEvent::Quit => return,
-                _ => (),
+        return
+    }
+
+    #[cfg(feature = "window")]
+    {
+        let (mut state, mut h) = init(args);
+        window::run(&mut state, &mut h, &output_settings());
+    }
+    #[cfg(not(feature = "window"))]
+    {
+        println!("simulator is built without window, run it with script");
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use substrate_crypto_light::sr25519::Pair;
+
+    const ENTROPY: [u8; 32] = [7; 32];
+
+    fn simulator_with(data_to_sign: Vec<u8>) -> DesktopSimulator {
+        let init_state = AppStateInit {
+            nfc: NFCState::Empty,
+            storage: StorageState { key_created: true },
+        };
+        let mut simulator = DesktopSimulator::new(&init_state, EmulatedFlash::open(None, true), StdRng::seed_from_u64(0));
+        simulator.entropy = Some(ENTROPY.to_vec());
+        simulator.transaction = Some(NfcTransactionData {
+            data_to_sign,
+            ..NfcTransactionData::demo()
+        });
+        simulator
+    }
+
+    fn public(entropy: &[u8]) -> Public {
+        Pair::from_entropy_and_pwd(entropy, "").ok().expect("valid entropy length").public()
+    }
+
+    #[test]
+    fn signature_verifies_with_seed_public_key() {
+        for len in [1, 256, 257, 1000] {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let signature = simulator_with(data.clone()).signature();
+            assert!(verify_encoded_signature(&public(&ENTROPY), &signing_payload(&data), &signature));
+            if len > 256 {
+                // long payloads are signed by hash only
+                assert!(!verify_encoded_signature(&public(&ENTROPY), &data, &signature));
             }
         }
+    }
 
-        //and here is some loop time for other things
+    #[test]
+    fn signature_does_not_verify_with_other_key() {
+        let data = vec![1, 2, 3];
+        let signature = simulator_with(data.clone()).signature();
+        assert!(!verify_encoded_signature(&public(&[8; 32]), &signing_payload(&data), &signature));
     }
 }
-
-fn invert_display(display: &mut SimulatorDisplay<BinaryColor>) {
-    for point in SCREEN_AREA.points() {
-        let dot = Pixel::<BinaryColor>(point, display.get_pixel(point).invert());
-        dot.draw(display).unwrap();
-    };
-}
\ No newline at end of file
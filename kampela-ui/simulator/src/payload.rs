@@ -0,0 +1,110 @@
+//! Transaction payloads in the same format device receives through NFC
+//!
+//! Payload is decoded with the same code firmware uses, with plain host memory in place of PSRAM.
+//! Accounts known to address book in emulated flash are shown with labels.
+use primitive_types::H256;
+use std::{fs, path::Path};
+
+use kampela_system::{
+    flash_address_book::{show_card, ACCOUNT_ID_LEN},
+    hal::FlashBus,
+    parser::{decode_call, decode_extensions, transaction_payload, CheckedMetadataMetal, DecodeError, TransactionPayload},
+};
+
+use kampela_ui::locale::Text;
+
+use crate::NfcTransactionData;
+
+/// Payload type byte for transaction
+const PAYLOAD_TRANSACTION: u8 = 3;
+
+impl NfcTransactionData {
+    /// Read payload file and decode transaction in it
+    pub fn from_file<F: FlashBus>(path: &Path, flash: &mut F) -> Self {
+        let payload = fs::read(path).expect("payload file could not be read");
+        Self::from_payload(&payload, flash).unwrap_or_else(|e| panic!("invalid payload in {}: {}", path.display(), e))
+    }
+
+    pub fn from_payload<F: FlashBus>(payload: &[u8], flash: &mut F) -> Result<Self, String> {
+        if payload.first() != Some(&PAYLOAD_TRANSACTION) {
+            return Err(String::from("not a transaction payload"))
+        }
+        let parts = transaction_payload::<&[u8], ()>(&payload, &mut ()).map_err(|e| format!("{:?}", e))?;
+
+        let call_data = &payload[parts.call.clone()];
+        let extension_data = &payload[parts.extension.clone()];
+        let mut data_to_sign = call_data.to_vec();
+        data_to_sign.extend_from_slice(extension_data);
+
+        let mut transaction = NfcTransactionData {
+            call: String::new(),
+            extension: String::new(),
+            data_to_sign,
+            decode_error: None,
+            unsaved_accounts: Vec::new(),
+            payload: payload.to_vec(),
+        };
+        transaction.decode(&parts, flash);
+        Ok(transaction)
+    }
+
+    /// Decode again, so that contacts saved since are shown
+    pub fn redecode<F: FlashBus>(&mut self, flash: &mut F) {
+        if let Ok(parts) = transaction_payload::<&[u8], ()>(&self.payload.as_slice(), &mut ()) {
+            self.decode(&parts, flash);
+        }
+    }
+
+    fn decode<F: FlashBus>(&mut self, parts: &TransactionPayload, flash: &mut F) {
+        match decode_transaction(&self.payload, parts, flash) {
+            Ok((call, extension, unsaved_accounts)) => {
+                self.call = call;
+                self.extension = extension;
+                self.unsaved_accounts = unsaved_accounts;
+                self.decode_error = None;
+            },
+            Err(e) => {
+                println!("payload could not be decoded: {}", decode_error_text(&e));
+                self.decode_error = Some(e);
+            },
+        }
+    }
+}
+
+/// Why transaction is not shown, in UI language, same as firmware words it
+pub fn decode_error_text(e: &DecodeError) -> String {
+    match e {
+        DecodeError::Metadata(_) => String::from(Text::MetadataDamaged.get()),
+        DecodeError::Call(reason) => Text::CallUndecodable.fill(&[reason]),
+        DecodeError::Extensions(reason) => Text::ExtensionsUndecodable.fill(&[reason]),
+        DecodeError::MetadataOutdated{in_metadata, in_transaction} => Text::MetadataOutdated.fill(&[in_metadata, in_transaction]),
+        DecodeError::NoSpecVersion => String::from(Text::NoSpecVersion.get()),
+    }
+}
+
+fn decode_transaction<F: FlashBus>(payload: &[u8], parts: &TransactionPayload, flash: &mut F) -> Result<(String, String, Vec<([u8; ACCOUNT_ID_LEN], String)>), DecodeError> {
+    let metadata = &payload[parts.metadata.clone()];
+    let checked_metadata_metal = CheckedMetadataMetal::from(&metadata, &mut ()).map_err(DecodeError::Metadata)?;
+    let specs = checked_metadata_metal.to_specs();
+    let spec_name = checked_metadata_metal.spec_name_version.spec_name.to_owned();
+
+    let genesis_hash = H256(payload[parts.genesis_hash.clone()].try_into().expect("static length"));
+    let decoded_extension = decode_extensions(&payload[parts.extension.clone()], &checked_metadata_metal, genesis_hash, &mut ())?;
+    let extension = decoded_extension
+        .iter()
+        .flat_map(|ext| ext.card(0, true, &specs, &spec_name))
+        .map(|card| card.show())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let decoded_call = decode_call(&payload[parts.call.clone()], &checked_metadata_metal, &mut ())?;
+    let mut unsaved_accounts = Vec::new();
+    let call = decoded_call
+        .card(0, &specs, &spec_name)
+        .iter()
+        .map(|card| show_card(flash, card, &genesis_hash.0, &mut unsaved_accounts))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    Ok((call, extension, unsaved_accounts))
+}
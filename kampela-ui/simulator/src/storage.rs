@@ -0,0 +1,171 @@
+//! Emulated flash, optionally kept in file between runs
+//!
+//! Settings and address book are kept in firmware layout, with firmware code. Seed record keeps
+//! firmware length byte, but entropy goes unencrypted, as there is no security element to wrap
+//! the key. Firmware keeps PIN only in memory, so simulator puts it on unused page.
+use clap::ValueEnum;
+use mnemonic_external::{regular::InternalWordList, AsWordList, WordSet};
+use std::{fmt, fs, path::PathBuf};
+
+use kampela_system::{
+    flash_map,
+    hal::{mock::MockFlash, FlashBus, FLASH_PAGE_SIZE},
+};
+use kampela_ui::platform::PinCode;
+
+/// Pages up to the end of address book; wordlist is not needed in simulator
+const FLASH_PAGES: usize = flash_map::ADDRESS_BOOK.end() as usize;
+
+const PIN_SET: u8 = 1;
+
+/// Mnemonic of well-known development account, never to be used with real funds
+const TEST_MNEMONIC: &str = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+/// Record that could be corrupted before start, to check recovery
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Record {
+    Seed,
+    Pin,
+    Settings,
+    /// First address book slot
+    Contact,
+}
+
+impl Record {
+    fn address(&self) -> u32 {
+        match self {
+            Record::Seed => flash_map::SEED.base(),
+            Record::Pin => flash_map::PIN.base(),
+            Record::Settings => flash_map::SETTINGS.base(),
+            Record::Contact => flash_map::ADDRESS_BOOK.base(),
+        }
+    }
+}
+
+pub struct EmulatedFlash {
+    flash: MockFlash,
+    file: Option<PathBuf>,
+}
+
+impl fmt::Debug for EmulatedFlash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmulatedFlash").field("file", &self.file).finish()
+    }
+}
+
+impl EmulatedFlash {
+    /// Flash from file, if given and exists, or erased flash; `fresh` erases it anyway
+    pub fn open(file: Option<PathBuf>, fresh: bool) -> Self {
+        let mut flash = MockFlash::new(FLASH_PAGES);
+        if let Some(ref path) = file {
+            if !fresh && path.exists() {
+                let data = fs::read(path).expect("flash file could not be read");
+                if data.len() != flash.data.len() {
+                    panic!("flash file {} has unexpected length {}, start with --fresh to recreate it", path.display(), data.len());
+                }
+                flash.data = data;
+                println!("emulated flash loaded from {}", path.display());
+            }
+        }
+        let out = Self { flash, file };
+        out.save();
+        out
+    }
+
+    fn save(&self) {
+        if let Some(ref path) = self.file {
+            fs::write(path, &self.flash.data).expect("flash file could not be written");
+        }
+    }
+
+    /// Invert record marker byte, as if flash got damaged
+    pub fn corrupt(&mut self, record: Record) {
+        self.flash.data[record.address() as usize] ^= 0xff;
+        self.save();
+        println!("{:?} record corrupted", record);
+    }
+
+    pub fn read_seed(&mut self) -> Option<Vec<u8>> {
+        let mut data = [0u8; FLASH_PAGE_SIZE];
+        if let Err(_) = self.read_data(flash_map::SEED.base(), &mut data) {
+            panic!("Failed to read seedphrase");
+        }
+        match data[0] {
+            0 => None,
+            len @ (16 | 20 | 24 | 28 | 32) => Some(data[1..1 + len as usize].to_vec()),
+            255 => None,
+            _ => {
+                self.erase_data(flash_map::SEED.base(), flash_map::SEED.count);
+                println!("Seed storage corrupted! Wiping seed...");
+                None
+            },
+        }
+    }
+
+    pub fn store_seed(&mut self, entropy: &[u8]) {
+        let mut data = [0xffu8; FLASH_PAGE_SIZE];
+        data[0] = entropy.len() as u8;
+        data[1..1 + entropy.len()].copy_from_slice(entropy);
+        if let Err(_) = self.store_data(flash_map::SEED.base(), &data) {
+            panic!("Failed to save seedphrase");
+        }
+    }
+
+    /// Store seed of [`TEST_MNEMONIC`]
+    pub fn store_test_seed(&mut self) {
+        let wordlist = InternalWordList;
+        let bits11_set = TEST_MNEMONIC
+            .split(' ')
+            .map(|word| wordlist.bits11_for_word(word).expect("test mnemonic words are in wordlist"))
+            .collect();
+        let entropy = WordSet{bits11_set}.to_entropy().expect("test mnemonic is valid");
+        self.store_seed(&entropy);
+        println!("test mnemonic stored: {}", TEST_MNEMONIC);
+    }
+
+    pub fn read_pin(&mut self) -> Option<PinCode> {
+        let mut data = [0u8; 1 + 4];
+        if let Err(_) = self.read_data(flash_map::PIN.base(), &mut data) {
+            panic!("Failed to read PIN");
+        }
+        match data[0] {
+            PIN_SET => Some(data[1..].try_into().expect("static length")),
+            _ => None,
+        }
+    }
+
+    pub fn store_pin(&mut self, pin: &PinCode) {
+        let mut data = [0xffu8; FLASH_PAGE_SIZE];
+        data[0] = PIN_SET;
+        data[1..1 + pin.len()].copy_from_slice(pin);
+        if let Err(_) = self.store_data(flash_map::PIN.base(), &data) {
+            panic!("Failed to save PIN");
+        }
+    }
+}
+
+/// Every change goes to file right away, as if device could lose power at any moment
+impl FlashBus for EmulatedFlash {
+    fn wake(&mut self) {
+        self.flash.wake();
+    }
+
+    fn sleep(&mut self) {
+        self.flash.sleep();
+    }
+
+    fn erase_page(&mut self, addr: u32) {
+        self.flash.erase_page(addr);
+        self.save();
+    }
+
+    fn write_page(&mut self, addr: u32, data: &[u8]) {
+        self.flash.write_page(addr, data);
+        self.save();
+    }
+
+    fn read_page(&mut self, addr: u32, data: &mut [u8; FLASH_PAGE_SIZE]) {
+        self.flash.read_page(addr, data);
+    }
+}
+
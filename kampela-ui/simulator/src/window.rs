@@ -0,0 +1,150 @@
+//! Desktop window, emulating e-paper refresh timing; needs SDL
+use embedded_graphics_core::{
+    primitives::PointsIter,
+    Drawable,
+    pixelcolor::BinaryColor,
+    Pixel,
+};
+use embedded_graphics_simulator::{OutputSettings, SimulatorDisplay, SimulatorEvent, Window};
+use std::{collections::VecDeque, thread::sleep, time::{Duration, Instant}};
+
+use kampela_ui::{
+    display_def::*,
+    refresh::RefreshPolicy,
+    uistate::{UIState, UpdateRequest, UpdateRequestMutate},
+};
+
+use crate::{energy::refresh_allowed, DesktopSimulator, HALHandle};
+
+/// Amount of time required for full screen update; debounce
+///  should be quite large as screen takes this much to clean
+const SLOW_UPDATE_TIME: Duration = Duration::new(1, 0);
+const BLINK_UPDATE_TIME: Duration = Duration::new(0, 5000000);
+const SLOW_UPDATE_ITER: usize = 8;
+const FAST_UPDATE_TIME: Duration = Duration::new(1, 0);
+const ULTRAFAST_UPDATE_TIME: Duration = Duration::new(1, 0);
+const UPDATE_DELAY_TIME: Duration = Duration::new(0, 500000000);
+
+const MAX_TOUCH_QUEUE: usize = 2;
+
+/// Run event loop in window until it is closed
+pub fn run(
+    state: &mut UIState<DesktopSimulator, SimulatorDisplay<BinaryColor>>,
+    h: &mut HALHandle,
+    output_settings: &OutputSettings,
+) {
+    let mut window = Window::new("Hello world", output_settings); //.show_static(&display);
+    
+    let mut update = Some(UpdateRequest::Slow);
+    let mut refresh_policy = RefreshPolicy::new();
+
+    let mut touches = VecDeque::new();
+
+    let mut last_iteration = Instant::now();
+
+    // event loop:
+    //
+    // 1. draw
+    // 2. collect input
+    // 3. handle input
+    // 4. do internal things
+    loop {
+        if let Some(ref mut energy) = state.platform.energy {
+            energy.charge(last_iteration.elapsed());
+        }
+        last_iteration = Instant::now();
+
+        // touch event
+        if let Some(point) = touches.pop_front() {
+            update.propagate(state.handle_tap(point, h));
+        };
+        // display event; it would be delayed, and held until capacitor charges
+        let important = state.is_important_screen();
+        if let Some(u) = update.take() {
+            update = Some(refresh_policy.adjust(u, important));
+        }
+        let pending = if refresh_allowed(&mut state.platform.energy, &update) { update.take() } else { None };
+        if let Some(u) = pending {
+            refresh_policy.record(&u, important);
+            sleep(UPDATE_DELAY_TIME);
+            let is_clear_update = matches!(u, UpdateRequest::Slow) || matches!(u, UpdateRequest::Fast);
+            match state.render(is_clear_update, h) {
+                Ok(a) => update.propagate(a),
+                Err(e) => println!("{:?}", e),
+            };
+
+            match u {
+                UpdateRequest::Hidden => {
+                    window.update(&state.display);
+                    println!("skip {} events in hidden update", window.events().count());
+                },
+                UpdateRequest::Slow => {
+                    invert_display(&mut state.display);
+                    window.update(&state.display);
+                    sleep(SLOW_UPDATE_TIME);
+                    invert_display(&mut state.display);
+                    window.update(&state.display);
+                    for _i in 0..SLOW_UPDATE_ITER {
+                        invert_display(&mut state.display);
+                        window.update(&state.display);
+                        sleep(BLINK_UPDATE_TIME);
+                        invert_display(&mut state.display);
+                        window.update(&state.display);
+                        sleep(BLINK_UPDATE_TIME);
+                    }
+
+                    window.update(&state.display);
+                    println!("skip {} events in slow update", window.events().count());
+                },
+                UpdateRequest::Fast => {
+                    invert_display(&mut state.display);
+                    window.update(&state.display);
+                    sleep(FAST_UPDATE_TIME);
+                    invert_display(&mut state.display);
+                    window.update(&state.display);
+                    println!("fast update");
+                },
+                UpdateRequest::UltraFast => {
+                    window.update(&state.display);
+                    println!("ultrafast update");
+                    sleep(ULTRAFAST_UPDATE_TIME);
+                },
+                UpdateRequest::Part(a) => {
+                    window.update(&state.display);
+                    println!("part update of area {:?}", a);
+                    sleep(ULTRAFAST_UPDATE_TIME);
+                },
+            }
+        }
+        // this collects ui events, do not remove or simulator will crash
+        window.update(&state.display);
+
+        // register input (only pushes are valid in Kampela)
+        for event in window.events() {
+            match event {
+                SimulatorEvent::MouseButtonDown {
+                    mouse_btn: _,
+                    point,
+                } => {
+                    if touches.len() < MAX_TOUCH_QUEUE {
+                        touches.push_back(point);
+                        println!("point {} registered", point);
+                    } else {
+                        println!("point {} omitted", point);
+                    }
+                }
+                SimulatorEvent::Quit => return,
+                _ => (),
+            }
+        }
+
+        //and here is some loop time for other things
+    }
+}
+
+fn invert_display(display: &mut SimulatorDisplay<BinaryColor>) {
+    for point in SCREEN_AREA.points() {
+        let dot = Pixel::<BinaryColor>(point, display.get_pixel(point).invert());
+        dot.draw(display).unwrap();
+    };
+}
\ No newline at end of file
@@ -11,7 +11,7 @@ use embedded_graphics::{
     geometry::{Point, Size},
     pixelcolor::BinaryColor,
     mono_font::{
-        ascii::FONT_8X13_BOLD,
+        iso_8859_1::FONT_8X13_BOLD,
         MonoTextStyle,
     },
     primitives::Rectangle,
@@ -24,7 +24,7 @@ use embedded_text::{
 };
 use mnemonic_external::{AsWordList, Bits11, WordListElement, WordSet};
 
-use crate::{display_def::*, message, platform::Platform, uistate::UnitScreen, widget::nav_bar::nav_bar::NavCommand};
+use crate::{display_def::*, locale::Text, message, platform::Platform, uistate::UnitScreen, widget::nav_bar::nav_bar::NavCommand};
 
 use crate::widget::{view::{ViewScreen, View, Widget}, nav_bar::nav_bar::{NavBar, NAV_BAR_WIDGET}};
 
@@ -97,7 +97,7 @@ impl<P: Platform> Backup<P> {
         Backup {
             state,
             phrase,
-            navbar: NavBar::new(("back", "store")),
+            navbar: NavBar::new((Text::Back.get(), Text::Store.get())),
             prev_screen,
             platform_type: PhantomData::<P>::default(),
         }
@@ -122,7 +122,7 @@ impl<P: Platform> Backup<P> {
             .build();
     
         TextBox::with_textbox_style(
-            "Please write down seed phrase",
+            Text::WriteDownSeed.get(),
             HEADER_WIDGET.bounds,
             character_style,
             textbox_style
@@ -159,14 +159,14 @@ impl<P: Platform> ViewScreen for Backup<P> {
                 self.draw_backup_screen(target)?;
             },
             BackupState::Message => {
-                message::draw(target, "Storing into flash...", true)?;
+                message::draw(target, Text::StoringSeed.get(), true)?;
                 request = Some(UpdateRequest::Hidden);
                 self.state = BackupState::Storing;
             },
             BackupState::Error => {
                 message::draw(
                     target,
-                    "System error! Seed storage corrupted; if this persists, please destroy the device",
+                    Text::SeedStorageCorrupted.get(),
                     true
                 )?;
             },
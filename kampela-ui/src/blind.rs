@@ -0,0 +1,159 @@
+#[cfg(not(feature="std"))]
+use alloc::{string::String, boxed::Box, format};
+#[cfg(feature="std")]
+use std::{string::String, boxed::Box, format};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    pixelcolor::BinaryColor,
+    geometry::{Point, Size},
+    mono_font::{
+        iso_8859_1::{FONT_6X10, FONT_8X13_BOLD},
+        MonoTextStyle,
+    },
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    Drawable
+};
+
+use embedded_text::{
+    alignment::{HorizontalAlignment, VerticalAlignment},
+    style::{HeightMode, TextBoxStyleBuilder},
+    TextBox,
+};
+
+use crate::display_def::*;
+use crate::locale::Text;
+use crate::widget::{nav_bar::nav_bar::{NavBar, NavCommand, NAV_BAR_WIDGET}, view::{View, ViewScreen, Widget}};
+use crate::uistate::{EventResult, UpdateRequest, UnitScreen};
+
+const WARNING_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: SCREEN_ZERO,
+        size: Size{
+            width: SCREEN_SIZE_X,
+            height: 18,
+        }
+    },
+    SCREEN_ZERO
+);
+
+const BODY_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: Point{
+            x: GAP as i32,
+            y: WARNING_WIDGET.bounds.size.height as i32 + GAP as i32,
+        },
+        size: Size{
+            width: SCREEN_SIZE_X - GAP * 2,
+            height: SCREEN_SIZE_Y - WARNING_WIDGET.bounds.size.height - NAV_BAR_WIDGET.bounds.size.height - GAP,
+        }
+    },
+    SCREEN_ZERO
+);
+
+/// Hex characters of hash shown in one line
+const HASH_LINE_LEN: usize = 32;
+
+/// Transaction that could not be decoded; only payload hash is shown
+pub struct BlindTransaction {
+    navbar: NavBar,
+}
+
+impl BlindTransaction {
+    pub fn new() -> Self {
+        BlindTransaction {
+            navbar: NavBar::new((Text::Reject.get(), Text::Sign.get())),
+        }
+    }
+}
+
+impl ViewScreen for BlindTransaction {
+    type DrawInput<'a> = (String, [u8; 32]);
+    type DrawOutput = ();
+    type TapInput<'a> = bool;
+    type TapOutput = ();
+
+    fn draw_screen<'a, D>(&mut self, target: &mut D, (reason, hash): Self::DrawInput<'a>) -> Result<(EventResult, ()), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        Self: 'a,
+    {
+        let state = None;
+        let request = None;
+
+        let area = target.bounding_box();
+        area.into_styled(PrimitiveStyle::with_fill(BinaryColor::Off)).draw(target)?;
+
+        WARNING_WIDGET.bounds.into_styled(PrimitiveStyle::with_fill(BinaryColor::On)).draw(target)?;
+        TextBox::with_textbox_style(
+            Text::CannotDecode.get(),
+            WARNING_WIDGET.bounds,
+            MonoTextStyle::new(&FONT_8X13_BOLD, BinaryColor::Off),
+            TextBoxStyleBuilder::new()
+                .alignment(HorizontalAlignment::Center)
+                .vertical_alignment(VerticalAlignment::Middle)
+                .build(),
+        ).draw(target)?;
+
+        let hash = hex::encode(hash);
+        let content = format!(
+            "{}\n\n{}\n{}\n{}",
+            reason,
+            Text::PayloadHash.get(),
+            &hash[..HASH_LINE_LEN],
+            &hash[HASH_LINE_LEN..],
+        );
+        TextBox::with_textbox_style(
+            &content,
+            BODY_WIDGET.bounds,
+            MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+            TextBoxStyleBuilder::new()
+                .height_mode(HeightMode::FitToText)
+                .alignment(HorizontalAlignment::Left)
+                .build(),
+        ).draw(target)?;
+
+        self.navbar.draw(target, false)?;
+        Ok((EventResult{state, request}, ()))
+    }
+
+    fn handle_tap_screen<'a>(&mut self, point: Point, allow_blind_signing: bool) -> (EventResult, ())
+    where
+        Self: 'a
+    {
+        let mut state = None;
+        let mut request = None;
+
+        if let Some(Some(c)) = self.navbar.handle_tap(point, ()) {
+            match c {
+                NavCommand::Left => {
+                    state = Some(UnitScreen::QRAddress);
+                    request = Some(UpdateRequest::Slow);
+                },
+                NavCommand::Right => {
+                    if allow_blind_signing {
+                        state = Some(UnitScreen::ShowDialog(
+                            Text::SignBlindly.get(),
+                            (Text::No.get(), Text::Yes.get()),
+                            (
+                                Box::new(|| EventResult {
+                                    request: Some(UpdateRequest::UltraFast),
+                                    state: Some(UnitScreen::ShowBlindTransaction)
+                                }),
+                                Box::new(|| EventResult {
+                                    request: Some(UpdateRequest::UltraFast),
+                                    state: Some(UnitScreen::QRSignature)
+                                }),
+                            ),
+                            true
+                        ));
+                    } else {
+                        state = Some(UnitScreen::ShowMessage(String::from(Text::BlindSigningForbidden.get())));
+                    }
+                    request = Some(UpdateRequest::UltraFast);
+                },
+            }
+        }
+        (EventResult{state, request}, ())
+    }
+}
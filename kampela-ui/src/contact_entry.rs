@@ -0,0 +1,193 @@
+#[cfg(not(feature="std"))]
+use alloc::string::String;
+#[cfg(feature="std")]
+use std::string::String;
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    pixelcolor::BinaryColor,
+    geometry::{Point, Size},
+    mono_font::{
+        iso_8859_1::{FONT_6X10, FONT_10X20},
+        MonoTextStyle,
+    },
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    Drawable
+};
+
+use embedded_text::{
+    alignment::{HorizontalAlignment, VerticalAlignment},
+    style::TextBoxStyleBuilder,
+    TextBox,
+};
+
+use crate::display_def::*;
+use crate::locale::Text;
+use crate::platform::{UnsavedAccount, ACCOUNT_ID_LEN};
+use crate::seed_entry::{key::Key, keyboard::{Keyboard, KEYBOARD_AREA, REMOVE_KEY_WIDGET}};
+use crate::transaction::TransactionPage;
+use crate::widget::{nav_bar::nav_bar::{NavBar, NavCommand}, view::{View, ViewScreen, Widget}};
+use crate::uistate::{EventResult, UpdateRequest, UnitScreen};
+
+/// Longest label that fits on screen
+pub const LABEL_MAX_LEN: usize = 24;
+
+const TITLE_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: SCREEN_ZERO,
+        size: Size{
+            width: SCREEN_SIZE_X,
+            height: 16,
+        }
+    },
+    SCREEN_ZERO
+);
+
+/// Account being labelled, two lines of small font
+const ADDRESS_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: Point{
+            x: 0,
+            y: TITLE_WIDGET.bounds.size.height as i32,
+        },
+        size: Size{
+            width: SCREEN_SIZE_X,
+            height: 22,
+        }
+    },
+    SCREEN_ZERO
+);
+
+const LABEL_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: Point{
+            x: 0,
+            y: ADDRESS_WIDGET.bounds.top_left.y + ADDRESS_WIDGET.bounds.size.height as i32,
+        },
+        size: Size{
+            width: SCREEN_SIZE_X,
+            height: KEYBOARD_AREA.top_left.y as u32 - TITLE_WIDGET.bounds.size.height - ADDRESS_WIDGET.bounds.size.height,
+        }
+    },
+    SCREEN_ZERO
+);
+
+/// Label entry for account saved from reviewed transaction
+pub struct ContactEntry {
+    account: UnsavedAccount,
+    label: String,
+    keyboard: Keyboard,
+    remove: Key,
+    navbar: NavBar,
+}
+
+impl ContactEntry {
+    pub fn new(account: UnsavedAccount) -> Self {
+        ContactEntry {
+            account,
+            label: String::new(),
+            keyboard: Keyboard::new(),
+            remove: Key::new(Text::Delete.get(), &REMOVE_KEY_WIDGET),
+            navbar: NavBar::new((Text::Cancel.get(), "")),
+        }
+    }
+
+    fn update_navbar(&mut self) {
+        if self.label.is_empty() {
+            self.navbar = NavBar::new((Text::Cancel.get(), ""))
+        } else {
+            self.navbar = NavBar::new((Text::Cancel.get(), Text::Save.get()))
+        }
+    }
+}
+
+impl ViewScreen for ContactEntry {
+    type DrawInput<'a> = ();
+    type DrawOutput = ();
+    type TapInput<'a> = ();
+    /// Account and label entered by user, once confirmed
+    type TapOutput = Option<([u8; ACCOUNT_ID_LEN], String)>;
+
+    fn draw_screen<'a, D>(&mut self, target: &mut D, _: ()) -> Result<(EventResult, ()), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        Self: 'a,
+    {
+        let state = None;
+        let request = None;
+
+        let area = target.bounding_box();
+        area.into_styled(PrimitiveStyle::with_fill(BinaryColor::Off)).draw(target)?;
+
+        let centered = TextBoxStyleBuilder::new()
+            .alignment(HorizontalAlignment::Center)
+            .vertical_alignment(VerticalAlignment::Middle)
+            .build();
+        TextBox::with_textbox_style(
+            Text::RecipientLabel.get(),
+            TITLE_WIDGET.bounds,
+            MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+            centered,
+        ).draw(target)?;
+        TextBox::with_textbox_style(
+            &self.account.address,
+            ADDRESS_WIDGET.bounds,
+            MonoTextStyle::new(&FONT_6X10, BinaryColor::On),
+            centered,
+        ).draw(target)?;
+        TextBox::with_textbox_style(
+            &self.label,
+            LABEL_WIDGET.bounds,
+            MonoTextStyle::new(&FONT_10X20, BinaryColor::On),
+            centered,
+        ).draw(target)?;
+
+        self.remove.draw(target, false)?;
+        self.keyboard.draw(target, false)?;
+        self.navbar.draw(target, false)?;
+        Ok((EventResult{state, request}, ()))
+    }
+
+    fn handle_tap_screen<'a>(&mut self, point: Point, _: ()) -> (EventResult, Self::TapOutput)
+    where
+        Self: 'a
+    {
+        let mut state = None;
+        let mut request = None;
+        let mut label = None;
+
+        if let Some(Some(c)) = self.keyboard.handle_tap(point, ()) {
+            if self.label.len() < LABEL_MAX_LEN {
+                // first letter is capital, as names usually are
+                if self.label.is_empty() {
+                    self.label.push(c[0].to_ascii_uppercase());
+                } else {
+                    self.label.push(c[0]);
+                }
+                self.update_navbar();
+                request = Some(UpdateRequest::UltraFast);
+            }
+        }
+        if self.remove.handle_tap(point, ()).is_some() {
+            self.label.pop();
+            self.update_navbar();
+            request = Some(UpdateRequest::UltraFast);
+        }
+        if let Some(Some(c)) = self.navbar.handle_tap(point, ()) {
+            match c {
+                NavCommand::Left => {
+                    state = Some(UnitScreen::ShowTransaction(TransactionPage::Call));
+                    request = Some(UpdateRequest::Fast);
+                },
+                NavCommand::Right => {
+                    if !self.label.is_empty() {
+                        label = Some((self.account.account_id, core::mem::take(&mut self.label)));
+                        state = Some(UnitScreen::ShowTransaction(TransactionPage::Call));
+                        request = Some(UpdateRequest::Fast);
+                    }
+                },
+            }
+        }
+        (EventResult{state, request}, label)
+    }
+}
@@ -5,7 +5,7 @@ use std::boxed::Box;
 
 use embedded_graphics::{
     mono_font::{
-        ascii::FONT_10X20,
+        iso_8859_1::FONT_10X20,
         MonoTextStyle,
     },
     prelude::{Point, Size, DrawTarget},
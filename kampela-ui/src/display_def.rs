@@ -4,7 +4,7 @@ use embedded_graphics::{
     },
     geometry::{Point, Size},
 };
-pub use kampela_display_common::display_def::*;
+pub use kampela_display_common::{display_def::*, orientation::Orientation};
 
 pub const GAP: u32 = 4;
 
@@ -0,0 +1,209 @@
+//! Text in proportional font, for content that ASCII mono fonts could not show
+//!
+//! Characters missing from font are drawn as `?`; if font has no `?` either, they are skipped.
+
+#[cfg(not(feature="std"))]
+use alloc::vec::Vec;
+#[cfg(feature="std")]
+use std::vec::Vec;
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::Point,
+    pixelcolor::BinaryColor,
+    primitives::Rectangle,
+    Pixel,
+};
+
+pub use kampela_display_common::font::*;
+
+const REPLACEMENT: char = '?';
+
+fn glyph_or_replacement<S: FontStorage>(font: &Font<S>, c: char) -> Option<Glyph> {
+    font.glyph(c).or_else(|| font.glyph(REPLACEMENT))
+}
+
+fn char_advance<S: FontStorage>(font: &Font<S>, c: char) -> u32 {
+    glyph_or_replacement(font, c).map(|glyph| glyph.advance as u32).unwrap_or(0)
+}
+
+/// Width of single line of text
+pub fn text_width<S: FontStorage>(font: &Font<S>, text: &str) -> u32 {
+    text.chars().map(|c| char_advance(font, c)).sum()
+}
+
+fn draw_glyph<S, D>(target: &mut D, font: &Font<S>, glyph: &Glyph, pen: Point, color: BinaryColor) -> Result<(), D::Error>
+where
+    S: FontStorage,
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut row_data = [0u8; MAX_ROW_LEN];
+    let left = pen.x + glyph.x_offset as i32;
+    for row in 0..glyph.height {
+        font.read_row(glyph, row, &mut row_data);
+        let y = pen.y + glyph.y_offset as i32 + row as i32;
+        let pixels = (0..glyph.width as usize)
+            .filter(|col| row_data[col / 8] & (0x80 >> (col % 8)) != 0)
+            .map(|col| Pixel(Point::new(left + col as i32, y), color));
+        target.draw_iter(pixels)?;
+    }
+    Ok(())
+}
+
+/// Draw single line of text from its top left corner; returns pen position after it
+pub fn draw_line<S, D>(target: &mut D, font: &Font<S>, text: &str, top_left: Point, color: BinaryColor) -> Result<Point, D::Error>
+where
+    S: FontStorage,
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut pen = top_left;
+    for c in text.chars() {
+        if let Some(glyph) = glyph_or_replacement(font, c) {
+            draw_glyph(target, font, &glyph, pen, color)?;
+            pen.x += glyph.advance as i32;
+        }
+    }
+    Ok(pen)
+}
+
+/// Split paragraph into lines not wider than `width`, at spaces where possible
+fn wrap<'a, S: FontStorage>(font: &Font<S>, paragraph: &'a str, width: u32) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut line_width = 0;
+    // last space in line: its index, and where next line would start
+    let mut break_at: Option<(usize, usize)> = None;
+    for (i, c) in paragraph.char_indices() {
+        let advance = char_advance(font, c);
+        if i > start && line_width + advance > width {
+            // space at line end is dropped
+            if c == ' ' {
+                lines.push(&paragraph[start..i]);
+                start = i + 1;
+                break_at = None;
+                line_width = 0;
+                continue;
+            }
+            let (end, next) = match break_at {
+                Some(space) if space.0 > start => space,
+                _ => (i, i),
+            };
+            lines.push(&paragraph[start..end]);
+            start = next;
+            break_at = None;
+            line_width = text_width(font, &paragraph[start..i]);
+        }
+        if c == ' ' {
+            break_at = Some((i, i + 1));
+        }
+        line_width += advance;
+    }
+    lines.push(&paragraph[start..]);
+    lines
+}
+
+/// Draw text wrapped within bounds; lines that do not fit in bounds are not drawn
+///
+/// Each `\n` starts new paragraph, with `paragraph_spacing` above it. Returns height of text
+/// drawn.
+pub fn draw_text_box<S, D>(target: &mut D, font: &Font<S>, text: &str, bounds: Rectangle, color: BinaryColor, paragraph_spacing: u32) -> Result<u32, D::Error>
+where
+    S: FontStorage,
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let mut y = 0;
+    for (n, paragraph) in text.split('\n').enumerate() {
+        if n != 0 {
+            y += paragraph_spacing;
+        }
+        for line in wrap(font, paragraph, bounds.size.width) {
+            if y + font.line_height() > bounds.size.height {
+                return Ok(y)
+            }
+            draw_line(target, font, line, bounds.top_left + Point::new(0, y as i32), color)?;
+            y += font.line_height();
+        }
+    }
+    Ok(y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use embedded_graphics::{geometry::Size, mock_display::MockDisplay};
+
+    const ADVANCE: u32 = 4;
+    const LINE_HEIGHT: u32 = 3;
+
+    /// Every glyph is one pixel at pen, except blank space
+    fn image() -> Vec<u8> {
+        let chars = [' ', '?', 'a', 'b'];
+        let header = FontHeader { line_height: LINE_HEIGHT as u8, ascent: 2, glyph_count: chars.len() as u16 };
+        let bitmaps_start = HEADER_LEN + chars.len() * INDEX_ENTRY_LEN;
+        let mut image = header.encode().to_vec();
+        for (i, c) in chars.iter().enumerate() {
+            let glyph = Glyph { codepoint: *c as u32, advance: ADVANCE as u8, width: 1, height: 1, x_offset: 0, y_offset: 0, bitmap: (bitmaps_start + i) as u32 };
+            image.extend_from_slice(&glyph.encode());
+        }
+        image.extend(chars.iter().map(|c| if *c == ' ' { 0 } else { 0x80 }));
+        image
+    }
+
+    #[test]
+    fn width_counts_replacement() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        assert_eq!(text_width(&font, "ab a"), 4 * ADVANCE);
+        assert_eq!(text_width(&font, "xé"), 2 * ADVANCE);
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        assert_eq!(wrap(&font, "aa bb", 5 * ADVANCE), ["aa bb"]);
+        assert_eq!(wrap(&font, "aa bb", 4 * ADVANCE), ["aa", "bb"]);
+        assert_eq!(wrap(&font, "a b ab", 3 * ADVANCE), ["a b", "ab"]);
+        assert_eq!(wrap(&font, "", ADVANCE), [""]);
+    }
+
+    #[test]
+    fn wraps_long_words_anywhere() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        assert_eq!(wrap(&font, "aaaaa", 2 * ADVANCE), ["aa", "aa", "a"]);
+        assert_eq!(wrap(&font, "b aaaaa", 2 * ADVANCE), ["b", "aa", "aa", "a"]);
+        // narrower than one glyph: still one glyph per line
+        assert_eq!(wrap(&font, "ab", 1), ["a", "b"]);
+    }
+
+    #[test]
+    fn line_is_drawn_glyph_by_glyph() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        let mut display = MockDisplay::new();
+        let pen = draw_line(&mut display, &font, "a b", Point::new(1, 2), BinaryColor::On).unwrap();
+        assert_eq!(pen, Point::new(1 + 3 * ADVANCE as i32, 2));
+        assert_eq!(display.get_pixel(Point::new(1, 2)), Some(BinaryColor::On));
+        assert_eq!(display.get_pixel(Point::new(1 + ADVANCE as i32, 2)), None);
+        assert_eq!(display.get_pixel(Point::new(1 + 2 * ADVANCE as i32, 2)), Some(BinaryColor::On));
+    }
+
+    #[test]
+    fn text_box_keeps_to_bounds() {
+        let image = image();
+        let font = Font::new(&image[..]).unwrap();
+        let bounds = Rectangle::new(Point::zero(), Size::new(2 * ADVANCE, 2 * LINE_HEIGHT));
+        let mut display = MockDisplay::new();
+        assert_eq!(draw_text_box(&mut display, &font, "aa bb", bounds, BinaryColor::On, 1).unwrap(), 2 * LINE_HEIGHT);
+        // third line does not fit
+        let mut display = MockDisplay::new();
+        assert_eq!(draw_text_box(&mut display, &font, "aa bb ab", bounds, BinaryColor::On, 1).unwrap(), 2 * LINE_HEIGHT);
+        assert_eq!(display.get_pixel(Point::new(0, 2 * LINE_HEIGHT as i32)), None);
+        // paragraph spacing
+        let tall = Rectangle::new(Point::zero(), Size::new(2 * ADVANCE, 4 * LINE_HEIGHT));
+        let mut display = MockDisplay::new();
+        assert_eq!(draw_text_box(&mut display, &font, "a\nb", tall, BinaryColor::On, 1).unwrap(), 2 * LINE_HEIGHT + 1);
+    }
+}
@@ -3,8 +3,10 @@
 
 pub mod uistate;
 pub mod platform;
+pub mod refresh;
 pub mod widget{
     pub mod view;
+    pub mod list;
     pub mod nav_bar{
         pub mod nav_bar;
         pub mod nav_button;
@@ -12,6 +14,8 @@ pub mod widget{
 }
 
 pub mod display_def;
+pub mod font;
+pub mod locale;
 pub mod pin{
     pub mod pin;
     pub mod pindots;
@@ -33,6 +37,9 @@ mod message;
 mod dialog;
 
 pub mod transaction;
+pub mod blind;
+pub mod contact_entry;
+pub mod settings;
 pub mod qr;
 
 #[macro_use]
@@ -0,0 +1,341 @@
+//! User-facing strings, by message id, in every supported language
+//!
+//! Each language is a `match` over all of [`Text`], so a message without translation does not
+//! compile. Strings are drawn with ISO 8859-1 mono fonts, so translations keep to Latin-1;
+//! navigation button labels fit in 9 characters.
+
+#[cfg(not(feature="std"))]
+use alloc::string::String;
+#[cfg(feature="std")]
+use std::string::String;
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+pub use kampela_display_common::language::Language;
+
+/// Placeholder in templates, filled by [`Text::fill`]
+const PLACEHOLDER: &str = "{}";
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+/// Language of strings from [`Text::get`]; set from platform settings on start and on change
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language.code(), Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::from_code(LANGUAGE.load(Ordering::Relaxed))
+}
+
+/// Message id
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Text {
+    // navigation and dialog options
+    Back,
+    Cancel,
+    Clear,
+    Disable,
+    Enable,
+    Generate,
+    Next,
+    No,
+    Previous,
+    Reject,
+    Restore,
+    Save,
+    Sign,
+    Store,
+    Yes,
+    Settings,
+    /// Keyboard key removing last character
+    Delete,
+
+    // onboarding
+    RestoreOrGenerate,
+    WriteDownSeed,
+    StoringSeed,
+    SeedStorageCorrupted,
+    DiscardEntry,
+    TapToContinue,
+
+    // pin
+    PinWrong,
+    PinOk,
+
+    // transaction
+    Signing,
+    SignTransaction,
+    UnknownNetwork,
+    /// Template: version in metadata, version needed by transaction
+    MetadataOutdated,
+    CannotDecode,
+    PayloadHash,
+    SignBlindly,
+    BlindSigningForbidden,
+    RecipientLabel,
+    MetadataDamaged,
+    /// Template: parser error, untranslated
+    CallUndecodable,
+    /// Template: parser error, untranslated
+    ExtensionsUndecodable,
+    NoSpecVersion,
+
+    // settings
+    BlindSigningEnabled,
+    BlindSigningDisabled,
+    /// Template: language name
+    LanguageSetting,
+    /// Template: rotation angle
+    OrientationSetting,
+
+    // received over NFC
+    InvalidSenderAddress,
+    InvalidNfcPacket,
+    InvalidTransaction,
+    InvalidNetworkSpecs,
+    InvalidContact,
+    UpdateSignatureInvalid,
+    NoVerifier,
+    UpdateDamaged,
+    UnknownVerifier,
+    ReceivingNfc,
+    /// Template: contact label
+    ContactAdded,
+    AddressBookFull,
+    ContactTooLarge,
+    ContactSaveFailed,
+    /// Template: network name
+    NetworkAdded,
+    NetworkRegistryFull,
+    NetworkTooLarge,
+    NetworkSaveFailed,
+    BuiltinNetwork,
+
+    /// Template: error id, module, line
+    RestartedAfterError,
+}
+
+impl Text {
+    /// Every message id, for checks over all translations
+    pub const ALL: [Text; 62] = [
+        Text::Back, Text::Cancel, Text::Clear, Text::Disable, Text::Enable, Text::Generate,
+        Text::Next, Text::No, Text::Previous, Text::Reject, Text::Restore, Text::Save, Text::Sign,
+        Text::Store, Text::Yes, Text::Settings, Text::Delete, Text::RestoreOrGenerate,
+        Text::WriteDownSeed, Text::StoringSeed, Text::SeedStorageCorrupted, Text::DiscardEntry,
+        Text::TapToContinue, Text::PinWrong, Text::PinOk, Text::Signing, Text::SignTransaction,
+        Text::UnknownNetwork, Text::MetadataOutdated, Text::CannotDecode, Text::PayloadHash,
+        Text::SignBlindly, Text::BlindSigningForbidden, Text::RecipientLabel, Text::MetadataDamaged,
+        Text::CallUndecodable, Text::ExtensionsUndecodable, Text::NoSpecVersion,
+        Text::BlindSigningEnabled, Text::BlindSigningDisabled, Text::LanguageSetting,
+        Text::OrientationSetting, Text::InvalidSenderAddress, Text::InvalidNfcPacket,
+        Text::InvalidTransaction, Text::InvalidNetworkSpecs, Text::InvalidContact,
+        Text::UpdateSignatureInvalid, Text::NoVerifier, Text::UpdateDamaged, Text::UnknownVerifier,
+        Text::ReceivingNfc, Text::ContactAdded, Text::AddressBookFull, Text::ContactTooLarge,
+        Text::ContactSaveFailed, Text::NetworkAdded, Text::NetworkRegistryFull,
+        Text::NetworkTooLarge, Text::NetworkSaveFailed, Text::BuiltinNetwork,
+        Text::RestartedAfterError,
+    ];
+
+    /// String in current language
+    pub fn get(self) -> &'static str {
+        self.translate(language())
+    }
+
+    pub fn translate(self, language: Language) -> &'static str {
+        match language {
+            Language::English => english(self),
+            Language::Spanish => spanish(self),
+        }
+    }
+
+    /// Template in current language with each `{}` replaced by next of `args`
+    pub fn fill(self, args: &[&str]) -> String {
+        let mut out = String::new();
+        let mut args = args.iter();
+        let mut parts = self.get().split(PLACEHOLDER).peekable();
+        while let Some(part) = parts.next() {
+            out.push_str(part);
+            if parts.peek().is_some() {
+                out.push_str(args.next().unwrap_or(&""));
+            }
+        }
+        out
+    }
+}
+
+fn english(text: Text) -> &'static str {
+    match text {
+        Text::Back => "back",
+        Text::Cancel => "cancel",
+        Text::Clear => "clear",
+        Text::Disable => "disable",
+        Text::Enable => "enable",
+        Text::Generate => "generate",
+        Text::Next => "next",
+        Text::No => "no",
+        Text::Previous => "previous",
+        Text::Reject => "reject",
+        Text::Restore => "restore",
+        Text::Save => "save",
+        Text::Sign => "sign",
+        Text::Store => "store",
+        Text::Yes => "yes",
+        Text::Settings => "settings",
+        Text::Delete => "DEL",
+
+        Text::RestoreOrGenerate => "restore or generate?",
+        Text::WriteDownSeed => "Please write down seed phrase",
+        Text::StoringSeed => "Storing into flash...",
+        Text::SeedStorageCorrupted => "System error! Seed storage corrupted; if this persists, please destroy the device",
+        Text::DiscardEntry => "Are you sure?\nEntered data will be lost",
+        Text::TapToContinue => "tap to continue",
+
+        Text::PinWrong => "Pin is wrong",
+        Text::PinOk => "Pin is Ok",
+
+        Text::Signing => "Signing...",
+        Text::SignTransaction => "Sign the transaction?",
+        Text::UnknownNetwork => "UNKNOWN NETWORK",
+        Text::MetadataOutdated => "Metadata outdated\nv{} loaded\nv{} needed",
+        Text::CannotDecode => "CANNOT DECODE",
+        Text::PayloadHash => "Payload hash:",
+        Text::SignBlindly => "Sign without seeing the content?",
+        Text::BlindSigningForbidden => "Blind signing is disabled in settings",
+        Text::RecipientLabel => "Label for recipient",
+        Text::MetadataDamaged => "Received metadata is damaged.",
+        Text::CallUndecodable => "Call could not be decoded. {}",
+        Text::ExtensionsUndecodable => "Extensions could not be decoded. {}",
+        Text::NoSpecVersion => "Extensions contain no spec version.",
+
+        Text::BlindSigningEnabled => "Blind signing:\nenabled",
+        Text::BlindSigningDisabled => "Blind signing:\ndisabled",
+        Text::LanguageSetting => "Language:\n{}",
+        Text::OrientationSetting => "Screen rotation:\n{}°",
+
+        Text::InvalidSenderAddress => "Invalid sender address",
+        Text::InvalidNfcPacket => "NFC transfer is damaged",
+        Text::InvalidTransaction => "Invalid transaction",
+        Text::InvalidNetworkSpecs => "Invalid network specs",
+        Text::InvalidContact => "Invalid contact",
+        Text::UpdateSignatureInvalid => "Update signature is invalid",
+        Text::NoVerifier => "Updates are not accepted: no verifier key in firmware",
+        Text::UpdateDamaged => "Update is damaged",
+        Text::UnknownVerifier => "Update signed by unknown verifier",
+        Text::ReceivingNfc => "Receiving NFC packets...",
+        Text::ContactAdded => "Contact {} added",
+        Text::AddressBookFull => "Address book is full",
+        Text::ContactTooLarge => "Contact is too large",
+        Text::ContactSaveFailed => "Failed to save contact",
+        Text::NetworkAdded => "Network {} added",
+        Text::NetworkRegistryFull => "Network registry is full",
+        Text::NetworkTooLarge => "Network specs are too large",
+        Text::NetworkSaveFailed => "Failed to save network",
+        Text::BuiltinNetwork => "Built-in network could not be replaced",
+
+        Text::RestartedAfterError => "Restarted after error {} at {}:{}",
+    }
+}
+
+fn spanish(text: Text) -> &'static str {
+    match text {
+        Text::Back => "atrás",
+        Text::Cancel => "cancelar",
+        Text::Clear => "borrar",
+        Text::Disable => "apagar",
+        Text::Enable => "encender",
+        Text::Generate => "generar",
+        Text::Next => "siguiente",
+        Text::No => "no",
+        Text::Previous => "anterior",
+        Text::Reject => "rechazar",
+        Text::Restore => "restaurar",
+        Text::Save => "guardar",
+        Text::Sign => "firmar",
+        Text::Store => "guardar",
+        Text::Yes => "sí",
+        Text::Settings => "ajustes",
+        Text::Delete => "BOR",
+
+        Text::RestoreOrGenerate => "¿restaurar o generar?",
+        Text::WriteDownSeed => "Anote la frase semilla",
+        Text::StoringSeed => "Guardando en flash...",
+        Text::SeedStorageCorrupted => "¡Error del sistema! Semilla guardada dañada; si persiste, destruya el dispositivo",
+        Text::DiscardEntry => "¿Seguro?\nLos datos introducidos se perderán",
+        Text::TapToContinue => "toque para continuar",
+
+        Text::PinWrong => "PIN incorrecto",
+        Text::PinOk => "PIN correcto",
+
+        Text::Signing => "Firmando...",
+        Text::SignTransaction => "¿Firmar la transacción?",
+        Text::UnknownNetwork => "RED DESCONOCIDA",
+        Text::MetadataOutdated => "Metadatos obsoletos\nv{} cargada\nv{} necesaria",
+        Text::CannotDecode => "NO DECODIFICABLE",
+        Text::PayloadHash => "Hash del contenido:",
+        Text::SignBlindly => "¿Firmar sin ver el contenido?",
+        Text::BlindSigningForbidden => "La firma a ciegas está desactivada en ajustes",
+        Text::RecipientLabel => "Etiqueta del destinatario",
+        Text::MetadataDamaged => "Los metadatos recibidos están dañados.",
+        Text::CallUndecodable => "No se pudo decodificar la llamada. {}",
+        Text::ExtensionsUndecodable => "No se pudieron decodificar las extensiones. {}",
+        Text::NoSpecVersion => "Las extensiones no contienen versión de especificación.",
+
+        Text::BlindSigningEnabled => "Firma a ciegas:\nactivada",
+        Text::BlindSigningDisabled => "Firma a ciegas:\ndesactivada",
+        Text::LanguageSetting => "Idioma:\n{}",
+        Text::OrientationSetting => "Rotación de pantalla:\n{}°",
+
+        Text::InvalidSenderAddress => "Dirección de remitente no válida",
+        Text::InvalidNfcPacket => "Transferencia NFC dañada",
+        Text::InvalidTransaction => "Transacción no válida",
+        Text::InvalidNetworkSpecs => "Datos de red no válidos",
+        Text::InvalidContact => "Contacto no válido",
+        Text::UpdateSignatureInvalid => "Firma de actualización no válida",
+        Text::NoVerifier => "No se aceptan actualizaciones: el firmware no tiene clave de verificador",
+        Text::UpdateDamaged => "Actualización dañada",
+        Text::UnknownVerifier => "Actualización firmada por verificador desconocido",
+        Text::ReceivingNfc => "Recibiendo paquetes NFC...",
+        Text::ContactAdded => "Contacto {} añadido",
+        Text::AddressBookFull => "La libreta de direcciones está llena",
+        Text::ContactTooLarge => "El contacto es demasiado grande",
+        Text::ContactSaveFailed => "No se pudo guardar el contacto",
+        Text::NetworkAdded => "Red {} añadida",
+        Text::NetworkRegistryFull => "El registro de redes está lleno",
+        Text::NetworkTooLarge => "Los datos de red son demasiado grandes",
+        Text::NetworkSaveFailed => "No se pudo guardar la red",
+        Text::BuiltinNetwork => "Una red integrada no se puede reemplazar",
+
+        Text::RestartedAfterError => "Reiniciado tras el error {} en {}:{}",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_ids_are_listed_once() {
+        for (i, text) in Text::ALL.iter().enumerate() {
+            assert!(!Text::ALL[..i].contains(text), "{text:?} listed twice");
+        }
+    }
+
+    #[test]
+    fn translations_are_complete() {
+        for text in Text::ALL {
+            let placeholders = text.translate(Language::English).matches(PLACEHOLDER).count();
+            for language in Language::ALL {
+                let translation = text.translate(language);
+                assert!(!translation.is_empty(), "{text:?} is empty in {language:?}");
+                assert_eq!(translation.matches(PLACEHOLDER).count(), placeholders, "{text:?} placeholders in {language:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn templates_are_filled_in_order() {
+        set_language(Language::English);
+        assert_eq!(Text::MetadataOutdated.fill(&["9430", "9420"]), "Metadata outdated\nv9430 loaded\nv9420 needed");
+        assert_eq!(Text::ContactAdded.fill(&[]), "Contact  added");
+    }
+}
@@ -2,7 +2,7 @@ use embedded_graphics::{
     draw_target::DrawTarget,
     pixelcolor::BinaryColor,
     mono_font::{
-        ascii::FONT_10X20,
+        iso_8859_1::FONT_10X20,
         MonoTextStyle,
     },
     primitives::{Primitive, PrimitiveStyle},
@@ -7,7 +7,7 @@ use embedded_graphics::{
     pixelcolor::BinaryColor, prelude::{Drawable, DrawTarget, Point}, primitives::{Primitive, PrimitiveStyle}
 };
 
-use crate::{message, uistate::UpdateRequest, widget::view::ViewScreen};
+use crate::{locale::Text, message, uistate::UpdateRequest, widget::view::ViewScreen};
 use crate::uistate::EventResult;
 use crate::widget::view::View;
 use crate::platform::{PinCode, Platform};
@@ -106,13 +106,13 @@ impl<P> ViewScreen for Pincode<P> where
         let state = None;
 
         if matches!(self.tapped, PinpadState::DrawWrong) {
-            message::draw(target, "Pin is wrong", false)?;
+            message::draw(target, Text::PinWrong.get(), false)?;
             request = Some(UpdateRequest::Fast);
             self.tapped = PinpadState::Initial;
             return Ok((EventResult {request, state}, false))
         }
         if matches!(self.tapped, PinpadState::DrawOk) {
-            message::draw(target, "Pin is Ok", false)?;
+            message::draw(target, Text::PinOk.get(), false)?;
             return Ok((EventResult {request, state}, true))
         }
 
@@ -7,13 +7,25 @@ use std::{string::String, vec::Vec};
 
 use rand::{CryptoRng, Rng};
 
-use substrate_crypto_light::sr25519::{Pair, Public};
+use substrate_crypto_light::sr25519::{Pair, Public, Signature};
 use substrate_parser::{TransactionUnmarkedParsed, ShortSpecs};
 
 use mnemonic_external::AsWordList;
 
+use kampela_display_common::{display_def::NETWORK_LOGO_LEN, orientation::Orientation};
+
+use crate::font::FontStorage;
+use crate::locale::Language;
+
 pub type PinCode = [u8; 4];
+
+/// Multisignature variant id of sr25519 signature
+const SR25519_ID: u8 = 1;
+
+/// Length of signature in QR: hex of sr25519 id followed by signature
+pub const ENCODED_SIGNATURE_LEN: usize = 130;
 const ENTROPY_LEN: usize = 32; //TODO: move to appropriate place
+pub const ACCOUNT_ID_LEN: usize = 32;
 
 /// Implement this on platform to make crate work
 pub trait Platform {
@@ -33,6 +45,12 @@ pub trait Platform {
     // Device-specific wordlist implementation
     fn get_wordlist() -> Self::AsWordList;
 
+    /// Proportional font image, for text with non-ASCII characters
+    type FontStorage: FontStorage;
+    /// Device-specific font storage, kept for platform lifetime so that its cache outlives a
+    /// render; text is drawn with ASCII mono fonts if it holds no font
+    fn font_storage(&self) -> &Self::FontStorage;
+
     /// RNG getter
     fn rng(h: &mut Self::HAL) -> Self::Rng<'_>;
 
@@ -58,14 +76,45 @@ pub trait Platform {
 
     fn set_transaction(&mut self, transaction: Self::NfcTransaction);
 
+    /// Network of received transaction, if it is known to device
+    fn network(&mut self) -> Option<Network>;
+
+    /// Reason why received transaction can not be shown to user, if any
+    fn transaction_error(&mut self) -> Option<TransactionError>;
+
+    /// Blake2b-256 hash of received payload; the only thing user sees in blind signing
+    fn payload_hash(&mut self) -> [u8; 32];
+
     fn call(&mut self) -> Option<String>;
 
     fn extensions(&mut self) -> Option<String>;
 
+    /// Accounts in received call that are not in address book, in order of appearance
+    fn unsaved_accounts(&mut self) -> Vec<UnsavedAccount>;
+
+    /// Put account from received call in address book; error text is shown to user
+    fn save_contact(&mut self, account_id: [u8; ACCOUNT_ID_LEN], label: String) -> Result<(), String>;
+
     fn signature(&mut self) -> [u8; 130];
 
     fn address(&mut self) -> &[u8; 76];
 
+    /// Setting allowing to sign transactions that could not be decoded
+    fn allow_blind_signing(&self) -> bool;
+
+    fn set_allow_blind_signing(&mut self, allow: bool);
+
+    /// Language of UI strings, kept with other settings
+    fn language(&self) -> Language;
+
+    fn set_language(&mut self, language: Language);
+
+    /// Screen orientation, kept with other settings; display owner applies it to drawing and
+    /// touch
+    fn orientation(&self) -> Orientation;
+
+    fn set_orientation(&mut self, orientation: Orientation);
+
     //----derivatives----
 
     fn generate_seed_entropy(h: &mut Self::HAL) -> [u8; ENTROPY_LEN] {
@@ -83,6 +132,50 @@ pub trait Platform {
 
 }
 
+/// Encode signature for QR, as hex of multisignature
+pub fn encode_signature(signature: &Signature) -> [u8; ENCODED_SIGNATURE_LEN] {
+    let mut signature_with_id = [SR25519_ID; 65];
+    signature_with_id[1..].copy_from_slice(&signature.0);
+    hex::encode(signature_with_id)
+        .into_bytes()
+        .try_into()
+        .expect("static length")
+}
+
+/// Check signature encoded for QR against signed bytes
+pub fn verify_encoded_signature(public: &Public, signed: &[u8], encoded: &[u8; ENCODED_SIGNATURE_LEN]) -> bool {
+    let mut signature_with_id = [0u8; 65];
+    if hex::decode_to_slice(encoded, &mut signature_with_id).is_err() || signature_with_id[0] != SR25519_ID {
+        return false
+    }
+    let signature = Signature(signature_with_id[1..].try_into().expect("static length"));
+    public.verify(signed, &signature)
+}
+
+/// Network as it is shown to user
+pub struct Network {
+    pub name: String,
+    pub logo: [u8; NETWORK_LOGO_LEN],
+}
+
+/// Account in received call that could be saved in address book
+#[derive(Clone, Debug)]
+pub struct UnsavedAccount {
+    pub account_id: [u8; ACCOUNT_ID_LEN],
+    /// Account as shown in call
+    pub address: String,
+}
+
+/// Received transaction could not be decoded
+pub enum TransactionError {
+    /// Payload could be signed blindly, if user allows it in settings
+    Undecodable(String),
+    /// Metadata spec version differs from the one in transaction; signing is not possible
+    MetadataOutdated{in_metadata: String, in_transaction: String},
+    /// Payload could not be checked against metadata; signing is not possible
+    Unsignable(String),
+}
+
 pub struct NfcTransaction {
     pub decoded_transaction: TransactionUnmarkedParsed,
     pub data_to_sign: Vec<u8>,
@@ -5,12 +5,21 @@ use embedded_graphics::{
     draw_target::DrawTarget,
     geometry::Point,
     pixelcolor::BinaryColor,
+    primitives::Rectangle,
 };
 
 use crate::display_def::*;
 use qrcodegen_no_heap::{QrCode, QrCodeEcc, Version};
 
 pub fn draw<D>(data_to_qr: &[u8], display: &mut D) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    draw_in(data_to_qr, display, SCREEN_AREA)
+}
+
+/// Draw QR code centered within area; whole display is cleared
+pub fn draw_in<D>(data_to_qr: &[u8], display: &mut D, area: Rectangle) -> Result<(), D::Error>
 where
     D: DrawTarget<Color = BinaryColor>,
 {
@@ -25,7 +34,7 @@ where
 
     let scaling = {
         if qr_code.version() == Version::new(18) {2}
-        else {SCREEN_SIZE_Y as i32/qr_code.size()}
+        else {area.size.height as i32/qr_code.size()}
     };
 
     let filled = PrimitiveStyle::with_fill(BinaryColor::Off);
@@ -38,8 +47,8 @@ where
                 if qr_code.get_module(x / scaling, y / scaling) {BinaryColor::On}
                 else {BinaryColor::Off}
             };
-            let x_point = SCREEN_SIZE_X as i32/2 - size/2 + x;
-            let y_point = SCREEN_SIZE_Y as i32/2 - size/2 + y;
+            let x_point = area.top_left.x + area.size.width as i32/2 - size/2 + x;
+            let y_point = area.top_left.y + area.size.height as i32/2 - size/2 + y;
             let point = Point::new(x_point, y_point);
             let pixel = Pixel::<BinaryColor>(point, color);
             pixel.draw(display)?;
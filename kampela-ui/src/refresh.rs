@@ -0,0 +1,82 @@
+//! When to clear ghosting with full refresh
+//!
+//! Partial and fast EPD updates leave traces of previous image, and only full refresh removes
+//! them. Host asks policy before rendering pending update and tells it what was done after.
+
+use embedded_graphics::primitives::Rectangle;
+
+use crate::display_def::*;
+use crate::uistate::UpdateRequest;
+
+/// Partial and fast updates allowed between full refreshes
+pub const MAX_PARTIAL_UPDATES: usize = 20;
+
+/// Total area of partial and fast updates allowed between full refreshes, in whole screens
+pub const MAX_COVERED_SCREENS: u32 = 8;
+
+fn area(rectangle: &Rectangle) -> u32 {
+    rectangle.size.width * rectangle.size.height
+}
+
+pub struct RefreshPolicy {
+    partial_updates: usize,
+    /// Pixels refreshed by partial and fast updates
+    covered: u32,
+    /// Last update shown important screen
+    important_shown: bool,
+}
+
+impl RefreshPolicy {
+    pub fn new() -> Self {
+        Self {
+            partial_updates: 0,
+            covered: 0,
+            important_shown: false,
+        }
+    }
+
+    fn ghosting_accumulated(&self) -> bool {
+        self.partial_updates >= MAX_PARTIAL_UPDATES || self.covered >= MAX_COVERED_SCREENS * area(&SCREEN_AREA)
+    }
+
+    /// Update that should be made instead of requested one
+    ///
+    /// `important` is set for screens that must be crisp, e.g. QR codes; they get full refresh
+    /// when they replace other screen.
+    pub fn adjust(&self, request: UpdateRequest, important: bool) -> UpdateRequest {
+        match request {
+            UpdateRequest::Hidden | UpdateRequest::Slow => request,
+            _ => {
+                if self.ghosting_accumulated() || (important && !self.important_shown) {
+                    UpdateRequest::Slow
+                } else {
+                    request
+                }
+            },
+        }
+    }
+
+    /// Register update that was made; `UltraFast` is counted as covering whole screen
+    pub fn record(&mut self, update: &UpdateRequest, important: bool) {
+        let covered = match update {
+            UpdateRequest::Hidden => return,
+            UpdateRequest::Slow => {
+                self.partial_updates = 0;
+                self.covered = 0;
+                self.important_shown = important;
+                return
+            },
+            UpdateRequest::Fast | UpdateRequest::UltraFast => area(&SCREEN_AREA),
+            UpdateRequest::Part(a) => area(a),
+        };
+        self.partial_updates += 1;
+        self.covered = self.covered.saturating_add(covered);
+        self.important_shown = important;
+    }
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -13,6 +13,7 @@ use embedded_graphics::{
 use mnemonic_external::{AsWordList, Bits11, WordListElement, WordSet};
 
 use crate::{
+    locale::Text,
     platform::Platform,
     widget::{
         view::{View, ViewScreen},
@@ -64,11 +65,11 @@ impl<P: Platform> SeedEntry<P> {
         let mut state = SeedEntry {
             entry: Entry::new(),
             keyboard: Keyboard::new(),
-            remove: Key::new("DEL", &REMOVE_KEY_WIDGET),
+            remove: Key::new(Text::Delete.get(), &REMOVE_KEY_WIDGET),
             proposal: Proposal::new(wordlist),
             phrase: Phrase::new(phrase),
-            navbar_entry: NavBar::new(("clear", "")),
-            navbar_phrase: NavBar::new(("back", "")),
+            navbar_entry: NavBar::new((Text::Clear.get(), "")),
+            navbar_phrase: NavBar::new((Text::Back.get(), "")),
             tapped: KeyboardState::Initial,
             negative: false,
         };
@@ -112,9 +113,9 @@ impl<P: Platform> SeedEntry<P> {
     }
     fn update_navbar_phrase(&mut self) {
         if self.phrase.validate().is_some() {
-            self.navbar_phrase = NavBar::new(("back", "next"))
+            self.navbar_phrase = NavBar::new((Text::Back.get(), Text::Next.get()))
         } else {
-            self.navbar_phrase = NavBar::new(("back", ""))
+            self.navbar_phrase = NavBar::new((Text::Back.get(), ""))
         }
     }
 }
@@ -218,8 +219,8 @@ impl<P: Platform> ViewScreen for SeedEntry<P> {
                         } else {
                             let buffer = self.get_buffer();
                             state = Some(UnitScreen::ShowDialog(
-                                "Are you sure?\nEntered data will be lost",
-                                ("no", "yes"),
+                                Text::DiscardEntry.get(),
+                                (Text::No.get(), Text::Yes.get()),
                                 (
                                     Box::new(|| EventResult {
                                         request: Some(UpdateRequest::UltraFast),
@@ -0,0 +1,149 @@
+#[cfg(not(feature="std"))]
+use alloc::{format, string::String, vec, vec::Vec};
+#[cfg(feature="std")]
+use std::{format, string::String, vec, vec::Vec};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    pixelcolor::BinaryColor,
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
+
+use crate::display_def::*;
+use crate::locale::{Language, Text};
+use crate::widget::{list::ListView, nav_bar::nav_bar::{NavBar, NavCommand, NAV_BAR_WIDGET}, view::{View, ViewScreen, Widget}};
+use crate::uistate::{EventResult, UpdateRequest, UnitScreen};
+
+const SETTINGS_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: SCREEN_ZERO,
+        size: Size{
+            width: SCREEN_SIZE_X,
+            height: SCREEN_SIZE_Y - NAV_BAR_WIDGET.bounds.size.height,
+        }
+    },
+    SCREEN_ZERO
+);
+
+/// Shown only; toggled with navbar button
+const BLIND_SIGNING_ROW: usize = 0;
+/// Tapped to switch to next language
+const LANGUAGE_ROW: usize = 1;
+/// Tapped to flip screen
+const ORIENTATION_ROW: usize = 2;
+const ROWS: usize = 3;
+
+const ROW_HEIGHT: u32 = SETTINGS_WIDGET.bounds.size.height / ROWS as u32;
+
+/// Setting changed by user
+pub enum SettingsChange {
+    AllowBlindSigning(bool),
+    Language(Language),
+    Orientation(Orientation),
+}
+
+/// Device settings; only reachable on unlocked device
+pub struct Settings {
+    allow_blind_signing: bool,
+    language: Language,
+    orientation: Orientation,
+    list: ListView<String>,
+    navbar: NavBar,
+}
+
+impl Settings {
+    pub fn new(allow_blind_signing: bool, language: Language, orientation: Orientation) -> Self {
+        Settings {
+            allow_blind_signing,
+            language,
+            orientation,
+            list: ListView::new(&SETTINGS_WIDGET, ROW_HEIGHT, vec![String::new(); ROWS]),
+            navbar: Self::navbar(allow_blind_signing, language),
+        }
+    }
+
+    /// Row texts in current language, which is updated after tap is handled
+    fn rows(&self) -> Vec<String> {
+        let blind_signing = if self.allow_blind_signing {
+            Text::BlindSigningEnabled.get()
+        } else {
+            Text::BlindSigningDisabled.get()
+        };
+        vec![
+            String::from(blind_signing),
+            Text::LanguageSetting.fill(&[self.language.name()]),
+            Text::OrientationSetting.fill(&[&format!("{}", self.orientation.degrees())]),
+        ]
+    }
+
+    /// Labels are taken in settings language, so navbar is rebuilt when it changes
+    fn navbar(allow_blind_signing: bool, language: Language) -> NavBar {
+        let toggle = if allow_blind_signing { Text::Disable } else { Text::Enable };
+        NavBar::new((Text::Back.translate(language), toggle.translate(language)))
+    }
+}
+
+impl ViewScreen for Settings {
+    type DrawInput<'a> = ();
+    type DrawOutput = ();
+    type TapInput<'a> = ();
+    type TapOutput = Option<SettingsChange>;
+
+    fn draw_screen<'a, D>(&mut self, target: &mut D, _: ()) -> Result<(EventResult, ()), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        Self: 'a,
+    {
+        let state = None;
+        let request = None;
+
+        self.list.set_items(self.rows());
+        self.list.draw(target, false)?;
+        self.navbar.draw(target, false)?;
+        Ok((EventResult{state, request}, ()))
+    }
+
+    fn handle_tap_screen<'a>(&mut self, point: Point, _: ()) -> (EventResult, Option<SettingsChange>)
+    where
+        Self: 'a
+    {
+        let mut state = None;
+        let mut request = None;
+        let mut changed = None;
+
+        if let Some(Some(c)) = self.navbar.handle_tap(point, ()) {
+            match c {
+                NavCommand::Left => {
+                    state = Some(UnitScreen::QRAddress);
+                    request = Some(UpdateRequest::Slow);
+                },
+                NavCommand::Right => {
+                    self.allow_blind_signing = !self.allow_blind_signing;
+                    self.navbar = Self::navbar(self.allow_blind_signing, self.language);
+                    changed = Some(SettingsChange::AllowBlindSigning(self.allow_blind_signing));
+                    request = Some(UpdateRequest::Fast);
+                },
+            }
+        } else if let Some((_, Some(row))) = self.list.handle_tap(point, ()) {
+            // rows act as buttons, nothing stays selected
+            self.list.select(None);
+            match row {
+                LANGUAGE_ROW => {
+                    self.language = self.language.next();
+                    self.navbar = Self::navbar(self.allow_blind_signing, self.language);
+                    changed = Some(SettingsChange::Language(self.language));
+                    request = Some(UpdateRequest::Fast);
+                },
+                ORIENTATION_ROW => {
+                    self.orientation = self.orientation.next();
+                    changed = Some(SettingsChange::Orientation(self.orientation));
+                    // whole screen is redrawn in new orientation
+                    request = Some(UpdateRequest::Slow);
+                },
+                _ => (),
+            }
+        }
+        (EventResult{state, request}, changed)
+    }
+}
@@ -6,24 +6,70 @@ use std::{string::String, boxed::Box};
 use embedded_graphics::{
     draw_target::DrawTarget,
     pixelcolor::BinaryColor,
-    geometry::Point,
+    geometry::{Point, Size},
+    image::{Image, ImageRaw},
     mono_font::{
-        ascii::FONT_6X10,
+        iso_8859_1::{FONT_6X10, FONT_8X13_BOLD},
         MonoTextStyle,
     },
-    primitives::{Primitive, PrimitiveStyle},
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
     Drawable
 };
 
 use embedded_text::{
-    alignment::HorizontalAlignment,
+    alignment::{HorizontalAlignment, VerticalAlignment},
     style::{HeightMode, TextBoxStyleBuilder},
     TextBox,
 };
 
-use crate::widget::{nav_bar::nav_bar::{NavBar, NavCommand}, view::{View, ViewScreen}};
+use crate::display_def::*;
+use crate::font::{draw_line, draw_text_box, Font, FontStorage};
+use crate::locale::Text;
+use crate::platform::Network;
+use crate::widget::{nav_bar::nav_bar::{NavBar, NavCommand, NAV_BAR_WIDGET}, view::{View, ViewScreen, Widget}};
 use crate::uistate::{EventResult, UpdateRequest, UnitScreen};
 
+const NETWORK_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: SCREEN_ZERO,
+        size: Size{
+            width: SCREEN_SIZE_X,
+            height: NETWORK_LOGO_SIDE + 2,
+        }
+    },
+    SCREEN_ZERO
+);
+
+const NETWORK_NAME_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: Point{
+            x: (NETWORK_LOGO_SIDE + GAP * 2) as i32,
+            y: 0,
+        },
+        size: Size{
+            width: SCREEN_SIZE_X - NETWORK_LOGO_SIDE - GAP * 2,
+            height: NETWORK_WIDGET.bounds.size.height,
+        }
+    },
+    SCREEN_ZERO
+);
+
+const BODY_WIDGET: Widget = Widget::new(
+    Rectangle{
+        top_left: Point{
+            x: 0,
+            y: NETWORK_WIDGET.bounds.size.height as i32,
+        },
+        size: Size{
+            width: SCREEN_SIZE_X,
+            height: SCREEN_SIZE_Y - NETWORK_WIDGET.bounds.size.height - NAV_BAR_WIDGET.bounds.size.height,
+        }
+    },
+    SCREEN_ZERO
+);
+
+const PARAGRAPH_SPACING: u32 = 5;
+
 #[derive(Clone)]
 pub enum TransactionPage {
     Call,
@@ -33,31 +79,92 @@ pub enum TransactionPage {
 pub struct Transaction {
     page: TransactionPage,
     navbar: NavBar,
+    /// Call has recipient that is not in address book yet
+    can_save_contact: bool,
 }
 
 impl Transaction {
-    pub fn new(page: TransactionPage) -> Self {
-        let navbar = match page {
-            TransactionPage::Call => NavBar::new(("", "next")),
-            TransactionPage::Extension => NavBar::new(("previous", "sign")),
-        };
+    pub fn new(page: TransactionPage, can_save_contact: bool) -> Self {
+        let navbar = Self::navbar(&page, can_save_contact);
         Transaction {
             page,
             navbar,
+            can_save_contact,
+        }
+    }
+    fn navbar(page: &TransactionPage, can_save_contact: bool) -> NavBar {
+        match page {
+            TransactionPage::Call => {
+                if can_save_contact {
+                    NavBar::new((Text::Save.get(), Text::Next.get()))
+                } else {
+                    NavBar::new(("", Text::Next.get()))
+                }
+            },
+            TransactionPage::Extension => NavBar::new((Text::Previous.get(), Text::Sign.get())),
         }
     }
     pub fn get_page(&self) -> TransactionPage {
         self.page.clone()
     }
+
+    /// Header with network the transaction is signed for; unknown network is a warning
+    fn draw_network<D>(&self, target: &mut D, network: &Option<Network>, font: &Option<Font<&dyn FontStorage>>) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        match network {
+            Some(network) => {
+                let logo = ImageRaw::<BinaryColor>::new(&network.logo, NETWORK_LOGO_SIDE);
+                Image::new(&logo, Point::new(GAP as i32, 1)).draw(target)?;
+
+                if let Some(font) = font {
+                    let bounds = NETWORK_NAME_WIDGET.bounds;
+                    let top = bounds.top_left.y + (bounds.size.height as i32 - font.line_height() as i32) / 2;
+                    draw_line(target, font, &network.name, Point::new(bounds.top_left.x, top), BinaryColor::On)?;
+                    return Ok(())
+                }
+                let character_style = MonoTextStyle::new(&FONT_8X13_BOLD, BinaryColor::On);
+                let textbox_style = TextBoxStyleBuilder::new()
+                    .alignment(HorizontalAlignment::Left)
+                    .vertical_alignment(VerticalAlignment::Middle)
+                    .build();
+                TextBox::with_textbox_style(
+                    &network.name,
+                    NETWORK_NAME_WIDGET.bounds,
+                    character_style,
+                    textbox_style,
+                ).draw(target)?;
+            },
+            None => {
+                let filled = PrimitiveStyle::with_fill(BinaryColor::On);
+                NETWORK_WIDGET.bounds.into_styled(filled).draw(target)?;
+
+                let character_style = MonoTextStyle::new(&FONT_8X13_BOLD, BinaryColor::Off);
+                let textbox_style = TextBoxStyleBuilder::new()
+                    .alignment(HorizontalAlignment::Center)
+                    .vertical_alignment(VerticalAlignment::Middle)
+                    .build();
+                TextBox::with_textbox_style(
+                    Text::UnknownNetwork.get(),
+                    NETWORK_WIDGET.bounds,
+                    character_style,
+                    textbox_style,
+                ).draw(target)?;
+            },
+        }
+        Ok(())
+    }
 }
 
 impl ViewScreen for Transaction {
-    type DrawInput<'a> = Box<dyn FnOnce(&TransactionPage) -> String + 'a>;
+    /// Network, content of shown page, and proportional font if platform has one
+    type DrawInput<'a> = (Option<Network>, &'a str, Option<Font<&'a dyn FontStorage>>);
     type DrawOutput = ();
     type TapInput<'a> = ();
     type TapOutput = ();
 
-    fn draw_screen<'a, D>(&mut self, target: &mut D, get_content: Self::DrawInput<'a>) -> Result<(EventResult, ()), D::Error>
+    fn draw_screen<'a, D>(&mut self, target: &mut D, (network, content, font): Self::DrawInput<'a>) -> Result<(EventResult, ()), D::Error>
     where
         D: DrawTarget<Color = BinaryColor>,
         Self: 'a,
@@ -70,18 +177,27 @@ impl ViewScreen for Transaction {
         let textbox_style = TextBoxStyleBuilder::new()
             .height_mode(HeightMode::FitToText)
             .alignment(HorizontalAlignment::Left)
-            .paragraph_spacing(5)
+            .paragraph_spacing(PARAGRAPH_SPACING)
             .build();
         
         let area = target.bounding_box();
         area.into_styled(filled).draw(target)?;
-        
-        TextBox::with_textbox_style(
-            &get_content(&self.page),
-            area,
-            character_style,
-            textbox_style
-        ).draw(target)?;
+
+        self.draw_network(target, &network, &font)?;
+
+        match font {
+            Some(ref font) => {
+                draw_text_box(target, font, content, BODY_WIDGET.bounds, BinaryColor::On, PARAGRAPH_SPACING)?;
+            },
+            None => {
+                TextBox::with_textbox_style(
+                    content,
+                    BODY_WIDGET.bounds,
+                    character_style,
+                    textbox_style
+                ).draw(target)?;
+            },
+        }
 
         self.navbar.draw(target, false)?;
         Ok((EventResult{state, request}, ()))
@@ -98,10 +214,15 @@ impl ViewScreen for Transaction {
             match self.page {
                 TransactionPage::Call => {
                     match c {
-                        NavCommand::Left => {},
+                        NavCommand::Left => {
+                            if self.can_save_contact {
+                                state = Some(UnitScreen::ContactEntry);
+                                request = Some(UpdateRequest::Fast);
+                            }
+                        },
                         NavCommand::Right => {
                             self.page = TransactionPage::Extension;
-                            self.navbar = NavBar::new(("previous", "sign"));
+                            self.navbar = Self::navbar(&self.page, self.can_save_contact);
                             request = Some(UpdateRequest::Fast);
                         }
                     }
@@ -110,13 +231,13 @@ impl ViewScreen for Transaction {
                     match c {
                         NavCommand::Left => {
                             self.page = TransactionPage::Call;
-                            self.navbar = NavBar::new(("", "next"));
+                            self.navbar = Self::navbar(&self.page, self.can_save_contact);
                             request = Some(UpdateRequest::Fast);
                         },
                         NavCommand::Right => {
                             state = Some(UnitScreen::ShowDialog(
-                                "Sign the transaction?",
-                                ("no", "yes"),
+                                Text::SignTransaction.get(),
+                                (Text::No.get(), Text::Yes.get()),
                                 (
                                     Box::new(|| EventResult {
                                         request: Some(UpdateRequest::UltraFast),
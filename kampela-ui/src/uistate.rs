@@ -22,7 +22,7 @@ use stdwrap::*;
 
 use embedded_graphics::{
     draw_target::DrawTarget,
-    geometry::Point,
+    geometry::{Point, Size},
     pixelcolor::BinaryColor,
     prelude::Primitive,
     primitives::{
@@ -33,11 +33,11 @@ use embedded_graphics::{
     Drawable,
 };
 
-use crate::{dialog::Dialog, display_def::*, pin::pin::Pincode, qr, transaction::{Transaction, TransactionPage}, widget::view::ViewScreen};
+use crate::{blind::BlindTransaction, contact_entry::ContactEntry, dialog::Dialog, settings::{Settings, SettingsChange}, display_def::*, font::{Font, FontStorage}, locale::{self, Text}, pin::pin::Pincode, qr, transaction::{Transaction, TransactionPage}, widget::{nav_bar::nav_bar::{NavBar, NavCommand, NAV_BAR_WIDGET}, view::{View, ViewScreen}}};
 
 use crate::backup::Backup;
 
-use crate::platform::Platform;
+use crate::platform::{Platform, TransactionError};
 
 use crate::seed_entry::seed_entry::SeedEntry;
 
@@ -91,6 +91,9 @@ pub enum UnitScreen {
         bool
     ),
     ShowTransaction(TransactionPage),
+    ShowBlindTransaction,
+    ContactEntry,
+    Settings,
     QRSignature,
     QRAddress,
     Locked,
@@ -107,8 +110,13 @@ pub enum Screen<P: Platform> {
     OnboardingRestore(SeedEntry<P>),
     OnboardingBackup(Backup<P>),
     ShowMessage(String, Option<UnitScreen>),
+    /// Message kept until tapped, then screen it covered is restored
+    ShowNotice(String, Option<UnitScreen>),
     ShowDialog(Dialog),
     ShowTransaction(Transaction),
+    ShowBlindTransaction(BlindTransaction),
+    ContactEntry(ContactEntry),
+    Settings(Settings),
     QRSignature,
     QRAddress,
     Locked,
@@ -122,6 +130,9 @@ impl<P: Platform> Screen<P> {
             Screen::OnboardingBackup(b) => Some(UnitScreen::OnboardingBackup(Some(b.get_entropy().unwrap()))),
             Screen::ShowMessage(s, _) => Some(UnitScreen::ShowMessage(s.to_owned())),
             Screen::ShowTransaction(t) => Some(UnitScreen::ShowTransaction(t.get_page())),
+            Screen::ShowBlindTransaction(_) => Some(UnitScreen::ShowBlindTransaction),
+            Screen::ContactEntry(_) => Some(UnitScreen::ContactEntry),
+            Screen::Settings(_) => Some(UnitScreen::Settings),
             Screen::QRSignature => Some(UnitScreen::QRSignature),
             Screen::QRAddress => Some(UnitScreen::QRAddress),
             Screen::Locked => Some(UnitScreen::Locked),
@@ -133,10 +144,16 @@ impl<P: Platform> Default for Screen<P> {
     fn default() -> Self {Screen::QRAddress}
 }
 
+/// Settings are opened only with dedicated button, not by accidental tap on address
+fn address_navbar() -> NavBar {
+    NavBar::new(("", Text::Settings.get()))
+}
+
 impl <P: Platform, D: DrawTarget<Color = BinaryColor>> UIState<P, D> {
     pub fn new(mut platform: P, display: D, h: &mut <P as Platform>::HAL) -> Self
         where <P as Platform>::AsWordList: Sized {
         platform.read_entropy();
+        locale::set_language(platform.language());
         let initial_screen: Option<UnitScreen>;
         let unlocked: bool;
         if platform.public().is_none() {
@@ -181,8 +198,8 @@ impl <P: Platform, D: DrawTarget<Color = BinaryColor>> UIState<P, D> {
                 },
                 UnitScreen::OnboardingRestoreOrGenerate => {
                     self.screen = Screen::OnboardingRestoreOrGenerate(Dialog::new(
-                        "restore or generate?",
-                        ("restore", "generate"),
+                        Text::RestoreOrGenerate.get(),
+                        (Text::Restore.get(), Text::Generate.get()),
                         (
                             Box::new(|| EventResult{request: Some(UpdateRequest::Fast), state: Some(UnitScreen::OnboardingRestore(None))}),
                             Box::new(|| EventResult{request: Some(UpdateRequest::Fast), state: Some(UnitScreen::OnboardingBackup(None))}),
@@ -198,14 +215,30 @@ impl <P: Platform, D: DrawTarget<Color = BinaryColor>> UIState<P, D> {
                         if matches!(self.screen, Screen::ShowMessage(_, _)) {
                             self.screen = Screen::QRSignature;
                         } else {
-                            self.screen = Screen::ShowMessage("Signing...".to_owned(), Some(UnitScreen::QRSignature));
+                            self.screen = Screen::ShowMessage(Text::Signing.get().to_owned(), Some(UnitScreen::QRSignature));
                         }
                     } else {
                         self.screen = Screen::PinEntry(Pincode::new(h), UnitScreen::QRSignature);
                     }
                 },
                 UnitScreen::ShowTransaction(p) => {
-                    self.screen = Screen::ShowTransaction(Transaction::new(p));
+                    self.screen = Screen::ShowTransaction(Transaction::new(p, !self.platform.unsaved_accounts().is_empty()));
+                },
+                UnitScreen::ShowBlindTransaction => {
+                    self.screen = Screen::ShowBlindTransaction(BlindTransaction::new());
+                },
+                UnitScreen::ContactEntry => {
+                    self.screen = match self.platform.unsaved_accounts().into_iter().next() {
+                        Some(account) => Screen::ContactEntry(ContactEntry::new(account)),
+                        None => Screen::ShowTransaction(Transaction::new(TransactionPage::Call, false)),
+                    };
+                },
+                UnitScreen::Settings => {
+                    if self.unlocked {
+                        self.screen = Screen::Settings(Settings::new(self.platform.allow_blind_signing(), self.platform.language(), self.platform.orientation()));
+                    } else {
+                        self.screen = Screen::PinEntry(Pincode::new(h), UnitScreen::Settings);
+                    }
                 },
             }
         }
@@ -248,17 +281,80 @@ impl <P: Platform, D: DrawTarget<Color = BinaryColor>> UIState<P, D> {
                 out = res.request;
                 new_screen = res.state;
             },
+            Screen::ShowBlindTransaction(ref mut a) => {
+                let (res, _) = a.handle_tap_screen(point, self.platform.allow_blind_signing());
+                out = res.request;
+                new_screen = res.state;
+            },
+            Screen::ContactEntry(ref mut a) => {
+                let (res, label) = a.handle_tap_screen(point, ());
+                out = res.request;
+                new_screen = res.state;
+                if let Some((account_id, label)) = label {
+                    match self.platform.save_contact(account_id, label) {
+                        // next account without label, until user cancels
+                        Ok(()) => if !self.platform.unsaved_accounts().is_empty() {
+                            new_screen = Some(UnitScreen::ContactEntry);
+                        },
+                        Err(e) => new_screen = Some(UnitScreen::ShowMessage(e)),
+                    }
+                }
+            },
+            Screen::Settings(ref mut a) => {
+                let (res, change) = a.handle_tap_screen(point, ());
+                out = res.request;
+                new_screen = res.state;
+                match change {
+                    Some(SettingsChange::AllowBlindSigning(allow)) => self.platform.set_allow_blind_signing(allow),
+                    Some(SettingsChange::Language(language)) => {
+                        self.platform.set_language(language);
+                        locale::set_language(language);
+                    },
+                    Some(SettingsChange::Orientation(orientation)) => self.platform.set_orientation(orientation),
+                    None => (),
+                }
+            },
+            Screen::ShowNotice(_, ref mut covered) => {
+                out = Some(UpdateRequest::UltraFast);
+                new_screen = Some(covered.take().unwrap_or_default());
+            },
+            Screen::QRAddress => {
+                if let Some(Some(NavCommand::Right)) = address_navbar().handle_tap(point, ()) {
+                    out = Some(UpdateRequest::UltraFast);
+                    new_screen = Some(UnitScreen::Settings);
+                }
+            },
             _ => (),
         }
         self.switch_screen(new_screen, h);
         out
     }
+    /// Current screen must be shown crisp, without ghosting of previous one
+    ///
+    /// Checked before render, as rendering may switch to the next screen.
+    pub fn is_important_screen(&self) -> bool {
+        matches!(self.screen, Screen::QRSignature | Screen::QRAddress | Screen::OnboardingBackup(_))
+    }
+
     pub fn handle_message(&mut self, message: String, h: &mut <P as Platform>::HAL) -> Option<UpdateRequest>
         where <P as Platform>::AsWordList: Sized {
         let screen = Some(UnitScreen::ShowMessage(message));
         self.switch_screen(screen, h);
         Some(UpdateRequest::UltraFast)
     }
+
+    /// Show message over current screen until user taps it
+    pub fn handle_notice(&mut self, message: String) -> Option<UpdateRequest> {
+        let covered = self.screen.get_unit();
+        self.screen = Screen::ShowNotice(message, covered);
+        Some(UpdateRequest::UltraFast)
+    }
+
+    /// Notice from [`handle_notice`](Self::handle_notice) is on screen, not dismissed yet
+    pub fn is_notice_shown(&self) -> bool {
+        matches!(self.screen, Screen::ShowNotice(_, _))
+    }
+
     /// Handle NFC message reception.
     /// TODO this correctly
     /// currently it is a quick demo for expo
@@ -266,7 +362,14 @@ impl <P: Platform, D: DrawTarget<Color = BinaryColor>> UIState<P, D> {
         where <P as Platform>::AsWordList: Sized {
         // match self.screen {
             // Screen::OnboardingRestoreOrGenerate => {
-        let screen = Some(UnitScreen::ShowTransaction(TransactionPage::Call));
+        let screen = match self.platform.transaction_error() {
+            Some(TransactionError::Undecodable(_)) => Some(UnitScreen::ShowBlindTransaction),
+            Some(TransactionError::MetadataOutdated{in_metadata, in_transaction}) => Some(UnitScreen::ShowMessage(
+                Text::MetadataOutdated.fill(&[&in_metadata, &in_transaction])
+            )),
+            Some(TransactionError::Unsignable(reason)) => Some(UnitScreen::ShowMessage(reason)),
+            None => Some(UnitScreen::ShowTransaction(TransactionPage::Call)),
+        };
         self.switch_screen(screen, h);
         Some(UpdateRequest::UltraFast)
             // },
@@ -349,6 +452,9 @@ impl <P: Platform, D: DrawTarget<Color = BinaryColor>> UIState<P, D> {
                     _ => None
                 };
             },
+            Screen::ShowNotice(ref m, _) => {
+                message::draw(display, &format!("{}\n{}", m, Text::TapToContinue.get()), true)?;
+            },
             Screen::OnboardingRestoreOrGenerate(ref mut a) |
             Screen::ShowDialog(ref mut a) => {
                 let (res, _) = a.draw_screen(display, ())?;
@@ -356,29 +462,52 @@ impl <P: Platform, D: DrawTarget<Color = BinaryColor>> UIState<P, D> {
                 new_screen = res.state;
             }
             Screen::ShowTransaction(ref mut a) => {
+                let network = self.platform.network();
+                let content = match a.get_page() {
+                    TransactionPage::Call => self.platform.call(),
+                    TransactionPage::Extension => self.platform.extensions(),
+                }.expect("transaction should be stored to display");
+                let font = Font::new(self.platform.font_storage() as &dyn FontStorage);
                 let (res, _) = a.draw_screen(
                     display,
-                    Box::new(|s| {
-                        match s {
-                            TransactionPage::Call => {
-                                self.platform.call().expect("transaction should be stored to display")
-                            },
-                            TransactionPage::Extension => {
-                                self.platform.extensions().expect("transaction should be stored to display")
-                            },
-                        }
-                    })
+                    (
+                        network,
+                        &content,
+                        font,
+                    )
                 )?;
                 out = res.request;
                 new_screen = res.state;
             },
+            Screen::ShowBlindTransaction(ref mut a) => {
+                let reason = match self.platform.transaction_error() {
+                    Some(TransactionError::Undecodable(reason)) => reason,
+                    _ => String::new(),
+                };
+                let hash = self.platform.payload_hash();
+                let (res, _) = a.draw_screen(display, (reason, hash))?;
+                out = res.request;
+                new_screen = res.state;
+            },
+            Screen::ContactEntry(ref mut a) => {
+                let (res, _) = a.draw_screen(display, ())?;
+                out = res.request;
+                new_screen = res.state;
+            },
+            Screen::Settings(ref mut a) => {
+                let (res, _) = a.draw_screen(display, ())?;
+                out = res.request;
+                new_screen = res.state;
+            },
             Screen::QRSignature => {
                 qr::draw(&self.platform.signature(), display)?
             },
             Screen::QRAddress => {
                 let line1 = format!("substrate:0x{}", hex::encode(self.platform.public().expect("no entropy stored, no address could be shown").0));
 
-                qr::draw(&line1.as_bytes(), display)?
+                let qr_area = Rectangle::new(SCREEN_ZERO, Size::new(SCREEN_SIZE_X, SCREEN_SIZE_Y - NAV_BAR_WIDGET.bounds.size.height));
+                qr::draw_in(&line1.as_bytes(), display, qr_area)?;
+                address_navbar().draw(display, false)?;
             },
         }
         self.switch_screen(new_screen, h);
@@ -0,0 +1,491 @@
+//! Scrollable list of items, for menus and multi-item screens
+//!
+//! Items are shown page by page in rows of equal height; when they do not fit in one page, scroll
+//! bar is drawn on the right, and tap above or below its thumb flips page. Selected row is
+//! inverted. Tap results carry partial update covering only rows that changed.
+
+#[cfg(not(feature="std"))]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature="std")]
+use std::{string::String, vec::Vec};
+
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    mono_font::{
+        iso_8859_1::FONT_10X20,
+        MonoTextStyle,
+    },
+    pixelcolor::BinaryColor,
+    primitives::{Primitive, PrimitiveStyle, Rectangle},
+    Drawable,
+};
+use embedded_text::{
+    alignment::{HorizontalAlignment, VerticalAlignment},
+    style::TextBoxStyleBuilder,
+    TextBox,
+};
+
+use crate::uistate::{EventResult, UpdateRequest};
+use crate::widget::view::{DrawView, View, Widget};
+
+/// Scroll bar is wide enough to be tapped
+pub const SCROLL_BAR_WIDTH: u32 = 20;
+const SCROLL_THUMB_MIN: u32 = 8;
+const SCROLL_THUMB_MARGIN: u32 = 3;
+
+/// Text inset from row edge
+const ITEM_PADDING: u32 = 4;
+
+/// Content of list row
+pub trait ListItem {
+    /// Draw item within row; `target` is row-sized, and `color` contrasts with row background,
+    /// which is inverted for selected row
+    fn draw_item<D>(&self, target: &mut D, color: BinaryColor) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>;
+}
+
+fn draw_text_item<D>(text: &str, target: &mut D, color: BinaryColor) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = BinaryColor>,
+{
+    let bounds = target.bounding_box();
+    TextBox::with_textbox_style(
+        text,
+        Rectangle::new(
+            Point::new(ITEM_PADDING as i32, 0),
+            Size::new(bounds.size.width.saturating_sub(2 * ITEM_PADDING), bounds.size.height),
+        ),
+        MonoTextStyle::new(&FONT_10X20, color),
+        TextBoxStyleBuilder::new()
+            .alignment(HorizontalAlignment::Left)
+            .vertical_alignment(VerticalAlignment::Middle)
+            .build(),
+    ).draw(target)?;
+    Ok(())
+}
+
+impl ListItem for &str {
+    fn draw_item<D>(&self, target: &mut D, color: BinaryColor) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        draw_text_item(self, target, color)
+    }
+}
+
+impl ListItem for String {
+    fn draw_item<D>(&self, target: &mut D, color: BinaryColor) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        draw_text_item(self, target, color)
+    }
+}
+
+pub struct ListView<T: ListItem> {
+    widget: &'static Widget,
+    row_height: u32,
+    items: Vec<T>,
+    /// First item of shown page
+    first: usize,
+    selected: Option<usize>,
+}
+
+impl<T: ListItem> ListView<T> {
+    /// List filling `widget`, with rows of `row_height`, nothing selected
+    ///
+    /// Panics if row does not fit in widget, or widget could not hold scroll bar.
+    pub fn new(widget: &'static Widget, row_height: u32, items: Vec<T>) -> Self {
+        let size = widget.bounds.size;
+        assert!(row_height > 0 && row_height <= size.height, "list row height {} does not fit in {} pixels", row_height, size.height);
+        assert!(
+            size.width > SCROLL_BAR_WIDTH && size.height >= 2 * SCROLL_THUMB_MARGIN + SCROLL_THUMB_MIN,
+            "list of {}x{} pixels could not hold scroll bar", size.width, size.height,
+        );
+        ListView {
+            widget,
+            row_height,
+            items,
+            first: 0,
+            selected: None,
+        }
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Rows in one page
+    pub fn page_len(&self) -> usize {
+        ((self.widget.bounds.size.height / self.row_height) as usize).max(1)
+    }
+
+    fn is_scrollable(&self) -> bool {
+        self.items.len() > self.page_len()
+    }
+
+    fn row_width(&self) -> u32 {
+        if self.is_scrollable() {
+            self.widget.bounds.size.width - SCROLL_BAR_WIDTH
+        } else {
+            self.widget.bounds.size.width
+        }
+    }
+
+    /// Row of item within list, if item is on shown page
+    fn row_area(&self, index: usize) -> Option<Rectangle> {
+        if index < self.first || index >= self.first + self.page_len() || index >= self.items.len() {
+            return None
+        }
+        let row = (index - self.first) as u32;
+        Some(Rectangle::new(
+            Point::new(0, (row * self.row_height) as i32),
+            Size::new(self.row_width(), self.row_height),
+        ))
+    }
+
+    fn scroll_bar_area(&self) -> Rectangle {
+        Rectangle::new(
+            Point::new(self.row_width() as i32, 0),
+            Size::new(SCROLL_BAR_WIDTH, self.widget.bounds.size.height),
+        )
+    }
+
+    fn scroll_thumb_area(&self) -> Rectangle {
+        let bar = self.scroll_bar_area();
+        let track = bar.size.height - 2 * SCROLL_THUMB_MARGIN;
+        let len = self.items.len() as u32;
+        let height = (track * self.page_len() as u32 / len).max(SCROLL_THUMB_MIN).min(track);
+        let top = (track * self.first as u32 / len).min(track - height);
+        Rectangle::new(
+            bar.top_left + Point::new(SCROLL_THUMB_MARGIN as i32, (SCROLL_THUMB_MARGIN + top) as i32),
+            Size::new(SCROLL_BAR_WIDTH - 2 * SCROLL_THUMB_MARGIN, height),
+        )
+    }
+
+    /// Partial update of list area, in screen coordinates
+    fn part(&self, area: Rectangle) -> UpdateRequest {
+        UpdateRequest::Part(Rectangle::new(self.widget.top_left_absolute() + area.top_left, area.size))
+    }
+
+    /// Smallest rectangle covering both rows; shown rows only
+    fn rows_part(&self, a: Option<usize>, b: Option<usize>) -> Option<UpdateRequest> {
+        let area = match (a.and_then(|a| self.row_area(a)), b.and_then(|b| self.row_area(b))) {
+            (Some(a), Some(b)) => {
+                let top = a.top_left.y.min(b.top_left.y);
+                let bottom = (a.top_left.y + a.size.height as i32).max(b.top_left.y + b.size.height as i32);
+                Rectangle::new(Point::new(0, top), Size::new(a.size.width, (bottom - top) as u32))
+            },
+            (Some(a), None) | (None, Some(a)) => a,
+            (None, None) => return None,
+        };
+        Some(self.part(area))
+    }
+
+    fn show_page(&mut self, first: usize) -> Option<UpdateRequest> {
+        if first == self.first { return None }
+        self.first = first;
+        Some(self.part(self.bounding_box_view()))
+    }
+
+    pub fn next_page(&mut self) -> Option<UpdateRequest> {
+        let first = self.first + self.page_len();
+        if first >= self.items.len() { return None }
+        self.show_page(first)
+    }
+
+    pub fn previous_page(&mut self) -> Option<UpdateRequest> {
+        self.show_page(self.first.saturating_sub(self.page_len()))
+    }
+
+    /// Select item, flipping to its page if needed; `None` clears selection
+    pub fn select(&mut self, index: Option<usize>) -> Option<UpdateRequest> {
+        let index = index.filter(|i| *i < self.items.len());
+        if index == self.selected { return None }
+        let previous = core::mem::replace(&mut self.selected, index);
+        if let Some(i) = index {
+            let page_len = self.page_len();
+            if let Some(request) = self.show_page(i - i % page_len) {
+                return Some(request)
+            }
+        }
+        self.rows_part(previous, index)
+    }
+
+    /// Replace item content; only its row is updated
+    pub fn set_item(&mut self, index: usize, item: T) -> Option<UpdateRequest> {
+        *self.items.get_mut(index)? = item;
+        self.rows_part(Some(index), None)
+    }
+
+    /// Replace all items, clearing selection and going back to first page
+    pub fn set_items(&mut self, items: Vec<T>) -> Option<UpdateRequest> {
+        self.items = items;
+        self.first = 0;
+        self.selected = None;
+        Some(self.part(self.bounding_box_view()))
+    }
+}
+
+impl<T: ListItem> View for ListView<T> {
+    /// Negative
+    type DrawInput<'a> = bool where T: 'a;
+    type DrawOutput = ();
+    type TapInput<'a> = () where T: 'a;
+    /// Tapped item, if any
+    type TapOutput = (EventResult, Option<usize>);
+
+    fn bounding_box(&self) -> Rectangle {
+        self.widget.bounding_box()
+    }
+
+    fn bounding_box_absolut(&self) -> Rectangle {
+        self.widget.bounding_box_absolute()
+    }
+
+    fn draw_view<'a, D>(&mut self, target: &mut DrawView<D>, n: Self::DrawInput<'_>) -> Result<Self::DrawOutput, D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+        Self: 'a,
+    {
+        let (on, off) = if n {
+            (BinaryColor::Off, BinaryColor::On)
+        } else {
+            (BinaryColor::On, BinaryColor::Off)
+        };
+        self.bounding_box_view().into_styled(PrimitiveStyle::with_fill(off)).draw(target)?;
+
+        let shown = self.first..(self.first + self.page_len()).min(self.items.len());
+        for index in shown {
+            let area = self.row_area(index).expect("shown rows are on page");
+            let color = if self.selected == Some(index) {
+                area.into_styled(PrimitiveStyle::with_fill(on)).draw(target)?;
+                off
+            } else {
+                on
+            };
+            let mut row_target = DrawView::new(area, target);
+            self.items[index].draw_item(&mut row_target, color)?;
+        }
+
+        if self.is_scrollable() {
+            self.scroll_bar_area().into_styled(PrimitiveStyle::with_stroke(on, 1)).draw(target)?;
+            self.scroll_thumb_area().into_styled(PrimitiveStyle::with_fill(on)).draw(target)?;
+        }
+        Ok(())
+    }
+
+    fn handle_tap_view<'a>(&mut self, point: Point, _: ()) -> Self::TapOutput
+    where
+        Self: 'a,
+    {
+        let mut request = None;
+        let mut tapped = None;
+
+        if self.is_scrollable() && self.scroll_bar_area().contains(point) {
+            let thumb = self.scroll_thumb_area();
+            if point.y < thumb.top_left.y {
+                request = self.previous_page();
+            } else if point.y >= thumb.top_left.y + thumb.size.height as i32 {
+                request = self.next_page();
+            }
+        } else {
+            let index = self.first + (point.y.max(0) as u32 / self.row_height) as usize;
+            if self.row_area(index).is_some_and(|area| area.contains(point)) {
+                request = self.select(Some(index));
+                tapped = Some(index);
+            }
+        }
+        (EventResult { request, state: None }, tapped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use embedded_graphics::mock_display::MockDisplay;
+
+    /// Three rows of 20 pixels per page; scroll bar from x 80 when scrollable
+    const WIDGET: Widget = Widget::new(Rectangle::new(Point::new(10, 20), Size::new(100, 60)), Point::zero());
+    const ROW_HEIGHT: u32 = 20;
+
+    const SMALL_WIDGET: Widget = Widget::new(Rectangle::new(Point::zero(), Size::new(60, 60)), Point::zero());
+
+    fn list(len: usize) -> ListView<&'static str> {
+        let items = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+        ListView::new(&WIDGET, ROW_HEIGHT, items[..len].to_vec())
+    }
+
+    /// Area of partial update, in screen coordinates
+    fn part(request: Option<UpdateRequest>) -> Option<Rectangle> {
+        match request {
+            Some(UpdateRequest::Part(area)) => Some(area),
+            None => None,
+            Some(_) => panic!("only partial updates are expected from list"),
+        }
+    }
+
+    fn rows(first_row: i32, rows: u32, width: u32) -> Rectangle {
+        Rectangle::new(Point::new(10, 20 + first_row * ROW_HEIGHT as i32), Size::new(width, rows * ROW_HEIGHT))
+    }
+
+    #[test]
+    fn pages_flip_within_items() {
+        let mut list = list(7);
+        assert_eq!(list.page_len(), 3);
+        assert!(list.is_scrollable());
+        assert_eq!(part(list.next_page()), Some(WIDGET.bounding_box_absolute()));
+        assert_eq!(list.first, 3);
+        assert_eq!(part(list.next_page()), Some(WIDGET.bounding_box_absolute()));
+        assert_eq!(list.first, 6);
+        // last page holds the only item left
+        assert_eq!(part(list.next_page()), None);
+        assert_eq!(list.row_area(6), Some(Rectangle::new(Point::zero(), Size::new(80, ROW_HEIGHT))));
+        assert_eq!(list.row_area(5), None);
+        assert_eq!(list.row_area(7), None);
+
+        assert_eq!(part(list.previous_page()), Some(WIDGET.bounding_box_absolute()));
+        assert_eq!(list.first, 3);
+        list.previous_page();
+        assert_eq!(part(list.previous_page()), None);
+        assert_eq!(list.first, 0);
+    }
+
+    #[test]
+    fn scroll_bar_taps_flip_pages() {
+        let mut list = list(7);
+        // thumb of first page is at the top of bar
+        let (result, tapped) = list.handle_tap_view(Point::new(90, 50), ());
+        assert_eq!(tapped, None);
+        assert_eq!(part(result.request), Some(WIDGET.bounding_box_absolute()));
+        assert_eq!(list.first, 3);
+        let (result, _) = list.handle_tap_view(Point::new(90, 1), ());
+        assert_eq!(part(result.request), Some(WIDGET.bounding_box_absolute()));
+        assert_eq!(list.first, 0);
+        // tap on thumb itself does nothing
+        let thumb = list.scroll_thumb_area();
+        let (result, _) = list.handle_tap_view(thumb.center(), ());
+        assert_eq!(part(result.request), None);
+    }
+
+    #[test]
+    fn thumb_stays_in_bar() {
+        for len in 4..10 {
+            let mut list = list(len);
+            loop {
+                let thumb = list.scroll_thumb_area();
+                let bar = list.scroll_bar_area();
+                assert!(thumb.size.height >= SCROLL_THUMB_MIN);
+                assert!(bar.contains(thumb.top_left) && bar.contains(thumb.bottom_right().unwrap()), "{len} items, page at {}", list.first);
+                if list.next_page().is_none() { break }
+            }
+        }
+    }
+
+    #[test]
+    fn selection_updates_changed_rows_only() {
+        let mut list = list(7);
+        assert_eq!(part(list.select(Some(1))), Some(rows(1, 1, 80)));
+        assert_eq!(list.selected(), Some(1));
+        assert_eq!(part(list.select(Some(1))), None);
+        // previous and new selection, with row between
+        assert_eq!(part(list.select(Some(0))), Some(rows(0, 2, 80)));
+        assert_eq!(part(list.select(Some(2))), Some(rows(0, 3, 80)));
+        assert_eq!(part(list.select(None)), Some(rows(2, 1, 80)));
+        assert_eq!(list.selected(), None);
+    }
+
+    #[test]
+    fn selection_flips_to_its_page() {
+        let mut list = list(7);
+        list.select(Some(1));
+        assert_eq!(part(list.select(Some(5))), Some(WIDGET.bounding_box_absolute()));
+        assert_eq!(list.first, 3);
+        assert_eq!(list.selected(), Some(5));
+        // row 5 is third on its page
+        assert_eq!(part(list.select(None)), Some(rows(2, 1, 80)));
+        // items past the end are not selected
+        assert_eq!(part(list.select(Some(7))), None);
+        assert_eq!(list.selected(), None);
+    }
+
+    #[test]
+    fn row_taps_select_items() {
+        let mut list = list(7);
+        let (result, tapped) = list.handle_tap_view(Point::new(5, 25), ());
+        assert_eq!(tapped, Some(1));
+        assert_eq!(part(result.request), Some(rows(1, 1, 80)));
+        assert_eq!(list.selected(), Some(1));
+
+        list.next_page();
+        list.next_page();
+        // below the last item on page
+        let (result, tapped) = list.handle_tap_view(Point::new(5, 25), ());
+        assert_eq!(tapped, None);
+        assert_eq!(part(result.request), None);
+        let (_, tapped) = list.handle_tap_view(Point::new(5, 5), ());
+        assert_eq!(tapped, Some(6));
+    }
+
+    #[test]
+    fn rows_span_widget_without_scroll_bar() {
+        let mut list = list(3);
+        assert!(!list.is_scrollable());
+        assert_eq!(part(list.select(Some(2))), Some(rows(2, 1, 100)));
+        // no scroll bar to tap
+        let (_, tapped) = list.handle_tap_view(Point::new(90, 5), ());
+        assert_eq!(tapped, Some(0));
+        assert_eq!(part(list.next_page()), None);
+    }
+
+    #[test]
+    fn items_are_replaced() {
+        let mut list = list(7);
+        assert_eq!(part(list.set_item(2, "two")), Some(rows(2, 1, 80)));
+        assert_eq!(list.items()[2], "two");
+        // off page and past the end
+        assert_eq!(part(list.set_item(4, "four")), None);
+        assert_eq!(list.items()[4], "four");
+        assert_eq!(part(list.set_item(7, "seven")), None);
+
+        list.select(Some(4));
+        assert_eq!(part(list.set_items(["a", "b"].to_vec())), Some(WIDGET.bounding_box_absolute()));
+        assert_eq!(list.first, 0);
+        assert_eq!(list.selected(), None);
+        assert!(!list.is_scrollable());
+    }
+
+    #[test]
+    fn selected_row_is_inverted() {
+        let mut list = ListView::new(&SMALL_WIDGET, ROW_HEIGHT, ["a", "b", "c", "d"].to_vec());
+        list.select(Some(1));
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        list.draw(&mut display, false).unwrap();
+        assert_eq!(display.get_pixel(Point::new(0, 5)), Some(BinaryColor::Off));
+        assert_eq!(display.get_pixel(Point::new(0, 25)), Some(BinaryColor::On));
+        assert_eq!(display.get_pixel(Point::new(0, 45)), Some(BinaryColor::Off));
+        // scroll bar outline
+        assert_eq!(display.get_pixel(Point::new(40, 30)), Some(BinaryColor::On));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_row_height_is_rejected() {
+        ListView::new(&WIDGET, 0, ["a"].to_vec());
+    }
+
+    #[test]
+    #[should_panic]
+    fn widget_lower_than_scroll_bar_is_rejected() {
+        const LOW: Widget = Widget::new(Rectangle::new(Point::zero(), Size::new(100, 5)), Point::zero());
+        ListView::new(&LOW, 5, ["a"].to_vec());
+    }
+}
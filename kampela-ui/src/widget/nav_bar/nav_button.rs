@@ -3,7 +3,7 @@ use embedded_graphics::{
 	prelude::{DrawTarget, Point, Dimensions},
 	Drawable,
 	mono_font::{
-        ascii::FONT_10X20,
+        iso_8859_1::FONT_10X20,
         MonoTextStyleBuilder,
         MonoFont,
     },
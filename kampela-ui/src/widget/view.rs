@@ -19,7 +19,7 @@ pub struct DrawView<'a, D> where
 }
 
 impl <'a, D: DrawTarget> DrawView<'a, D> {
-    fn new(area: Rectangle, target: &'a mut D) -> Self {
+    pub(crate) fn new(area: Rectangle, target: &'a mut D) -> Self {
         DrawView { area, origin: target }
     }
 }
@@ -6,7 +6,7 @@
 extern crate alloc;
 extern crate core;
 
-use alloc::{borrow::ToOwned, format};
+use alloc::borrow::ToOwned;
 use core::{alloc::Layout, panic::PanicInfo};
 use core::ptr::addr_of;
 use cortex_m::asm::delay;
@@ -15,12 +15,12 @@ use embedded_alloc::Heap;
 use lazy_static::lazy_static;
 
 use efm32pg23_fix::{interrupt, Interrupt, NVIC, Peripherals};
-use kampela_ui::platform::Platform;
+use kampela_ui::{locale::Text, platform::Platform};
 
 mod ui;
 use ui::UI;
 mod nfc;
-use nfc::{BufferStatus, NfcReceiver, NfcStateOutput, NfcResult, NfcError};
+use nfc::{NfcReceiver, NfcStateOutput, NfcResult, NfcError};
 
 #[global_allocator]
 static HEAP: Heap = Heap::empty();
@@ -28,9 +28,11 @@ static HEAP: Heap = Heap::empty();
 use kampela_system::{
     PERIPHERALS, CORE_PERIPHERALS,
     devices::power::ADC,
-    debug_display::burning_tank,
+    flash_verifier::VerifierError,
     init::init_peripherals,
+    panic_screen::{panic_report, prepare_panic_screen, show_panic},
     parallel::Operation,
+    triple_buffer::{Region, TripleBuffer},
     BUF_THIRD, CH_TIM0, LINK_1, LINK_2, LINK_DESCRIPTORS, TIMER0_CC0_ICF, NfcXfer, NfcXferBlock,
 };
 
@@ -41,7 +43,7 @@ use cortex_m::interrupt::Mutex;
 
 lazy_static!{
     #[derive(Debug)]
-    static ref BUFFER_STATUS: Mutex<RefCell<BufferStatus>> = Mutex::new(RefCell::new(BufferStatus::new()));
+    static ref BUFFER_STATUS: Mutex<RefCell<TripleBuffer<u16, BUF_THIRD>>> = Mutex::new(RefCell::new(TripleBuffer::new()));
 }
 
 /*
@@ -60,7 +62,7 @@ fn oom(l: Layout) -> ! {
 #[panic_handler]
 fn panic(panic: &PanicInfo<'_>) -> ! {
     let mut peripherals = unsafe{Peripherals::steal()};
-    burning_tank(&mut peripherals, format!("{:?}", panic));
+    show_panic(&mut peripherals, &panic_report(panic));
     loop {}
 }
 
@@ -75,13 +77,9 @@ fn LDMA() {
         if let Some(ref mut peripherals) = PERIPHERALS.borrow(cs).borrow_mut().deref_mut() {
             peripherals.LDMA_S.if_.reset();
             let mut buffer_status = BUFFER_STATUS.borrow(cs).borrow_mut();
-            match buffer_status.pass_if_done7() {
-                Ok(_) => {
-                    if !buffer_status.is_write_halted() {
-                        peripherals.LDMA_S.linkload.write(|w_reg| w_reg.linkload().variant(1 << CH_TIM0));
-                    }
-                },
-                Err(_) => {}
+            // overruns are counted in buffer status; lost frames are recovered by LT decoding
+            if let Ok(true) = buffer_status.write_done() {
+                peripherals.LDMA_S.linkload.write(|w_reg| w_reg.linkload().variant(1 << CH_TIM0));
             }
         }
         else {panic!("can not borrow peripherals in ldma interrupt")}
@@ -98,25 +96,32 @@ fn main() -> ! {
     }
 
 
-    let nfc_buffer: [u16; 3*BUF_THIRD] = [1; 3*BUF_THIRD];
+    // NFC buffer lives in static buffer status, so region addresses never change
+    let mut nfc_regions = [0u32; 3];
+    free(|cs| {
+        let buffer_status = BUFFER_STATUS.borrow(cs).borrow();
+        for (address, region) in nfc_regions.iter_mut().zip([Region::Reg0, Region::Reg1, Region::Reg2]) {
+            *address = buffer_status.region_address(region) as u32;
+        }
+    });
 
     let nfc_transfer_block = NfcXferBlock {
         block0: NfcXfer {
             descriptors: LINK_DESCRIPTORS,
             source: TIMER0_CC0_ICF,
-            dest: addr_of!(nfc_buffer[0]) as u32,
+            dest: nfc_regions[0],
             link: LINK_1,
         },
         block1: NfcXfer {
             descriptors: LINK_DESCRIPTORS,
             source: TIMER0_CC0_ICF,
-            dest: addr_of!(nfc_buffer[BUF_THIRD]) as u32,
+            dest: nfc_regions[1],
             link: LINK_1,
         },
         block2: NfcXfer {
             descriptors: LINK_DESCRIPTORS,
             source: TIMER0_CC0_ICF,
-            dest: addr_of!(nfc_buffer[2*BUF_THIRD]) as u32,
+            dest: nfc_regions[2],
             link: LINK_2,
         },
     };
@@ -124,6 +129,7 @@ fn main() -> ! {
     let mut peripherals = Peripherals::take().unwrap();
 
     init_peripherals(&mut peripherals, addr_of!(nfc_transfer_block));
+    prepare_panic_screen();
 
     delay(1000);
 
@@ -169,7 +175,7 @@ fn main() -> ! {
     //         .expand_to_keypair(ExpansionMode::Ed25519);
 
 
-    let mut nfc = NfcReceiver::new(&nfc_buffer, ui.state.platform.public().map(|a| a.0));
+    let mut nfc = NfcReceiver::new(ui.state.platform.public().map(|a| a.0));
     loop {
         adc.advance(());
         let nfc_state = nfc.advance(adc.read());
@@ -178,8 +184,29 @@ fn main() -> ! {
                 Err(e) => {
                     match e {
                         NfcError::InvalidAddress => {
-                            ui.handle_message("Invalid sender address".to_owned())
-                        }
+                            ui.handle_message(Text::InvalidSenderAddress.get().to_owned())
+                        },
+                        NfcError::InvalidPacket => {
+                            ui.handle_message(Text::InvalidNfcPacket.get().to_owned())
+                        },
+                        NfcError::InvalidTransaction => {
+                            ui.handle_message(Text::InvalidTransaction.get().to_owned())
+                        },
+                        NfcError::InvalidNetworkSpecs => {
+                            ui.handle_message(Text::InvalidNetworkSpecs.get().to_owned())
+                        },
+                        NfcError::InvalidContact => {
+                            ui.handle_message(Text::InvalidContact.get().to_owned())
+                        },
+                        NfcError::Verifier(e) => {
+                            let message = match e {
+                                VerifierError::BadSignature => Text::UpdateSignatureInvalid,
+                                VerifierError::NoVerifier => Text::NoVerifier,
+                                VerifierError::TooShort => Text::UpdateDamaged,
+                                VerifierError::UnknownVerifier => Text::UnknownVerifier,
+                            };
+                            ui.handle_message(message.get().to_owned())
+                        },
                     }
                     while !ui.advance(adc.read()).is_some_and(|c| c == true) {
                         adc.advance(());
@@ -190,7 +217,7 @@ fn main() -> ! {
                     match s {
                         NfcStateOutput::Operational(i) => {
                             if i == 1 {
-                                ui.handle_message("Receiving NFC packets...".to_owned());
+                                ui.handle_message(Text::ReceivingNfc.get().to_owned());
                             }
                             while !ui.advance(adc.read()).is_some_and(|c| c == false) {
                                 adc.advance(());
@@ -203,6 +230,14 @@ fn main() -> ! {
                                     ui.handle_address([0;76]);
                                     break
                                 },
+                                NfcResult::AddNetwork(specs) => {
+                                    ui.handle_network(specs);
+                                    break
+                                },
+                                NfcResult::AddContact(contact) => {
+                                    ui.handle_contact(contact);
+                                    break
+                                },
                                 NfcResult::Transaction(transaction) => {
                                     ui.handle_transaction(transaction);
                                     break
@@ -1,27 +1,31 @@
 //! Everything high-level related to interfacing with user
 
 use nalgebra::{Affine2, OMatrix, Point2, RowVector3};
-use alloc::{collections::VecDeque, string::String, vec::Vec};
+use alloc::{collections::VecDeque, format, string::String, vec::Vec};
 use lazy_static::lazy_static;
 use substrate_crypto_light::sr25519::{Pair, Public};
-use embedded_graphics::{
-    prelude::Point,
-    geometry::Dimensions,
-};
+use embedded_graphics::prelude::Point;
 
 use kampela_system::{
     devices::{
-        psram::{psram_decode_call, psram_decode_extension, read_from_psram, PsramAccess},
+        psram::{psram_blake2b_256, psram_decode_call, psram_decode_extension, psram_signing_payload, read_from_psram, PsramAccess},
         se_aes_gcm::{decode_entropy, encode_entropy, Protected},
         se_rng,
         touch::{touch_detected, Read, FT6X36_REG_NUM_TOUCHES, LEN_NUM_TOUCHES}
-    }, draw::FrameBuffer, flash_mnemonic::FlashWordList, parallel::Operation
+    }, draw::FrameBuffer, flash_font::FlashFont, flash_mnemonic::FlashWordList, parallel::Operation,
+    parser::DecodeError,
+    flash_address_book::{show_card, store_contact, AddressBookError, Contact, ACCOUNT_ID_LEN},
+    flash_networks::{find_network, store_network, NetworkSpecs, NetworkStoreError},
+    flash_error::{clear_error, read_error},
+    flash_settings::{read_settings, store_settings, Settings},
 };
 use kampela_system::devices::flash::*;
 use crate::nfc::NfcTransactionPsramAccess;
 use kampela_ui::{
     display_def::*,
-    platform::{PinCode, Platform},
+    locale::{Language, Text},
+    platform::{encode_signature, Network, PinCode, Platform, TransactionError, UnsavedAccount},
+    refresh::RefreshPolicy,
     uistate::{UIState, UpdateRequest, UpdateRequestMutate}
 };
 
@@ -34,21 +38,38 @@ pub struct UI {
     touches: VecDeque<Point>,
     touched: bool,
     update_request: Option<UpdateRequest>,
+    refresh_policy: RefreshPolicy,
+    /// Stored error report is on screen; it is cleared from flash once user dismisses it
+    error_shown: bool,
 }
 
 impl UI {
     /// Start of UI.
     pub fn init() -> Self {
         let hardware = Hardware::new();
-        let display = FrameBuffer::new_white();
+        let mut display = FrameBuffer::new_white();
+        display.set_orientation(hardware.settings.orientation);
         let state = UIState::new(hardware, display, &mut ());
-        return Self {
+        let mut ui = Self {
             state,
             status: UIStatus::DisplayOrListen(UIStatusDisplay::Listen),
             touches: VecDeque::new(),
             touched: false,
             update_request: Some(UpdateRequest::Slow),
+            refresh_policy: RefreshPolicy::new(),
+            error_shown: false,
+        };
+        // error screen could have been lost to power outage, so last error is shown again, until
+        // user dismisses it
+        if let Some(report) = read_error(&mut FreeFlash) {
+            ui.error_shown = true;
+            ui.update_request.propagate(ui.state.handle_notice(Text::RestartedAfterError.fill(&[
+                &format!("E{:04X}", report.id),
+                report.module.as_str(),
+                &format!("{}", report.line),
+            ])));
         }
+        ui
     }
 
     /// Call in event loop to progress through UI state
@@ -87,7 +108,7 @@ impl UI {
                 match touch.advance(()) {
                     Ok(Some(touch)) => {
                         if self.touches.len() < MAX_TOUCH_QUEUE {
-                            if let Some(point) = convert(touch) {
+                            if let Some(point) = convert(touch, self.state.display.orientation()) {
                                 self.touches.push_back(point);
                             }
                         }
@@ -104,20 +125,37 @@ impl UI {
     fn listen(&mut self) {
         if let Some(point) = self.touches.pop_front() {
             self.update_request.propagate(self.state.handle_tap(point, &mut ()));
+            // orientation changed in settings is applied before screen is redrawn
+            let orientation = self.state.platform.orientation();
+            if orientation != self.state.display.orientation() {
+                self.state.display.set_orientation(orientation);
+            }
+            if self.error_shown && !self.state.is_notice_shown() {
+                clear_error(&mut FreeFlash);
+                self.error_shown = false;
+            }
         }
         // update ui if needed
         if let Some(u) = self.update_request.take() {
+            let important = self.state.is_important_screen();
+            let u = self.refresh_policy.adjust(u, important);
             let is_clear_update = matches!(u, UpdateRequest::Slow) || matches!(u, UpdateRequest::Fast);
             self.update_request.propagate(self.state.render(is_clear_update, &mut ()).expect("guaranteed to work, no errors implemented"));
 
+            let u = match u {
+                UpdateRequest::UltraFast => match self.state.display.dirty_area() {
+                    Some(a) => UpdateRequest::Part(a),
+                    None => return, // nothing changed on screen
+                },
+                u => u,
+            };
+            self.refresh_policy.record(&u, important);
+
             match u {
                 UpdateRequest::Hidden => (),
                 UpdateRequest::Slow => self.state.display.request_full(),
                 UpdateRequest::Fast => self.state.display.request_fast(),
-                UpdateRequest::UltraFast => {
-                    let a = self.state.display.bounding_box();
-                    self.state.display.request_part(a);
-                },
+                UpdateRequest::UltraFast => unreachable!("replaced with part update above"),
                 UpdateRequest::Part(a) => self.state.display.request_part(a),
             }
             if !matches!(u, UpdateRequest::Hidden) {
@@ -138,6 +176,25 @@ impl UI {
     pub fn handle_address(&mut self, addr: [u8; 76]) {
         self.update_request.propagate(self.state.handle_address(addr));
     }
+
+    pub fn handle_contact(&mut self, contact: Contact) {
+        let message = match store_contact(&mut FreeFlash, &contact) {
+            Ok(()) => Text::ContactAdded.fill(&[&contact.label]),
+            Err(e) => String::from(address_book_error_text(e)),
+        };
+        self.handle_message(message);
+    }
+
+    pub fn handle_network(&mut self, specs: NetworkSpecs) {
+        let message = match store_network(&mut FreeFlash, &specs) {
+            Ok(()) => Text::NetworkAdded.fill(&[&specs.name]),
+            Err(NetworkStoreError::Builtin) => String::from(Text::BuiltinNetwork.get()),
+            Err(NetworkStoreError::RegistryFull) => String::from(Text::NetworkRegistryFull.get()),
+            Err(NetworkStoreError::TooLarge) => String::from(Text::NetworkTooLarge.get()),
+            Err(NetworkStoreError::Flash(_)) => String::from(Text::NetworkSaveFailed.get()),
+        };
+        self.handle_message(message);
+    }
 }
 
 /// General status of UI
@@ -159,11 +216,43 @@ enum UIStatus {
     /// Touch event processing
     TouchOperation(Read<LEN_NUM_TOUCHES, FT6X36_REG_NUM_TOUCHES>, UIStatusDisplay),
 }
+/// Transaction decoded once on reception, ready to be shown
+struct DecodedTransaction {
+    call: String,
+    extensions: String,
+    /// Accounts in call that are not in address book, with their cards as shown
+    unsaved_accounts: Vec<([u8; ACCOUNT_ID_LEN], String)>,
+}
+
+fn address_book_error_text(e: AddressBookError) -> &'static str {
+    match e {
+        AddressBookError::AddressBookFull => Text::AddressBookFull.get(),
+        AddressBookError::TooLarge => Text::ContactTooLarge.get(),
+        AddressBookError::Flash(_) => Text::ContactSaveFailed.get(),
+    }
+}
+
+fn decode_error_text(e: &DecodeError) -> String {
+    match e {
+        DecodeError::Metadata(_) => String::from(Text::MetadataDamaged.get()),
+        DecodeError::Call(reason) => Text::CallUndecodable.fill(&[reason]),
+        DecodeError::Extensions(reason) => Text::ExtensionsUndecodable.fill(&[reason]),
+        DecodeError::MetadataOutdated{in_metadata, in_transaction} => Text::MetadataOutdated.fill(&[in_metadata, in_transaction]),
+        DecodeError::NoSpecVersion => String::from(Text::NoSpecVersion.get()),
+    }
+}
+
 pub struct Hardware {
     pin: PinCode,
     protected: Option<Protected>,
     address: Option<[u8; 76]>,
     transaction_psram_access: Option<NfcTransactionPsramAccess>,
+    network: Option<NetworkSpecs>,
+    genesis_hash: [u8; 32],
+    decoded_transaction: Option<Result<DecodedTransaction, DecodeError>>,
+    payload_hash: Option<[u8; 32]>,
+    settings: Settings,
+    font: FlashFont,
 }
 
 impl Hardware {
@@ -176,20 +265,85 @@ impl Hardware {
             protected,
             address: None,
             transaction_psram_access: None,
+            network: None,
+            genesis_hash: [0; 32],
+            decoded_transaction: None,
+            payload_hash: None,
+            settings: read_settings(&mut FreeFlash),
+            font: FlashFont::new(),
+        }
+    }
+
+    /// Signed payload is call immediately followed by extensions
+    fn data_to_sign_psram_access(&self) -> Option<PsramAccess> {
+        let transaction_psram_access = self.transaction_psram_access.as_ref()?;
+        Some(PsramAccess {
+            start_address: transaction_psram_access.call_psram_access.start_address,
+            total_len:
+                transaction_psram_access.call_psram_access.total_len
+                + &transaction_psram_access.extension_psram_access.total_len
+        })
+    }
+
+    fn decode_transaction(&self, transaction_psram_access: &NfcTransactionPsramAccess) -> Result<DecodedTransaction, DecodeError> {
+        // extensions go first, their spec version tells if metadata fits the call; decimals and
+        // unit come from metadata only: stored network specs name the network, but must not
+        // change amounts shown
+        let (decoded_extension, metadata_specs, spec_name) = psram_decode_extension(
+            &transaction_psram_access.extension_psram_access,
+            &transaction_psram_access.metadata_psram_access,
+            &transaction_psram_access.genesis_hash_bytes_psram_access
+        )?;
+
+        let mut carded = Vec::new();
+        for ext in decoded_extension.iter() {
+            let addition_set = ext.card(0, true, &metadata_specs, &spec_name);
+            if !addition_set.is_empty() {
+                carded.extend_from_slice(&addition_set)
+            }
         }
+        let extensions = carded
+            .into_iter()
+            .map(|card| card.show())
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let (decoded_call, metadata_specs, spec_name) = psram_decode_call(
+            &transaction_psram_access.call_psram_access,
+            &transaction_psram_access.metadata_psram_access,
+        )?;
+
+        let mut unsaved_accounts = Vec::new();
+        let call = decoded_call.card(0, &metadata_specs, &spec_name)
+            .iter()
+            .map(|card| show_card(&mut FreeFlash, card, &self.genesis_hash, &mut unsaved_accounts))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Ok(DecodedTransaction {
+            call,
+            extensions,
+            unsaved_accounts,
+        })
     }
 }
 
 impl Platform for Hardware {
     type HAL = ();
     type Rng<'c> = se_rng::SeRng;
-    type AsWordList = FlashWordList;
+    type AsWordList = FlashWordList<FreeFlash>;
 
     type NfcTransaction = NfcTransactionPsramAccess;
     fn get_wordlist() -> Self::AsWordList {
         FlashWordList::new()
     }
 
+    type FontStorage = FlashFont;
+
+    fn font_storage(&self) -> &Self::FontStorage {
+        &self.font
+    }
+
     fn rng<'b>(_: &'b mut ()) -> Self::Rng<'static> {
         se_rng::SeRng{}
     }
@@ -237,85 +391,97 @@ impl Platform for Hardware {
     }
 
     fn set_transaction(&mut self, transaction: Self::NfcTransaction) {
+        self.genesis_hash = read_from_psram(&transaction.genesis_hash_bytes_psram_access)
+            .try_into()
+            .expect("static size");
+        self.network = find_network(&mut FreeFlash, &self.genesis_hash);
+        self.decoded_transaction = Some(self.decode_transaction(&transaction));
+        self.payload_hash = None;
         self.transaction_psram_access = Some(transaction);
     }
 
+    fn network(&mut self) -> Option<Network> {
+        self.network.as_ref().map(|specs| Network {
+            name: specs.name.clone(),
+            logo: specs.logo,
+        })
+    }
 
-    fn call(&mut self) -> Option<String> {
-        let transaction_psram_access = match self.transaction_psram_access {
-            Some(ref a) => a,
-            None => return None
-        };
 
-        let (decoded_call, specs, spec_name) = psram_decode_call(
-            &transaction_psram_access.call_psram_access,
-            &transaction_psram_access.metadata_psram_access,
-        );
+    fn transaction_error(&mut self) -> Option<TransactionError> {
+        match self.decoded_transaction {
+            Some(Err(DecodeError::MetadataOutdated{ref in_metadata, ref in_transaction})) => Some(TransactionError::MetadataOutdated{
+                in_metadata: in_metadata.clone(),
+                in_transaction: in_transaction.clone(),
+            }),
+            Some(Err(ref e)) if e.is_blind_signable() => Some(TransactionError::Undecodable(decode_error_text(e))),
+            Some(Err(ref e)) => Some(TransactionError::Unsignable(decode_error_text(e))),
+            _ => None,
+        }
+    }
 
-        let carded = decoded_call.card(0, &specs, &spec_name);
-        let call = carded
-            .into_iter()
-            .map(|card| card.show())
-            .collect::<Vec<String>>()
-            .join("\n");
+    fn payload_hash(&mut self) -> [u8; 32] {
+        if self.payload_hash.is_none() {
+            let data_to_sign_psram_access = self.data_to_sign_psram_access().expect("transaction should be stored to hash");
+            self.payload_hash = Some(psram_blake2b_256(&data_to_sign_psram_access));
+        }
+        self.payload_hash.expect("just set")
+    }
 
-        Some(call)
+    fn call(&mut self) -> Option<String> {
+        match self.decoded_transaction {
+            Some(Ok(ref a)) => Some(a.call.clone()),
+            _ => None,
+        }
     }
 
     fn extensions(&mut self) -> Option<String> {
-        let transaction_psram_access = match self.transaction_psram_access {
-            Some(ref a) => a,
-            None => return None
-        };
-        
-        let (decoded_extension, specs, spec_name) = psram_decode_extension(
-            &transaction_psram_access.extension_psram_access,
-            &transaction_psram_access.metadata_psram_access,
-            &transaction_psram_access.genesis_hash_bytes_psram_access
-        );
+        match self.decoded_transaction {
+            Some(Ok(ref a)) => Some(a.extensions.clone()),
+            _ => None,
+        }
+    }
 
-        let mut carded = Vec::new();
-        for ext in decoded_extension.iter() {
-            let addition_set = ext.card(0, true, &specs, &spec_name);
-            if !addition_set.is_empty() {
-                carded.extend_from_slice(&addition_set)
-            }
+    fn unsaved_accounts(&mut self) -> Vec<UnsavedAccount> {
+        match self.decoded_transaction {
+            Some(Ok(ref a)) => a.unsaved_accounts
+                .iter()
+                .map(|(account_id, address)| UnsavedAccount {
+                    account_id: *account_id,
+                    address: address.clone(),
+                })
+                .collect(),
+            _ => Vec::new(),
         }
-        let extensions = carded
-            .into_iter()
-            .map(|card| card.show())
-            .collect::<Vec<String>>()
-            .join("\n");
+    }
 
-        Some(extensions)
+    fn save_contact(&mut self, account_id: [u8; ACCOUNT_ID_LEN], label: String) -> Result<(), String> {
+        let contact = Contact {
+            account_id,
+            label,
+            genesis_hash: None,
+        };
+        store_contact(&mut FreeFlash, &contact).map_err(|e| String::from(address_book_error_text(e)))?;
+
+        // labels appear in shown call only after decoding again
+        if let Some(ref transaction) = self.transaction_psram_access {
+            self.decoded_transaction = Some(self.decode_transaction(transaction));
+        }
+        Ok(())
     }
 
     fn signature(&mut self) -> [u8; 130] {
-        let transaction_psram_access = match self.transaction_psram_access {
-            Some(ref a) => a,
+        let data_to_sign_psram_access = match self.data_to_sign_psram_access() {
+            Some(a) => a,
             None => panic!("qr generation failed")
         };
-        
-        let data_to_sign_psram_access = PsramAccess {
-            start_address: transaction_psram_access.call_psram_access.start_address,
-            total_len:
-                transaction_psram_access.call_psram_access.total_len
-                + &transaction_psram_access.extension_psram_access.total_len
-        };
-        let data_to_sign = read_from_psram(&data_to_sign_psram_access);
+        let data_to_sign = psram_signing_payload(&data_to_sign_psram_access);
 
         let signature = self.pair()
             .expect("entropy should be stored at this point")
             .sign_external_rng(&data_to_sign, &mut Self::rng(&mut ()));
 
-        let mut signature_with_id: [u8; 65] = [1; 65];
-        signature_with_id[1..].copy_from_slice(&signature.0);
-        let signature_with_id_bytes = hex::encode(signature_with_id)
-            .into_bytes()
-            .try_into()
-            .expect("static length");
-
-        signature_with_id_bytes
+        encode_signature(&signature)
     }
 
     fn address(&mut self) -> &[u8; 76] {
@@ -326,6 +492,39 @@ impl Platform for Hardware {
         }
     }
 
+    fn allow_blind_signing(&self) -> bool {
+        self.settings.allow_blind_signing
+    }
+
+    fn set_allow_blind_signing(&mut self, allow: bool) {
+        self.settings.allow_blind_signing = allow;
+        if let Err(_) = store_settings(&mut FreeFlash, &self.settings) {
+            panic!("Failed to save settings");
+        }
+    }
+
+    fn language(&self) -> Language {
+        self.settings.language
+    }
+
+    fn set_language(&mut self, language: Language) {
+        self.settings.language = language;
+        if let Err(_) = store_settings(&mut FreeFlash, &self.settings) {
+            panic!("Failed to save settings");
+        }
+    }
+
+    fn orientation(&self) -> Orientation {
+        self.settings.orientation
+    }
+
+    fn set_orientation(&mut self, orientation: Orientation) {
+        self.settings.orientation = orientation;
+        if let Err(_) = store_settings(&mut FreeFlash, &self.settings) {
+            panic!("Failed to save settings");
+        }
+    }
+
 }
 
 lazy_static! {
@@ -341,7 +540,8 @@ lazy_static! {
 
 
 
-pub fn convert(touch_data: [u8; LEN_NUM_TOUCHES]) -> Option<Point> {
+/// Touch point in UI coordinates
+pub fn convert(touch_data: [u8; LEN_NUM_TOUCHES], orientation: Orientation) -> Option<Point> {
     if touch_data[0] == 1 {
         let detected_y = (((touch_data[1] as u16 & 0b00001111) << 8) | touch_data[2] as u16) as i32;
         let detected_x = (((touch_data[3] as u16 & 0b00001111) << 8) | touch_data[4] as u16) as i32;
@@ -350,12 +550,12 @@ pub fn convert(touch_data: [u8; LEN_NUM_TOUCHES]) -> Option<Point> {
         let touch_as_point2 = Point2::new(touch.x as f32, touch.y as f32);
         let display_as_point2 = AFFINE_MATRIX.transform_point(&touch_as_point2);
 
-        Some(
+        Some(orientation.from_panel(
             Point {
                 x: display_as_point2.coords[0] as i32,
                 y: display_as_point2.coords[1] as i32,
             }
-        )
+        ))
     } else { None }
 }
 